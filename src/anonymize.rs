@@ -0,0 +1,49 @@
+use sha2::{Digest, Sha256};
+
+use crate::{checksum::to_hex, config::Secret, models::PredecessorMatch};
+
+/// Replaces player ids with salted hashes and drops player names, for sharing datasets. The same id always
+/// becomes the same pseudonym under the same salt, so players can still be followed across matches and runs
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    salt: Secret,
+}
+
+impl Anonymizer {
+    pub fn new(salt: Secret) -> Self {
+        Anonymizer { salt }
+    }
+
+    /// Hex of the first 16 bytes of SHA-256(salt, id). Empty ids (kills by towers and minions) stay empty
+    pub fn pseudonym(&self, player_id: &str) -> String {
+        if player_id.is_empty() {
+            return String::new();
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.expose().as_bytes());
+        hasher.update([0]);
+        hasher.update(player_id.as_bytes());
+        to_hex(&hasher.finalize()[..16])
+    }
+
+    /// Rewrites every player id in the match, including the ones in kills and destructions, so they all still
+    /// point at the same players
+    pub fn anonymize_match(&self, m: &mut PredecessorMatch) {
+        let replace = |id: &mut String| *id = self.pseudonym(id);
+
+        for p in &mut m.player_data {
+            replace(&mut p.player_id);
+            p.player_name = None;
+        }
+        for k in &mut m.hero_kills {
+            replace(&mut k.killed_player_id);
+            replace(&mut k.killer_player_id);
+        }
+        for s in &mut m.structure_destructions {
+            replace(&mut s.destruction_player_id);
+        }
+        for o in &mut m.objective_kills {
+            replace(&mut o.killer_player_id);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, Timelike};
+use flate2::{write::GzEncoder, Crc};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    cache::is_cache_file, config::Compression, error::RipperError, output::write_atomically,
+    windows::parse_match_file_name,
+};
+
+/// Zip entry names always use `/`, whatever the platform's separator is
+fn zip_entry_name(relative_path: &Path) -> Option<String> {
+    let parts = relative_path
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(parts.join("/"))
+}
+
+/// Threads for compressing zip entries, `threads` of them or one per core. Archive inside `ThreadPool::install`
+/// and the compression in `zip_matches` and `append_zip_matches` runs on these instead of rayon's global pool
+pub fn compression_pool(threads: Option<usize>) -> Result<ThreadPool, RipperError> {
+    let mut builder = ThreadPoolBuilder::new().thread_name(|i| format!("compress-{}", i));
+    if let Some(threads) = threads {
+        builder = builder.num_threads(threads);
+    }
+    Ok(builder.build()?)
+}
+
+/// Files compressed in parallel at once. Their compressed bytes are held in memory until they're written
+const ZIP_CHUNK_SIZE: usize = 64;
+
+/// Every file under the output folder with its zip entry name, in a stable (sorted) order
+fn walk_output_files(output_dir: &Path) -> Result<Vec<(PathBuf, String)>, RipperError> {
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(output_dir).sort_by_file_name() {
+        let entry = entry?;
+        // Caches can be rebuilt from the match files, so they aren't worth the space in the archive
+        if !entry.file_type().is_file() || is_cache_file(entry.path()) {
+            continue;
+        }
+        let name = entry
+            .path()
+            .strip_prefix(output_dir)
+            .expect("WalkDir only yields paths under the folder it was given");
+        let Some(entry_name) = zip_entry_name(name) else {
+            warn!("Skipping file with a non UTF-8 name: {:?}", name);
+            continue;
+        };
+        files.push((entry.into_path(), entry_name));
+    }
+
+    Ok(files)
+}
+
+/// `epoch` as a zip timestamp, in UTC since zips don't record a time zone. None outside the years a zip can hold
+/// (1980 to 2107)
+pub fn zip_time(epoch: u64) -> Option<zip::DateTime> {
+    let time = chrono::DateTime::from_timestamp(i64::try_from(epoch).ok()?, 0)?;
+    zip::DateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+/// Match files are stamped with the end epoch in their name, so an extracted archive sorts by time. Anything
+/// else, or an epoch a zip can't hold, keeps the fixed timestamp from `entry_options`
+fn entry_time(entry_name: &str) -> Option<zip::DateTime> {
+    let file_name = entry_name.rsplit('/').next()?;
+    let (_, end_epoch, _) = parse_match_file_name(file_name)?;
+    zip_time(end_epoch)
+}
+
+/// Compresses one file into a single-entry zip in memory, ready to be raw-copied into the real archive
+fn compress_entry(
+    path: &Path,
+    entry_name: &str,
+    mut options: FileOptions,
+) -> Result<Vec<u8>, RipperError> {
+    if let Some(time) = entry_time(entry_name) {
+        options = options.last_modified_time(time);
+    }
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(entry_name, options)?;
+    io::copy(&mut BufReader::new(File::open(path)?), &mut zip)?;
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Fixed timestamps and permissions, so zipping the same files twice gives the same bytes. Match files get the
+/// time from their name instead, see `entry_time`
+fn entry_options(compression: Compression) -> FileOptions {
+    FileOptions::default()
+        .compression_method(compression.into())
+        .last_modified_time(zip::DateTime::default())
+        .unix_permissions(0o644)
+}
+
+/// Compresses `files` into `zip` in order. Compression runs in parallel across files, but entries are written
+/// one at a time, since ZipWriter can only be used from one thread
+fn write_entries<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    files: &[(PathBuf, String)],
+    options: FileOptions,
+) -> Result<(), RipperError> {
+    for chunk in files.chunks(ZIP_CHUNK_SIZE) {
+        let compressed = chunk
+            .par_iter()
+            .map(|(path, entry_name)| compress_entry(path, entry_name, options))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for ((_, entry_name), bytes) in chunk.iter().zip(compressed) {
+            info!("Adding file: {:?}", entry_name);
+            let mut single = ZipArchive::new(Cursor::new(bytes))?;
+            zip.raw_copy_file(single.by_index_raw(0)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Entries are written in sorted order, so the archive comes out the same from one run to the next for the same
+/// files
+pub fn zip_matches(
+    output_dir: &Path,
+    zip_path: &Path,
+    compression: Compression,
+) -> Result<(), RipperError> {
+    let files = walk_output_files(output_dir)?;
+    info!("Zipping {} matches", files.len());
+
+    let output_file = BufWriter::new(File::create(zip_path)?);
+    let mut zip = ZipWriter::new(output_file);
+    write_entries(&mut zip, &files, entry_options(compression))?;
+    zip.finish()?.flush()?;
+
+    info!("Finished zipping matches to {:?}", zip_path);
+    Ok(())
+}
+
+/// CRC-32 and length of a file, to compare against what a zip entry recorded for it
+fn file_crc(path: &Path) -> Result<(u32, u64), RipperError> {
+    let mut crc = Crc::new();
+    let mut reader = BufReader::new(File::open(path)?);
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        crc.update(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    Ok((crc.sum(), crc.amount() as u64))
+}
+
+/// --append-zip: brings an existing zip up to date with the output folder without recompressing what's already
+/// in it. Files the zip already holds unchanged are skipped, and when that's all of its entries the new files
+/// are appended to the end of it in place.
+///
+/// A zip entry can't be replaced in place, so if any entry was changed or removed since (the manifest and
+/// checksums usually are) the zip is rewritten instead: unchanged entries are copied across still compressed and
+/// only the rest are compressed again. No zip at `zip_path` yet is the same as `zip_matches`
+pub fn append_zip_matches(
+    output_dir: &Path,
+    zip_path: &Path,
+    compression: Compression,
+) -> Result<(), RipperError> {
+    if !zip_path.exists() {
+        return zip_matches(output_dir, zip_path, compression);
+    }
+
+    let files = walk_output_files(output_dir)?;
+    let mut existing = ZipArchive::new(BufReader::new(File::open(zip_path)?))?;
+    let mut archived = HashMap::new();
+    for index in 0..existing.len() {
+        let entry = existing.by_index_raw(index)?;
+        archived.insert(
+            entry.name().to_owned(),
+            (index, entry.crc32(), entry.size()),
+        );
+    }
+
+    // Index into the existing zip of every file it already holds unchanged
+    let mut unchanged = HashMap::new();
+    for (path, entry_name) in &files {
+        if let Some(&(index, crc, size)) = archived.get(entry_name) {
+            if file_crc(path)? == (crc, size) {
+                unchanged.insert(entry_name.clone(), index);
+            }
+        }
+    }
+    let new_files: Vec<(PathBuf, String)> = files
+        .iter()
+        .filter(|(_, entry_name)| !unchanged.contains_key(entry_name))
+        .cloned()
+        .collect();
+    info!(
+        "{} matches already zipped, {} to add",
+        unchanged.len(),
+        new_files.len()
+    );
+    let options = entry_options(compression);
+
+    if unchanged.len() == existing.len() {
+        drop(existing);
+        if !new_files.is_empty() {
+            let file = File::options().read(true).write(true).open(zip_path)?;
+            let mut zip = ZipWriter::new_append(file)?;
+            write_entries(&mut zip, &new_files, options)?;
+            zip.finish()?.sync_all()?;
+        }
+    } else {
+        write_atomically(zip_path, |output_file| {
+            let mut zip = ZipWriter::new(output_file);
+            // Kept in the same sorted order as `zip_matches`, copying whatever can be copied as is
+            for chunk in
+                files.chunk_by(|a, b| unchanged.contains_key(&a.1) == unchanged.contains_key(&b.1))
+            {
+                if unchanged.contains_key(&chunk[0].1) {
+                    for (_, entry_name) in chunk {
+                        zip.raw_copy_file(existing.by_index_raw(unchanged[entry_name])?)?;
+                    }
+                } else {
+                    write_entries(&mut zip, chunk, options)?;
+                }
+            }
+            zip.finish()?;
+            Ok(())
+        })?;
+    }
+
+    info!("Finished zipping matches to {:?}", zip_path);
+    Ok(())
+}
+
+/// Streams the output folder into a .tar.gz, with the same entry names and order as `zip_matches`
+pub fn tar_gz_matches(output_dir: &Path, archive_path: &Path) -> Result<(), RipperError> {
+    let files = walk_output_files(output_dir)?;
+    info!("Archiving {} matches", files.len());
+
+    let encoder = GzEncoder::new(
+        BufWriter::new(File::create(archive_path)?),
+        flate2::Compression::default(),
+    );
+    let mut tar = tar::Builder::new(encoder);
+    // Leaves out timestamps and owners, same as the fixed options in `zip_matches`
+    tar.mode(tar::HeaderMode::Deterministic);
+
+    for (path, entry_name) in &files {
+        info!("Adding file: {:?}", entry_name);
+        tar.append_path_with_name(path, entry_name)?;
+    }
+
+    tar.into_inner()?.finish()?.flush()?;
+
+    info!("Finished archiving matches to {:?}", archive_path);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::{config::BackoffStrategy, BASE_RETRY_DELAY, MAX_RETRY_DELAY};
+
+/// How long to wait before each retry. The default is 500ms, 1s, 2s, 4s, ... capped at 30s, plus up to 50%
+/// random jitter so the workers don't retry in lockstep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    pub strategy: BackoffStrategy,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Growth per retry for exponential, and the upper bound on the next delay (as a multiple of the last one)
+    /// for decorrelated
+    pub multiplier: f64,
+    /// Random extra on top of fixed and exponential delays, as a fraction of the delay. Decorrelated is random
+    /// through and through, so this doesn't apply to it
+    pub jitter: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            strategy: BackoffStrategy::Exponential,
+            base_delay: BASE_RETRY_DELAY,
+            max_delay: MAX_RETRY_DELAY,
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl Backoff {
+    /// The wait before retry number `attempt` (starting at 1), given the wait before the last one (zero before
+    /// the first). Random parts come from `rng`, so a seeded one gives the same delays every time
+    pub fn delay<R: Rng>(&self, attempt: u32, previous: Duration, rng: &mut R) -> Duration {
+        let delay = match self.strategy {
+            BackoffStrategy::Fixed => self.base_delay,
+            BackoffStrategy::Exponential => {
+                let exponent = attempt.saturating_sub(1).min(64) as i32;
+                self.base_delay
+                    .mul_f64(self.multiplier.powi(exponent).min(u32::MAX as f64))
+                    .min(self.max_delay)
+            }
+            // "Decorrelated jitter": anywhere between the base and a multiple of the last delay, so retries
+            // spread out without all following the same curve
+            BackoffStrategy::Decorrelated => {
+                let upper = previous.max(self.base_delay).mul_f64(self.multiplier);
+                let delay = if upper > self.base_delay {
+                    rng.gen_range(self.base_delay..=upper)
+                } else {
+                    self.base_delay
+                };
+                return delay.min(self.max_delay);
+            }
+        };
+
+        let max_jitter = delay.mul_f64(self.jitter);
+        if max_jitter.is_zero() {
+            return delay;
+        }
+        delay + rng.gen_range(Duration::ZERO..=max_jitter)
+    }
+}
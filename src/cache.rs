@@ -0,0 +1,45 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::CacheFormat, error::RipperError, models::PredecessorMatch, output::write_atomically,
+};
+
+/// Extension of the cache file kept next to a match file, e.g. `{start}-{end}.bin` for `{start}-{end}.json`
+pub const CACHE_EXTENSION: &str = "bin";
+
+/// Where the cache for the match file at `path` lives
+pub fn cache_path(path: &Path) -> PathBuf {
+    path.with_extension(CACHE_EXTENSION)
+}
+
+pub fn is_cache_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == CACHE_EXTENSION)
+}
+
+/// Writes `matches` to the cache next to the match file at `path`. The match file stays the real output,
+/// the cache only exists so reports don't have to parse JSON again
+pub fn write_cache(
+    path: &Path,
+    format: CacheFormat,
+    matches: &[PredecessorMatch],
+) -> Result<(), RipperError> {
+    match format {
+        CacheFormat::Bincode => write_atomically(&cache_path(path), |file| {
+            Ok(bincode::serialize_into(file, matches)?)
+        }),
+    }
+}
+
+/// Reads the cache next to the match file at `path`, if there is one
+pub fn read_cache(path: &Path) -> Result<Option<Vec<PredecessorMatch>>, RipperError> {
+    let path = cache_path(path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = BufReader::new(File::open(path)?);
+    Ok(Some(bincode::deserialize_from(file)?))
+}
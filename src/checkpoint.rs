@@ -0,0 +1,54 @@
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::RipperError, output::write_atomically, quota::QuotaBucket};
+
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Where each in-flight work window had got to when the run was shut down, kept at `{output_dir}/checkpoint.json`
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Checkpoint {
+    pub windows: Vec<WindowCheckpoint>,
+    /// Requests made under --daily-quota in the last 24 hours, so a restart doesn't reset the budget
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quota_usage: Vec<QuotaBucket>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WindowCheckpoint {
+    pub start_epoch: u64,
+    pub end_epoch: u64,
+    /// The epoch the window would have queried next. Everything before it has been saved
+    pub current_epoch: u64,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint from the output folder, or an empty one if there isn't one
+    pub fn load(output_dir: &Path) -> Result<Self, RipperError> {
+        match File::open(output_dir.join(CHECKPOINT_FILE)) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Checkpoint::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, output_dir: &Path) -> Result<(), RipperError> {
+        write_atomically(&output_dir.join(CHECKPOINT_FILE), |file| {
+            serde_json::to_writer_pretty(file, self)?;
+            Ok(())
+        })
+    }
+
+    /// Removes a stale checkpoint once a run gets through without being interrupted, and with no quota to keep track of
+    pub fn clear(output_dir: &Path) -> Result<(), RipperError> {
+        match std::fs::remove_file(output_dir.join(CHECKPOINT_FILE)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
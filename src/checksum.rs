@@ -0,0 +1,118 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{
+    error::RipperError,
+    manifest::{Manifest, ManifestEntry},
+    output::write_atomically,
+};
+
+/// `sha256sum -c` compatible list of every file in the manifest, kept next to it in the output folder
+pub const CHECKSUMS_FILE: &str = "checksums.txt";
+
+/// Passes writes through to `inner` while hashing them, so a file's checksum comes from the bytes as they
+/// were written rather than from reading the file back
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        HashingWriter {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub fn finish(self) -> String {
+        to_hex(&self.hasher.finalize())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn hash_file(path: &Path) -> Result<String, RipperError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Rewrites checksums.txt from the manifest. Entries from before checksums existed are left out
+pub fn write_checksums(output_dir: &Path, manifest: &Manifest) -> Result<(), RipperError> {
+    write_atomically(&output_dir.join(CHECKSUMS_FILE), |file| {
+        for entry in &manifest.files {
+            if let Some(sha256) = &entry.sha256 {
+                writeln!(file, "{}  {}", sha256, entry.file)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Adds a line to the end of checksums.txt for each newly saved file, without reading or rewriting what's there.
+/// A file saved again under the same name keeps its old line until the next `write_checksums`
+pub fn append_checksums(output_dir: &Path, entries: &[ManifestEntry]) -> Result<(), RipperError> {
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(output_dir.join(CHECKSUMS_FILE))?;
+    let mut file = BufWriter::new(file);
+    for entry in entries {
+        if let Some(sha256) = &entry.sha256 {
+            writeln!(file, "{}  {}", sha256, entry.file)?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Re-hashes every file in the manifest and logs the ones that are missing or don't match.
+/// Returns how many failed
+pub fn verify_checksums(output_dir: &Path) -> Result<usize, RipperError> {
+    let manifest = Manifest::load(output_dir)?;
+    let mut checked = 0;
+    let mut failed = 0;
+
+    for ManifestEntry { file, sha256, .. } in &manifest.files {
+        let Some(expected) = sha256 else {
+            continue;
+        };
+        checked += 1;
+
+        let path = output_dir.join(file);
+        if !path.exists() {
+            warn!("{} is missing", file);
+            failed += 1;
+            continue;
+        }
+        let actual = hash_file(&path)?;
+        if &actual != expected {
+            warn!("{} has checksum {}, expected {}", file, actual, expected);
+            failed += 1;
+        }
+    }
+
+    info!("{} of {} files failed their checksum", failed, checked);
+    Ok(failed)
+}
@@ -0,0 +1,101 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+use tracing::error;
+
+use crate::error::RipperError;
+
+/// Shared by every worker, so a backend that's down altogether pauses the whole run instead of every window
+/// burning through its retries and failing at once. After `threshold` connection failures in a row the workers
+/// pause for `cooldown`, and once that has happened `max_pauses` times without the backend answering in between,
+/// the run is given up on
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    max_pauses: u32,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    pauses: u32,
+    paused_until: Option<Instant>,
+    gave_up: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration, max_pauses: u32) -> Self {
+        CircuitBreaker {
+            threshold,
+            cooldown,
+            max_pauses,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Waits out a pause if there is one. Fails once the backend has been given up on
+    pub async fn wait(&self) -> Result<(), RipperError> {
+        loop {
+            let paused_until = {
+                let state = self.state.lock().unwrap();
+                if state.gave_up {
+                    return Err(RipperError::BackendUnavailable {
+                        pauses: state.pauses,
+                    });
+                }
+                match state.paused_until {
+                    Some(until) if until > Instant::now() => until,
+                    _ => return Ok(()),
+                }
+            };
+            tokio::time::sleep_until(paused_until).await;
+        }
+    }
+
+    /// The backend answered, even if only with an error status, so it isn't down
+    pub fn record_response(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.pauses = 0;
+    }
+
+    pub fn record_connection_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        // Requests that were already in flight when the pause started don't count towards the next one
+        if state.gave_up
+            || state
+                .paused_until
+                .is_some_and(|until| until > Instant::now())
+        {
+            return;
+        }
+
+        state.consecutive_failures += 1;
+        if state.consecutive_failures < self.threshold {
+            return;
+        }
+        state.consecutive_failures = 0;
+
+        if state.pauses >= self.max_pauses {
+            state.gave_up = true;
+            error!(
+                "Backend still unreachable after {} pause(s), giving up on the run",
+                state.pauses
+            );
+            return;
+        }
+        state.pauses += 1;
+        state.paused_until = Some(Instant::now() + self.cooldown);
+        error!(
+            "Backend looks to be down ({} connection failures in a row), pausing every worker for {:?} ({} of {})",
+            self.threshold, self.cooldown, state.pauses, self.max_pauses
+        );
+    }
+
+    /// Whether the backend was given up on
+    pub fn gave_up(&self) -> bool {
+        self.state.lock().unwrap().gave_up
+    }
+}
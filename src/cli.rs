@@ -0,0 +1,590 @@
+use std::{convert::Infallible, net::SocketAddr, num::NonZeroU32, path::PathBuf};
+
+use clap::{builder::RangedU64ValueParser, error::ErrorKind, CommandFactory, Parser, Subcommand};
+
+use pred_ripper::{
+    fetch::{parse_base_url, parse_header, parse_proxy},
+    report::ReportKind,
+    time::{parse_epoch, parse_until},
+    ArchiveFormat, BackoffStrategy, CacheFormat, Compression, Config, GameMode, LogFormat,
+    OutputFormat, Partition, Region, RipperError, SchemaCheck, Secret, COMPACT_TARGET_BYTES,
+};
+
+/// Every setting here can also be put in `ripper.toml`. Flags given on the command line win over the file
+#[derive(Parser, Debug)]
+#[command(version, about = "Scrapes the Predecessor API for matches")]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Config file to load. Defaults to `ripper.toml` in the current directory, if there is one
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Where to start collecting from. Either a unix epoch or a UTC time like "2023-04-01 00:00:00" [default: 1669882894]
+    #[arg(long, value_parser = parse_epoch)]
+    pub start_epoch: Option<u64>,
+
+    /// Where to stop collecting. Same formats as --start-epoch, defaults to now
+    #[arg(long, value_parser = parse_epoch)]
+    pub end_epoch: Option<u64>,
+
+    /// Size of each work window in seconds [default: 3600]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub window_size: Option<u64>,
+
+    /// Collect the most recent work windows first, so stopping early still leaves the newest matches
+    #[arg(long)]
+    pub newest_first: bool,
+
+    /// Friendlier --start-epoch: a date like 2023-01-01 (from midnight UTC) or a time like "2023-01-01 12:00:00"
+    #[arg(long, value_parser = parse_epoch, conflicts_with = "start_epoch")]
+    pub since: Option<u64>,
+
+    /// Friendlier --end-epoch: a date like 2023-02-01 (up to the end of that day, UTC) or a time
+    #[arg(long, value_parser = parse_until, conflicts_with = "end_epoch")]
+    pub until: Option<u64>,
+
+    /// Seconds to skip ahead within a work window when a request comes back empty [default: 3600]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub empty_step: Option<u64>,
+
+    /// After several empty responses in a row, jump ahead further each time (doubling the step up to --max-stride).
+    /// Cuts down on requests over sparse history
+    #[arg(long)]
+    pub adaptive_window: bool,
+
+    /// Largest jump in seconds --adaptive-window will make after an empty response [default: 3600]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_stride: Option<u64>,
+
+    /// Number of work windows fetched concurrently [default: number of cores, at most 32]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub pool_size: Option<u64>,
+
+    /// Fetch each work window as this many sub-ranges at once, then merge them into one file. Speeds up dense
+    /// hours at the cost of holding a window's matches in memory until it's done [default: 1]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub window_splits: Option<u32>,
+
+    /// Give up on any single response bigger than this many bytes instead of reading it all into memory, which
+    /// with many workers at once can run the machine out of it. The endpoint only takes a start epoch, so there's
+    /// no narrower request to make instead: the window fails, and shows up in the failed windows to look at
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_window_bytes: Option<u64>,
+
+    /// Keep the existing matches folder and only fetch windows that aren't already downloaded
+    ///
+    /// A window counts as downloaded when files recorded in the manifest with at least one match, and that still
+    /// parse, cover it. Windows that only saved zero matches are fetched again in case matches arrived later
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Keep the existing matches folder and only fetch matches newer than the newest file in it. Meant for cron jobs
+    #[arg(long)]
+    pub update: bool,
+
+    /// Collect the range one request at a time, in order, instead of in parallel work windows. `cursor.json` in
+    /// the output folder records the epoch everything before which is saved, and a run with it there picks up
+    /// from it. Slower, but never leaves a gap behind the cursor
+    #[arg(long, conflicts_with_all = ["resume", "update", "newest_first", "daily_quota"])]
+    pub cursor: bool,
+
+    /// Don't download anything, just print the work windows that have no output in the matches folder
+    #[arg(long, conflicts_with = "resume")]
+    pub verify: bool,
+
+    /// Don't download or write anything, just log the work windows a run would collect
+    #[arg(long, conflicts_with_all = ["verify", "zip_only"])]
+    pub dry_run: bool,
+
+    /// Don't download anything, just build a report from the files already in the output folder
+    #[arg(long, value_enum, conflicts_with_all = ["verify", "zip_only", "dry_run", "resume"])]
+    pub report: Option<ReportKind>,
+
+    /// Don't download anything, just check the matches already in the output folder for inconsistencies
+    #[arg(long, conflicts_with_all = ["verify", "zip_only", "dry_run", "resume", "report"])]
+    pub validate: bool,
+
+    /// Don't download anything, just re-hash the files in the output folder and report any that no longer match their recorded checksum
+    #[arg(long, conflicts_with_all = ["verify", "zip_only", "dry_run", "resume", "report", "validate"])]
+    pub verify_checksums: bool,
+
+    /// Folder the match files are written to [default: matches]
+    #[arg(long, global = true)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Where to write the archive of the output folder. Defaults to `{output_dir}.zip` (or `.tar.gz`)
+    #[arg(long)]
+    pub zip_path: Option<PathBuf>,
+
+    /// Skip zipping the output folder at the end of the run
+    #[arg(long)]
+    pub no_zip: bool,
+
+    /// Add new files to the existing zip instead of zipping the whole output folder again. Files it already
+    /// holds unchanged aren't compressed again. Makes the zip if there isn't one yet. A tar.gz is always written
+    /// from scratch
+    #[arg(long, conflicts_with = "no_zip")]
+    pub append_zip: bool,
+
+    /// Don't fetch anything, just zip an existing output folder
+    #[arg(long, conflicts_with_all = ["no_zip", "resume", "verify"])]
+    pub zip_only: bool,
+
+    /// Archive the output folder as a zip or a tar.gz [default: zip]
+    #[arg(long, value_enum)]
+    pub archive_format: Option<ArchiveFormat>,
+
+    /// Compression used for the files in the zip [default: deflate]
+    #[arg(long, value_enum)]
+    pub compression: Option<Compression>,
+
+    /// Threads used to compress files for the zip, separate from --pool-size. Lower it to leave cores free for
+    /// other work on a shared machine [default: number of cores]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub compress_threads: Option<u64>,
+
+    /// How long a single request to the backend may take before it's abandoned and retried [default: 30]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub request_timeout_secs: Option<u64>,
+
+    /// Connection failures in a row, across every worker, before the whole run pauses for --outage-cooldown-secs
+    /// [default: 20]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub outage_threshold: Option<u32>,
+
+    /// How long the run pauses when the backend looks to be down [default: 60]
+    #[arg(long)]
+    pub outage_cooldown_secs: Option<u64>,
+
+    /// Pauses in a row, without the backend answering in between, before the run fails with an error [default: 3]
+    #[arg(long)]
+    pub outage_max_pauses: Option<u32>,
+
+    /// Keep running after catching up to now, polling for new matches with the --update logic, and never make more
+    /// than this many requests in any 24 hours. Usage is kept in the checkpoint so restarts don't reset it
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub daily_quota: Option<u64>,
+
+    /// Seconds between checks for new matches with --daily-quota once caught up [default: 900]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub poll_interval_secs: Option<u64>,
+
+    /// Retries for a request that failed with a connection error, timeout, 5xx or 429 before its work window
+    /// is given up on. 0 never retries [default: 5]
+    #[arg(long)]
+    pub max_retries: Option<u32>,
+
+    /// How the wait between retries grows. A 429 with Retry-After is always waited out as asked [default: exponential]
+    #[arg(long, value_enum)]
+    pub backoff: Option<BackoffStrategy>,
+
+    /// Shortest wait between retries, in milliseconds [default: 500]
+    #[arg(long)]
+    pub retry_base_delay_ms: Option<u64>,
+
+    /// Longest wait between retries, in milliseconds, before jitter [default: 30000]
+    #[arg(long)]
+    pub retry_max_delay_ms: Option<u64>,
+
+    /// How much the wait grows with every retry, at least 1 [default: 2]
+    #[arg(long)]
+    pub retry_multiplier: Option<f64>,
+
+    /// Cap on requests a second, shared by every worker. Unlimited by default
+    #[arg(long)]
+    pub max_rps: Option<NonZeroU32>,
+
+    /// User-Agent sent with every request. Defaults to reqwest's
+    #[arg(long)]
+    pub user_agent: Option<String>,
+
+    /// Extra header sent with every request, as KEY=VALUE. Can be given more than once
+    #[arg(long = "header", value_name = "KEY=VALUE", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
+    /// Sent as a bearer token with every request
+    #[arg(long, env = "RIPPER_API_TOKEN", hide_env_values = true, value_parser = parse_secret)]
+    pub api_token: Option<Secret>,
+
+    /// Send every request through this proxy, e.g. http://proxy:8080 or socks5://127.0.0.1:1080.
+    /// Without it the usual HTTP_PROXY/HTTPS_PROXY environment variables are honored
+    #[arg(long, value_parser = parse_proxy)]
+    pub proxy: Option<String>,
+
+    /// Replace player ids with salted hashes and drop player names before anything is saved, for sharing datasets.
+    /// Ids in kills and destructions are replaced the same way, so they still line up
+    #[arg(long)]
+    pub anonymize: bool,
+
+    /// Salt for --anonymize. Use the same one every run for players to keep the same pseudonyms. Random if not given
+    #[arg(long, env = "RIPPER_ANONYMIZE_SALT", hide_env_values = true, value_parser = parse_secret)]
+    pub anonymize_salt: Option<Secret>,
+
+    /// Log any fields in the responses that the models don't know about, a sign the API has changed.
+    /// With `fail` the work window is abandoned too. Off by default
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "warn")]
+    pub strict_schema: Option<SchemaCheck>,
+
+    /// Also save every response body untouched to `{output_dir}/raw/{epoch}.json`, before it's parsed.
+    /// Useful for reproducing parse failures. Off by default since it roughly doubles disk use
+    #[arg(long)]
+    pub keep_raw: bool,
+
+    /// How many fetched batches can queue up waiting to be written before fetching pauses [default: 32]
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub write_buffer: Option<usize>,
+
+    /// Stop after roughly this many matches. Windows run in parallel, so the total can overshoot by a few batches
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub limit: Option<usize>,
+
+    /// Format of the match files [default: json]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Indent json files so they're readable by hand. They end up several times bigger
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// Split the output folder into subfolders, e.g. `matches/2023/04/05/` for date or `matches/NAEAST/` for region
+    #[arg(long, value_enum)]
+    pub partition: Option<Partition>,
+
+    /// Gzip each match file as it's written, e.g. `{start}-{end}.json.gz`. Saves disk during the run itself,
+    /// not just in the final archive
+    #[arg(long)]
+    pub gzip_files: bool,
+
+    /// Write every match as a line of NDJSON to this one file instead of a file per batch, whatever --format is.
+    /// Lines are in the order batches finish, not end time order. Can't be resumed, so it's always started over
+    #[arg(long, conflicts_with_all = ["resume", "update", "daily_quota"])]
+    pub single_file: Option<PathBuf>,
+
+    /// Split a batch that would make a file bigger than this into `{start}-{end}.part0.json`, `.part1.json`, ...
+    /// Sizes are measured as compact JSON, so other formats only roughly keep to it
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_file_bytes: Option<u64>,
+
+    /// Write every match to a file of its own, `{match_id}.json` (or whatever --format is), instead of a file per
+    /// batch. Saving a match again overwrites its file. --resume, --report and stats find these through the manifest,
+    /// and compact leaves them alone
+    #[arg(long, conflicts_with_all = ["single_file", "max_file_bytes"])]
+    pub split_by_match: bool,
+
+    /// Also write each batch to a `.bin` cache next to its match file. --report reads the cache when it's there,
+    /// which is much faster than parsing JSON again
+    #[arg(long, value_enum)]
+    pub cache_format: Option<CacheFormat>,
+
+    /// Also insert every saved match into this SQLite database
+    #[cfg(feature = "sqlite")]
+    #[arg(long)]
+    pub sqlite: Option<PathBuf>,
+
+    /// Only save matches from this region, e.g. NAEAST. Can be given more than once
+    #[arg(long = "region", value_name = "REGION", value_parser = Region::parse_known)]
+    pub regions: Vec<Region>,
+
+    /// Only save matches with this game mode. Can be given more than once
+    #[arg(long = "game-mode", value_name = "MODE", value_parser = GameMode::parse_known)]
+    pub game_modes: Vec<GameMode>,
+
+    /// Only save matches that lasted at least this many seconds, e.g. 900 to leave out early surrenders
+    #[arg(long)]
+    pub min_duration: Option<u64>,
+
+    /// Only save matches that lasted at most this many seconds
+    #[arg(long)]
+    pub max_duration: Option<u64>,
+
+    /// Backend to fetch matches from, e.g. a staging server or a local mock. The epoch is appended as the
+    /// last path segment. Defaults to the public backend
+    #[arg(long, value_parser = parse_base_url)]
+    pub base_url: Option<String>,
+
+    /// Don't draw the progress bar. It's already left out when stderr isn't a terminal
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// Format of the log lines on stderr (and in --log-file) [default: text]
+    #[arg(long, global = true, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Also write logs to this file. Appended to if it already exists
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Also write the end-of-run summary to summary.json in the output folder
+    #[arg(long)]
+    pub summary: bool,
+
+    /// Serve Prometheus metrics (requests, retries, errors, matches and bytes saved, active workers) on this
+    /// address while the run is going, e.g. 127.0.0.1:9100
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Create a Unix socket here and send a line of JSON to everyone connected to it every time a work window
+    /// finishes: its epochs and match count, plus running totals. The run goes ahead without it if it can't be made
+    #[arg(long)]
+    pub status_socket: Option<PathBuf>,
+
+    /// Exit with a non-zero status if any work window failed. Without it, failures are only logged
+    #[arg(long)]
+    pub fail_on_error: bool,
+}
+
+/// The stages of a run on their own, and jobs other than collecting matches. Without one, a normal run (or one of
+/// the mode flags) happens. Only --config, --output-dir and the logging flags can also go after the subcommand
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Collect matches into --output-dir, then archive it unless --no-zip is given. The same as no subcommand
+    Fetch,
+    /// Archive an existing output folder without fetching anything. The same as --zip-only
+    Zip,
+    /// Print totals for the matches already in the output folder as JSON: matches, bytes, and matches per region
+    /// and game mode
+    Stats,
+    /// Combine several output folders into one, keeping a single copy of every match. Written in --format,
+    /// which has to be json or ndjson
+    Merge {
+        /// Output folders to merge. When the same match differs between them, the one listed first wins
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Folder to write the merged matches to
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Merge runs of small match files in the output folder into bigger ones, keeping a single copy of every
+    /// match. Written in --format, which has to be json or ndjson
+    Compact {
+        /// Size in bytes the merged files are kept under
+        #[arg(long, default_value_t = COMPACT_TARGET_BYTES, value_parser = clap::value_parser!(u64).range(1..))]
+        target_bytes: u64,
+    },
+    /// Run the responses saved by --keep-raw back through parsing and saving, into --output-dir, without
+    /// fetching anything. --output-dir is cleared first, so it can't be the folder the responses are in
+    Replay {
+        /// Folder of saved responses, e.g. `matches/raw`
+        raw_dir: PathBuf,
+    },
+    /// Print the JSON Schema of a saved match, for generating types from in other languages
+    #[cfg(feature = "schema")]
+    Schema {
+        /// Write it to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn parse_secret(s: &str) -> Result<Secret, Infallible> {
+    Ok(Secret::new(s))
+}
+
+impl Args {
+    /// Loads the config file named by --config (or `ripper.toml`) and applies the flags on top
+    pub fn load_config(&self) -> Result<Config, RipperError> {
+        let mut config = Config::load(self.config.as_deref())?;
+        self.apply_to(&mut config);
+        config.check()?;
+        Ok(config)
+    }
+
+    /// The flags that pick something other than a normal fetch. Every other subcommand would parse them and then
+    /// ignore them, so they're an error there instead
+    pub fn check_mode_flags(&self) -> Result<(), clap::Error> {
+        if matches!(self.command, None | Some(Command::Fetch)) {
+            return Ok(());
+        }
+        let mode_flags = [
+            ("--resume", self.resume),
+            ("--update", self.update),
+            ("--cursor", self.cursor),
+            ("--verify", self.verify),
+            ("--dry-run", self.dry_run),
+            ("--report", self.report.is_some()),
+            ("--validate", self.validate),
+            ("--verify-checksums", self.verify_checksums),
+            ("--zip-only", self.zip_only),
+            ("--daily-quota", self.daily_quota.is_some()),
+        ];
+        match mode_flags.iter().find(|(_, given)| *given) {
+            Some((flag, _)) => Err(Args::command().error(
+                ErrorKind::ArgumentConflict,
+                format!("{} can only be used with fetch or no subcommand", flag),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Overrides the config with every flag that was actually given
+    pub fn apply_to(&self, config: &mut Config) {
+        if let Some(start_epoch) = self.start_epoch {
+            config.start_epoch = start_epoch;
+        }
+        if self.end_epoch.is_some() {
+            config.end_epoch = self.end_epoch;
+        }
+        if let Some(since) = self.since {
+            config.start_epoch = since;
+        }
+        if self.until.is_some() {
+            config.end_epoch = self.until;
+        }
+        if let Some(window_size) = self.window_size {
+            config.window_size = window_size;
+        }
+        if let Some(empty_step) = self.empty_step {
+            config.empty_step = empty_step;
+        }
+        if let Some(max_stride) = self.max_stride {
+            config.max_stride = max_stride;
+        }
+        if let Some(pool_size) = self.pool_size {
+            config.pool_size = pool_size;
+        }
+        if let Some(output_dir) = &self.output_dir {
+            config.output_dir = output_dir.clone();
+        }
+        if self.zip_path.is_some() {
+            config.zip_path = self.zip_path.clone();
+        }
+        if let Some(compression) = self.compression {
+            config.compression = compression;
+        }
+        if let Some(compress_threads) = self.compress_threads {
+            config.compress_threads = Some(compress_threads as usize);
+        }
+        if let Some(archive_format) = self.archive_format {
+            config.archive_format = archive_format;
+        }
+        if let Some(request_timeout_secs) = self.request_timeout_secs {
+            config.request_timeout_secs = request_timeout_secs;
+        }
+        if let Some(outage_threshold) = self.outage_threshold {
+            config.outage_threshold = outage_threshold;
+        }
+        if let Some(outage_cooldown_secs) = self.outage_cooldown_secs {
+            config.outage_cooldown_secs = outage_cooldown_secs;
+        }
+        if let Some(outage_max_pauses) = self.outage_max_pauses {
+            config.outage_max_pauses = outage_max_pauses;
+        }
+        if self.daily_quota.is_some() {
+            config.daily_quota = self.daily_quota;
+        }
+        if let Some(poll_interval_secs) = self.poll_interval_secs {
+            config.poll_interval_secs = poll_interval_secs;
+        }
+        if let Some(window_splits) = self.window_splits {
+            config.window_splits = window_splits;
+        }
+        if self.max_window_bytes.is_some() {
+            config.max_window_bytes = self.max_window_bytes;
+        }
+        if let Some(max_retries) = self.max_retries {
+            config.max_retries = max_retries;
+        }
+        if let Some(backoff) = self.backoff {
+            config.backoff = backoff;
+        }
+        if let Some(retry_base_delay_ms) = self.retry_base_delay_ms {
+            config.retry_base_delay_ms = retry_base_delay_ms;
+        }
+        if let Some(retry_max_delay_ms) = self.retry_max_delay_ms {
+            config.retry_max_delay_ms = retry_max_delay_ms;
+        }
+        if let Some(retry_multiplier) = self.retry_multiplier {
+            config.retry_multiplier = retry_multiplier;
+        }
+        if self.max_rps.is_some() {
+            config.max_rps = self.max_rps;
+        }
+        if self.user_agent.is_some() {
+            config.user_agent = self.user_agent.clone();
+        }
+        if self.api_token.is_some() {
+            config.api_token = self.api_token.clone();
+        }
+        if self.proxy.is_some() {
+            config.proxy = self.proxy.clone();
+        }
+        // Added on top of any headers from the config file
+        config.headers.extend(self.headers.iter().cloned());
+        if self.anonymize_salt.is_some() {
+            config.anonymize_salt = self.anonymize_salt.clone();
+        }
+        if self.strict_schema.is_some() {
+            config.strict_schema = self.strict_schema;
+        }
+        if let Some(write_buffer) = self.write_buffer {
+            config.write_buffer = write_buffer;
+        }
+        if self.limit.is_some() {
+            config.limit = self.limit;
+        }
+        if let Some(format) = self.format {
+            config.format = format;
+        }
+        if self.partition.is_some() {
+            config.partition = self.partition;
+        }
+        if self.metrics_addr.is_some() {
+            config.metrics_addr = self.metrics_addr;
+        }
+        if self.max_file_bytes.is_some() {
+            config.max_file_bytes = self.max_file_bytes;
+        }
+        if self.cache_format.is_some() {
+            config.cache_format = self.cache_format;
+        }
+        #[cfg(feature = "sqlite")]
+        if self.sqlite.is_some() {
+            config.sqlite = self.sqlite.clone();
+        }
+        if let Some(base_url) = &self.base_url {
+            config.base_url = base_url.clone();
+        }
+        if let Some(log_format) = self.log_format {
+            config.log_format = log_format;
+        }
+        if self.log_file.is_some() {
+            config.log_file = self.log_file.clone();
+        }
+        if !self.regions.is_empty() {
+            config.regions = self.regions.clone();
+        }
+        if !self.game_modes.is_empty() {
+            config.game_modes = self.game_modes.clone();
+        }
+        if self.min_duration.is_some() {
+            config.min_duration = self.min_duration;
+        }
+        if self.max_duration.is_some() {
+            config.max_duration = self.max_duration;
+        }
+        config.resume |= self.resume;
+        config.update |= self.update;
+        config.cursor |= self.cursor;
+        config.newest_first |= self.newest_first;
+        config.adaptive_window |= self.adaptive_window;
+        config.keep_raw |= self.keep_raw;
+        config.gzip_files |= self.gzip_files;
+        config.split_by_match |= self.split_by_match;
+        config.anonymize |= self.anonymize;
+        config.no_zip |= self.no_zip;
+        config.append_zip |= self.append_zip;
+        config.progress &= !self.no_progress;
+        config.summary |= self.summary;
+        if self.single_file.is_some() {
+            config.single_file = self.single_file.clone();
+        }
+        if self.status_socket.is_some() {
+            config.status_socket = self.status_socket.clone();
+        }
+        config.fail_on_error |= self.fail_on_error;
+        config.pretty |= self.pretty;
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use tracing::info;
+
+use crate::{
+    cache::cache_path,
+    checksum::write_checksums,
+    config::Config,
+    error::RipperError,
+    manifest::Manifest,
+    models::PredecessorMatch,
+    output::{read_matches, save_matches},
+    windows::{match_files, parse_match_file_name},
+};
+
+/// What a compaction did, logged once it's finished
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CompactSummary {
+    /// Small files that were merged away
+    pub files_merged: usize,
+    /// Files written in their place
+    pub files_written: usize,
+    /// Repeated matches dropped along the way
+    pub duplicates: usize,
+}
+
+/// A match file and what its name says about it
+struct SmallFile {
+    path: PathBuf,
+    start_epoch: u64,
+    bytes: u64,
+}
+
+/// Runs of files next to each other in the same folder, in epoch order, that add up to at most `target_bytes`.
+/// Files already at the target are left alone, and so is anything that ends up in a run of one
+fn group_small_files(files: Vec<SmallFile>, target_bytes: u64) -> Vec<Vec<SmallFile>> {
+    let mut by_dir: BTreeMap<PathBuf, Vec<SmallFile>> = BTreeMap::new();
+    for file in files {
+        let dir = file.path.parent().unwrap_or(Path::new("")).to_path_buf();
+        by_dir.entry(dir).or_default().push(file);
+    }
+
+    let mut groups = Vec::new();
+    for (_, mut files) in by_dir {
+        files.sort_by_key(|file| file.start_epoch);
+        let mut group: Vec<SmallFile> = Vec::new();
+        let mut group_bytes = 0;
+        for file in files {
+            if !group.is_empty() && group_bytes + file.bytes > target_bytes {
+                groups.push(std::mem::take(&mut group));
+                group_bytes = 0;
+            }
+            if file.bytes >= target_bytes {
+                continue;
+            }
+            group_bytes += file.bytes;
+            group.push(file);
+        }
+        groups.push(group);
+    }
+
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// Merges runs of small match files in `output_dir` into files of up to `target_bytes`, e.g. a day of quiet hours
+/// into one file. Merged files keep the first copy of every match id, in end time order, and are written in
+/// --format next to the files they replace. The manifest and checksums are rewritten to match.
+///
+/// The new file is written before the old ones are removed, so an interrupted compaction leaves repeats behind
+/// rather than losing matches. Formats that can't be read back (csv, parquet) are left as they are
+pub fn compact_dir(
+    output_dir: &Path,
+    target_bytes: u64,
+    config: &Config,
+) -> Result<CompactSummary, RipperError> {
+    if !config.format.holds_matches() {
+        return Err(RipperError::Compact(format!(
+            "can't compact into {} files, they can't be read back as matches",
+            config.format.extension()
+        )));
+    }
+
+    let mut files = Vec::new();
+    for (path, format) in match_files(output_dir)? {
+        if !format.holds_matches() {
+            continue;
+        }
+        let Some((start_epoch, _, _)) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_match_file_name)
+        else {
+            continue;
+        };
+        let bytes = std::fs::metadata(&path)?.len();
+        files.push(SmallFile {
+            path,
+            start_epoch,
+            bytes,
+        });
+    }
+
+    let mut manifest = Manifest::load(output_dir)?;
+    let mut summary = CompactSummary::default();
+
+    for group in group_small_files(files, target_bytes) {
+        let mut seen = HashSet::new();
+        let mut matches: Vec<PredecessorMatch> = Vec::new();
+        for file in &group {
+            let format = file
+                .path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parse_match_file_name)
+                .map(|(_, _, format)| format)
+                .unwrap_or(config.format);
+            for m in read_matches(&file.path, format)?.unwrap_or_default() {
+                if seen.insert(m.match_id.clone()) {
+                    matches.push(m);
+                } else {
+                    summary.duplicates += 1;
+                }
+            }
+        }
+        // Stable, so matches ending on the same second keep the order they were saved in
+        matches.sort_by_key(|m| m.end_time);
+
+        let dir = group[0].path.parent().unwrap_or(output_dir);
+        let prefix = dir.strip_prefix(output_dir).unwrap_or(Path::new(""));
+        let saved = save_matches(
+            dir,
+            config.format,
+            config.pretty,
+            None,
+            config.gzip_files,
+            None,
+            &matches,
+        )?;
+        let written: Vec<PathBuf> = saved
+            .iter()
+            .map(|(entry, _)| dir.join(&entry.file))
+            .collect();
+
+        for file in &group {
+            let relative = file.path.strip_prefix(output_dir).unwrap_or(&file.path);
+            manifest
+                .files
+                .retain(|entry| Path::new(&entry.file) != relative);
+            // The cache would otherwise keep standing in for a file that's gone, or been rewritten
+            let cache = cache_path(&file.path);
+            if cache.exists() {
+                std::fs::remove_file(cache)?;
+            }
+            if !written.contains(&file.path) {
+                std::fs::remove_file(&file.path)?;
+            }
+        }
+        for (mut entry, _) in saved {
+            entry.file = prefix.join(&entry.file).to_string_lossy().into_owned();
+            manifest.record(entry);
+        }
+
+        summary.files_merged += group.len();
+        summary.files_written += written.len();
+    }
+
+    manifest.save(output_dir)?;
+    write_checksums(output_dir, &manifest)?;
+
+    info!(
+        "Compacted {} small files into {} in {:?}, dropped {} repeated matches",
+        summary.files_merged, summary.files_written, output_dir, summary.duplicates
+    );
+    Ok(summary)
+}
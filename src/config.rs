@@ -0,0 +1,586 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    net::SocketAddr,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer};
+use tracing::warn;
+
+use crate::{
+    anonymize::Anonymizer,
+    backoff::Backoff,
+    error::RipperError,
+    fetch::{parse_base_url, parse_proxy, ClientOptions},
+    fights::FightThresholds,
+    models::{GameMode, Region},
+    time::parse_epoch,
+    validate::MapBounds,
+    BASE_RETRY_DELAY, BASE_URL, FIRST_EPOCH, MAX_POOL_SIZE, MAX_RETRIES, MAX_RETRY_DELAY,
+    OUTAGE_COOLDOWN_SECS, OUTAGE_MAX_PAUSES, OUTAGE_THRESHOLD, POLL_INTERVAL_SECS, POOL_SIZE,
+    REQUEST_TIMEOUT_SECS, WINDOW_SIZE, WRITE_BUFFER,
+};
+
+/// A value that should never end up in logs. Debug prints a placeholder instead of the contents
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+/// One work window per core, up to MAX_POOL_SIZE. Falls back to POOL_SIZE when the core count can't be read
+pub fn default_pool_size() -> u64 {
+    std::thread::available_parallelism()
+        .map_or(POOL_SIZE, |cores| cores.get() as u64)
+        .min(MAX_POOL_SIZE)
+}
+
+/// Looked for in the current directory when --config isn't given
+pub const DEFAULT_CONFIG_FILE: &str = "ripper.toml";
+
+/// Settings for a run. Loaded from `ripper.toml` (every field is optional), then overridden by any CLI flags
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Either a unix epoch or a UTC time string, same as --start-epoch
+    #[serde(deserialize_with = "deserialize_epoch")]
+    pub start_epoch: u64,
+    #[serde(deserialize_with = "deserialize_optional_epoch")]
+    pub end_epoch: Option<u64>,
+    pub window_size: u64,
+    /// Start with the most recent work window and work backwards
+    pub newest_first: bool,
+    /// Seconds to skip ahead when a request comes back empty
+    pub empty_step: u64,
+    /// Double the step after every empty response in a row, up to max_stride, instead of always using empty_step
+    pub adaptive_window: bool,
+    pub max_stride: u64,
+    /// Defaults to the number of cores, see `default_pool_size`
+    pub pool_size: u64,
+    /// Fetch each work window as this many sub-ranges at once, for dense hours. 1 fetches it in one go
+    pub window_splits: u32,
+    /// Give up on a response once its body goes over this many bytes, rather than holding it all in memory
+    pub max_window_bytes: Option<u64>,
+    pub resume: bool,
+    /// Keep the output folder and only fetch matches newer than what's already in it
+    pub update: bool,
+    /// Collect one request at a time behind a single cursor instead of in parallel windows, see `Ripper::run_cursor`
+    pub cursor: bool,
+    pub output_dir: PathBuf,
+    pub zip_path: Option<PathBuf>,
+    pub no_zip: bool,
+    /// Bring an existing zip up to date instead of writing it again from scratch, see `append_zip_matches`
+    pub append_zip: bool,
+    pub compression: Compression,
+    /// Threads compressing files for the zip at once. None uses every core
+    pub compress_threads: Option<usize>,
+    pub archive_format: ArchiveFormat,
+    pub request_timeout_secs: u64,
+    /// Retries for a request that failed in a way worth trying again, on top of the first attempt
+    pub max_retries: u32,
+    /// How the wait between retries grows, see `Backoff`
+    pub backoff: BackoffStrategy,
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    pub retry_multiplier: f64,
+    /// Connection failures in a row, across every worker, that pause the run for outage_cooldown_secs
+    pub outage_threshold: u32,
+    pub outage_cooldown_secs: u64,
+    /// Pauses in a row before the backend is given up on and the run fails
+    pub outage_max_pauses: u32,
+    /// Run as a daemon that never makes more than this many requests in any 24 hours, see `Ripper::run_daemon`
+    pub daily_quota: Option<u64>,
+    /// How long --daily-quota waits between checks for new matches once it has caught up
+    pub poll_interval_secs: u64,
+    /// Requests a second across every worker. Unlimited when not set
+    pub max_rps: Option<NonZeroU32>,
+    pub user_agent: Option<String>,
+    /// Extra headers sent with every request
+    pub headers: BTreeMap<String, String>,
+    /// Sent as `Authorization: Bearer <token>` when set
+    pub api_token: Option<Secret>,
+    #[serde(deserialize_with = "deserialize_optional_proxy")]
+    pub proxy: Option<String>,
+    /// Replace player ids with salted hashes and drop player names before saving
+    pub anonymize: bool,
+    /// Salt for --anonymize. Keep it the same between runs for ids to map to the same pseudonyms
+    pub anonymize_salt: Option<Secret>,
+    /// Check responses for fields the models don't know about, so API changes get noticed
+    pub strict_schema: Option<SchemaCheck>,
+    /// Also save every response body as-is to `raw/{epoch}.json` in the output folder, before it's parsed
+    pub keep_raw: bool,
+    pub write_buffer: usize,
+    /// Stop once this many matches have been collected. Windows finish the batch they're on, so a run
+    /// can overshoot by up to pool_size batches
+    pub limit: Option<usize>,
+    pub format: OutputFormat,
+    /// Indent json output. Much bigger files, only worth it when reading them by hand
+    pub pretty: bool,
+    /// Split the output folder into subfolders instead of keeping every file at the top
+    pub partition: Option<Partition>,
+    /// Gzip every match file as it's written, as `{start}-{end}.json.gz`
+    pub gzip_files: bool,
+    /// Append every match to this one NDJSON file instead of writing match files, see `SingleFile`
+    pub single_file: Option<PathBuf>,
+    /// Split a batch across numbered `.partN` files once it would go over this many bytes
+    pub max_file_bytes: Option<u64>,
+    /// Write every match to `{match_id}.{ext}` instead of a file per batch, see `save_match_files`
+    pub split_by_match: bool,
+    /// Also write every batch to a binary cache file next to it, which reports load instead of the match file
+    pub cache_format: Option<CacheFormat>,
+    #[cfg(feature = "sqlite")]
+    pub sqlite: Option<PathBuf>,
+    /// Only keep matches from these regions. Empty keeps everything
+    #[serde(deserialize_with = "deserialize_known_regions")]
+    pub regions: Vec<Region>,
+    /// Only keep matches with these game modes. Empty keeps everything
+    #[serde(deserialize_with = "deserialize_known_game_modes")]
+    pub game_modes: Vec<GameMode>,
+    /// Only keep matches whose game_duration, in seconds, is at least this
+    pub min_duration: Option<u64>,
+    /// Only keep matches whose game_duration, in seconds, is at most this
+    pub max_duration: Option<u64>,
+    /// Serve Prometheus metrics on this address while the run is going
+    pub metrics_addr: Option<SocketAddr>,
+    /// Unix socket to send an NDJSON event on every time a work window finishes
+    pub status_socket: Option<PathBuf>,
+    /// Return an error (a non-zero exit code from the CLI) when any work window failed
+    pub fail_on_error: bool,
+    /// Also write the end-of-run summary to `summary.json` in the output folder
+    pub summary: bool,
+    /// Backend to fetch from, e.g. a staging server or a local mock
+    #[serde(deserialize_with = "deserialize_base_url")]
+    pub base_url: String,
+    /// Draw a progress bar on stderr. Never drawn when stderr isn't a terminal
+    pub progress: bool,
+    pub log_format: LogFormat,
+    /// Positions outside these are flagged by --validate
+    pub map_bounds: MapBounds,
+    /// How close together kills have to be for --report fights to put them in the same fight
+    pub team_fights: FightThresholds,
+    /// Also write logs to this file, on top of stderr
+    pub log_file: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            start_epoch: FIRST_EPOCH,
+            end_epoch: None,
+            window_size: WINDOW_SIZE,
+            newest_first: false,
+            empty_step: WINDOW_SIZE,
+            adaptive_window: false,
+            max_stride: WINDOW_SIZE,
+            pool_size: default_pool_size(),
+            window_splits: 1,
+            max_window_bytes: None,
+            resume: false,
+            update: false,
+            cursor: false,
+            output_dir: PathBuf::from("matches"),
+            zip_path: None,
+            no_zip: false,
+            append_zip: false,
+            compression: Compression::Deflate,
+            compress_threads: None,
+            archive_format: ArchiveFormat::Zip,
+            request_timeout_secs: REQUEST_TIMEOUT_SECS,
+            max_retries: MAX_RETRIES,
+            backoff: BackoffStrategy::Exponential,
+            retry_base_delay_ms: BASE_RETRY_DELAY.as_millis() as u64,
+            retry_max_delay_ms: MAX_RETRY_DELAY.as_millis() as u64,
+            retry_multiplier: 2.0,
+            outage_threshold: OUTAGE_THRESHOLD,
+            outage_cooldown_secs: OUTAGE_COOLDOWN_SECS,
+            outage_max_pauses: OUTAGE_MAX_PAUSES,
+            daily_quota: None,
+            poll_interval_secs: POLL_INTERVAL_SECS,
+            max_rps: None,
+            user_agent: None,
+            headers: BTreeMap::new(),
+            api_token: None,
+            proxy: None,
+            anonymize: false,
+            anonymize_salt: None,
+            strict_schema: None,
+            keep_raw: false,
+            write_buffer: WRITE_BUFFER,
+            limit: None,
+            format: OutputFormat::Json,
+            pretty: false,
+            partition: None,
+            gzip_files: false,
+            single_file: None,
+            max_file_bytes: None,
+            split_by_match: false,
+            cache_format: None,
+            #[cfg(feature = "sqlite")]
+            sqlite: None,
+            regions: Vec::new(),
+            game_modes: Vec::new(),
+            min_duration: None,
+            max_duration: None,
+            metrics_addr: None,
+            status_socket: None,
+            fail_on_error: false,
+            summary: false,
+            base_url: BASE_URL.to_string(),
+            progress: true,
+            log_format: LogFormat::Text,
+            map_bounds: MapBounds::default(),
+            team_fights: FightThresholds::default(),
+            log_file: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads a config file. With no explicit path, `ripper.toml` in the current directory is used if it exists
+    pub fn load(path: Option<&Path>) -> Result<Self, RipperError> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Path::new(DEFAULT_CONFIG_FILE),
+            None => return Ok(Config::default()),
+        };
+
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents).map_err(|source| RipperError::Config {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Catches settings that make no sense, alone or together. The flags already have clap keeping them in
+    /// range and apart, but values from the config file only get checked here
+    pub fn check(&self) -> Result<(), RipperError> {
+        let at_least_one = [
+            ("window_size", self.window_size),
+            ("empty_step", self.empty_step),
+            ("max_stride", self.max_stride),
+            ("pool_size", self.pool_size),
+            ("window_splits", self.window_splits as u64),
+            ("write_buffer", self.write_buffer as u64),
+            ("max_file_bytes", self.max_file_bytes.unwrap_or(1)),
+        ];
+        if let Some((name, _)) = at_least_one.iter().find(|(_, value)| *value == 0) {
+            return Err(RipperError::InvalidConfig(format!(
+                "{} has to be at least 1",
+                name
+            )));
+        }
+        // The flags' conflicts_with rules, which a config file gets around
+        let single_file = self.single_file.is_some();
+        let daily_quota = self.daily_quota.is_some();
+        let conflicts = [
+            (
+                "cursor",
+                self.cursor,
+                vec![
+                    ("resume", self.resume),
+                    ("update", self.update),
+                    ("newest_first", self.newest_first),
+                    ("daily_quota", daily_quota),
+                ],
+            ),
+            (
+                "single_file",
+                single_file,
+                vec![
+                    ("resume", self.resume),
+                    ("update", self.update),
+                    ("daily_quota", daily_quota),
+                ],
+            ),
+            (
+                "split_by_match",
+                self.split_by_match,
+                vec![
+                    ("single_file", single_file),
+                    ("max_file_bytes", self.max_file_bytes.is_some()),
+                ],
+            ),
+        ];
+        for (name, set, others) in conflicts {
+            if let Some((other, _)) = others.iter().find(|(_, other_set)| set && *other_set) {
+                return Err(RipperError::InvalidConfig(format!(
+                    "{} can't be used with {}",
+                    name, other
+                )));
+            }
+        }
+        if let Some(end_epoch) = self.end_epoch {
+            if end_epoch < self.start_epoch {
+                return Err(RipperError::InvalidRange {
+                    start_epoch: self.start_epoch,
+                    end_epoch,
+                });
+            }
+        }
+        if let (Some(min_duration), Some(max_duration)) = (self.min_duration, self.max_duration) {
+            if min_duration > max_duration {
+                return Err(RipperError::InvalidDurationRange {
+                    min_duration,
+                    max_duration,
+                });
+            }
+        }
+        if self.retry_base_delay_ms > self.retry_max_delay_ms {
+            return Err(RipperError::InvalidBackoff(format!(
+                "retry_base_delay_ms ({}) is more than retry_max_delay_ms ({})",
+                self.retry_base_delay_ms, self.retry_max_delay_ms
+            )));
+        }
+        if !(self.retry_multiplier.is_finite() && self.retry_multiplier >= 1.0) {
+            return Err(RipperError::InvalidBackoff(format!(
+                "retry_multiplier has to be at least 1, got {}",
+                self.retry_multiplier
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            strategy: self.backoff,
+            base_delay: Duration::from_millis(self.retry_base_delay_ms),
+            max_delay: Duration::from_millis(self.retry_max_delay_ms),
+            multiplier: self.retry_multiplier,
+            ..Backoff::default()
+        }
+    }
+
+    pub fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            request_timeout: Duration::from_secs(self.request_timeout_secs),
+            user_agent: self.user_agent.clone(),
+            headers: self.headers.clone(),
+            api_token: self.api_token.clone(),
+            proxy: self.proxy.clone(),
+        }
+    }
+
+    /// The anonymizer for --anonymize. Without a salt a random one is made up, so pseudonyms only hold within the run
+    pub fn anonymizer(&self) -> Option<Anonymizer> {
+        if !self.anonymize {
+            return None;
+        }
+        let salt = self.anonymize_salt.clone().unwrap_or_else(|| {
+            warn!("No --anonymize-salt given, using a random one. Player pseudonyms won't match other runs");
+            Secret::new(format!("{:032x}", rand::random::<u128>()))
+        });
+        Some(Anonymizer::new(salt))
+    }
+
+    /// Where the archive goes. Defaults to the output folder with the archive format's extension
+    pub fn zip_path(&self) -> PathBuf {
+        self.zip_path.clone().unwrap_or_else(|| {
+            let mut path = self.output_dir.clone().into_os_string();
+            path.push(".");
+            path.push(self.archive_format.extension());
+            path.into()
+        })
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackoffStrategy {
+    /// The base delay every time
+    Fixed,
+    /// The base delay times the multiplier for every retry so far, up to the max delay
+    Exponential,
+    /// Somewhere between the base delay and the multiplier times the last delay, up to the max delay
+    Decorrelated,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One JSON array per file
+    Json,
+    /// One match object per line
+    Ndjson,
+    /// One row per player per match, flattened for spreadsheets
+    Csv,
+    /// The same player rows as csv, as typed, compressed columns
+    #[cfg(feature = "polars")]
+    Parquet,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// No compression
+    Stored,
+    Deflate,
+    /// Much better ratio and speed than deflate on our JSON, but not every unzip tool supports it
+    Zstd,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Zip,
+    /// A gzipped tarball. --compression only applies to zips, this is always gzip
+    Targz,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Targz => "tar.gz",
+        }
+    }
+}
+
+impl From<Compression> for zip::CompressionMethod {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Stored => zip::CompressionMethod::Stored,
+            Compression::Deflate => zip::CompressionMethod::Deflated,
+            Compression::Zstd => zip::CompressionMethod::Zstd,
+        }
+    }
+}
+
+impl OutputFormat {
+    pub const ALL: &'static [OutputFormat] = &[
+        OutputFormat::Json,
+        OutputFormat::Ndjson,
+        OutputFormat::Csv,
+        #[cfg(feature = "polars")]
+        OutputFormat::Parquet,
+    ];
+
+    /// Whether files in this format can be read back as whole matches. CSV and Parquet only hold player rows
+    pub fn holds_matches(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Ndjson)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Csv => "csv",
+            #[cfg(feature = "polars")]
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line, with the event's fields as keys. For log aggregators
+    Json,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Partition {
+    /// `{output_dir}/YYYY/MM/DD/`, from the first match's end time (UTC)
+    Date,
+    /// `{output_dir}/{region}/`. Batches with several regions are split into one file per region
+    Region,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaCheck {
+    /// Log the unknown fields and keep going
+    Warn,
+    /// Log them and give up on the work window
+    Fail,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheFormat {
+    /// `{start}-{end}.bin` files written with bincode
+    Bincode,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawEpoch {
+    Epoch(u64),
+    Human(String),
+}
+
+impl RawEpoch {
+    fn resolve<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            RawEpoch::Epoch(epoch) => Ok(epoch),
+            RawEpoch::Human(value) => parse_epoch(&value).map_err(E::custom),
+        }
+    }
+}
+
+fn deserialize_epoch<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    RawEpoch::deserialize(deserializer)?.resolve()
+}
+
+fn deserialize_optional_epoch<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<RawEpoch>::deserialize(deserializer)?
+        .map(RawEpoch::resolve)
+        .transpose()
+}
+
+fn deserialize_base_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_base_url(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_known_regions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Region>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| Region::parse_known(value).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn deserialize_known_game_modes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<GameMode>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| GameMode::parse_known(value).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn deserialize_optional_proxy<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_proxy(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
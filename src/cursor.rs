@@ -0,0 +1,38 @@
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::RipperError, output::write_atomically};
+
+pub const CURSOR_FILE: &str = "cursor.json";
+
+/// How far a --cursor run has got, kept at `{output_dir}/cursor.json`. Every match that ended before `epoch` has
+/// been saved, so picking up from it never leaves a gap. A batch saved just before a kill can be past it, which
+/// is why the run starts by reading back the ids of the matches saved from `epoch` on, so none is saved twice
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub epoch: u64,
+}
+
+impl Cursor {
+    /// Loads the cursor from the output folder, or None if there isn't one
+    pub fn load(output_dir: &Path) -> Result<Option<Self>, RipperError> {
+        match File::open(output_dir.join(CURSOR_FILE)) {
+            Ok(file) => Ok(Some(serde_json::from_reader(BufReader::new(file))?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Written atomically, so a crash leaves either the old cursor or the new one
+    pub fn save(&self, output_dir: &Path) -> Result<(), RipperError> {
+        write_atomically(&output_dir.join(CURSOR_FILE), |file| {
+            serde_json::to_writer_pretty(file, self)?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use polars::prelude::*;
+
+use crate::{
+    export::{player_rows, PlayerRow},
+    models::PredecessorMatch,
+};
+
+/// One row per player per match, with the same columns as the csv export. Missing role and player
+/// names become nulls
+pub fn matches_to_dataframe(matches: &[PredecessorMatch]) -> PolarsResult<DataFrame> {
+    let rows: Vec<PlayerRow> = matches.iter().flat_map(player_rows).collect();
+
+    macro_rules! column {
+        ($field:ident) => {
+            rows.iter().map(|r| r.$field).collect::<Vec<_>>()
+        };
+        ($field:ident as $ty:ty) => {
+            rows.iter().map(|r| r.$field as $ty).collect::<Vec<_>>()
+        };
+    }
+
+    df!(
+        "match_id" => column!(match_id),
+        "region" => column!(region),
+        "game_mode" => column!(game_mode),
+        "winning_team" => column!(winning_team),
+        "game_duration" => column!(game_duration),
+        "player_id" => column!(player_id),
+        "player_name" => column!(player_name),
+        "team_id" => column!(team_id),
+        "hero_name" => column!(hero_name),
+        "role_name" => column!(role_name),
+        "kills" => column!(kills),
+        "deaths" => column!(deaths),
+        "assists" => column!(assists),
+        "largest_killing_spree" => column!(largest_killing_spree),
+        "largest_multi_kill" => column!(largest_multi_kill),
+        "minions_killed" => column!(minions_killed),
+        "lane_minions_killed" => column!(lane_minions_killed),
+        "neutral_minions_killed" => column!(neutral_minions_killed),
+        "neutral_minions_team_jungle" => column!(neutral_minions_team_jungle),
+        "neutral_minions_enemy_jungle" => column!(neutral_minions_enemy_jungle),
+        "gold_earned" => column!(gold_earned),
+        "gold_spent" => column!(gold_spent),
+        "wards_placed" => column!(wards_placed),
+        "wards_destroyed" => column!(wards_destroyed),
+        "ability_count" => column!(ability_count as u64),
+        "inventory_count" => column!(inventory_count as u64),
+        "kda" => column!(kda),
+        "gold_per_min" => column!(gold_per_min),
+        "cs_per_min" => column!(cs_per_min),
+        "damage_per_min" => column!(damage_per_min),
+    )
+}
@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RipperError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("Cache error: {0}")]
+    Cache(#[from] bincode::Error),
+
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("Error walking the output folder: {0}")]
+    Walk(#[from] walkdir::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[cfg(feature = "polars")]
+    #[error("Polars error: {0}")]
+    Polars(#[from] polars::error::PolarsError),
+
+    #[error("Could not clear the output folder {path:?}: {source}. Another run may be using it, or a file in it is open elsewhere")]
+    ClearOutputDir {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("{0} work window(s) failed")]
+    WindowsFailed(usize),
+
+    #[error("Can't merge: {0}")]
+    Merge(String),
+
+    #[error("Invalid config file {path:?}: {source}")]
+    Config {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("The start ({start_epoch}) is after the end ({end_epoch})")]
+    InvalidRange { start_epoch: u64, end_epoch: u64 },
+
+    #[error(
+        "The minimum game duration ({min_duration}s) is more than the maximum ({max_duration}s)"
+    )]
+    InvalidDurationRange {
+        min_duration: u64,
+        max_duration: u64,
+    },
+
+    #[error("Could not parse time \"{0}\"")]
+    TimeParse(String),
+
+    #[error("Backend returned status {status} for epoch {epoch}")]
+    ApiStatus { epoch: u64, status: u16 },
+
+    #[error("The writer stopped before all matches were saved")]
+    WriterClosed,
+
+    #[error("Invalid proxy {url:?}: {source}")]
+    InvalidProxy { url: String, source: reqwest::Error },
+
+    #[error("Invalid header {0:?}")]
+    InvalidHeader(String),
+
+    #[error("Response for epoch {epoch} has unknown fields: {}", fields.join(", "))]
+    UnknownFields { epoch: u64, fields: Vec<String> },
+
+    #[error("Parsing a response failed: {0}")]
+    ParseTask(tokio::task::JoinError),
+
+    #[error("Couldn't start the compression threads: {0}")]
+    CompressThreads(#[from] rayon::ThreadPoolBuildError),
+
+    #[error("The writer task failed: {0}")]
+    WriterTask(#[from] tokio::task::JoinError),
+
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Invalid retry backoff: {0}")]
+    InvalidBackoff(String),
+
+    #[error("Can't compact: {0}")]
+    Compact(String),
+
+    #[error("Response for epoch {epoch} is over the limit of {max_bytes} bytes")]
+    ResponseTooLarge { epoch: u64, max_bytes: u64 },
+
+    #[error("Can't replay: {0}")]
+    Replay(String),
+
+    #[error("Backend appears unavailable: still unreachable after {pauses} pause(s)")]
+    BackendUnavailable { pauses: u32 },
+
+    #[error("The daily request quota is used up until epoch {available_at}")]
+    QuotaExhausted { available_at: u64 },
+
+    #[error("Giving up on epoch {epoch} after {attempts} attempt(s): {source}")]
+    RetriesExhausted {
+        epoch: u64,
+        attempts: u32,
+        source: Box<RipperError>,
+    },
+}
+
+impl RipperError {
+    /// Connection errors, timeouts, 5xx and 429 are worth another go. Anything else (other 4xx, bad JSON) won't fix itself
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RipperError::Http(err) => err.is_connect() || err.is_timeout(),
+            RipperError::ApiStatus { status, .. } => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+
+    /// The backend couldn't be reached at all, as opposed to answering with an error
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, RipperError::Http(err) if err.is_connect() || err.is_timeout())
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::models::{GameTime, PredecessorMatch, Region, WardData};
+
+/// One row per player per match, with the parent match's fields repeated on every row.
+/// Nested lists (abilities, inventory) are summarized as counts rather than exploded
+#[derive(Serialize, Debug)]
+pub struct PlayerRow<'a> {
+    pub match_id: &'a str,
+    pub region: &'a str,
+    pub game_mode: &'a str,
+    pub winning_team: i64,
+    pub game_duration: i64,
+    pub player_id: &'a str,
+    pub player_name: Option<&'a str>,
+    pub team_id: i64,
+    pub hero_name: &'a str,
+    pub role_name: Option<&'a str>,
+    pub kills: i64,
+    pub deaths: i64,
+    pub assists: i64,
+    pub largest_killing_spree: i64,
+    pub largest_multi_kill: i64,
+    pub minions_killed: i64,
+    pub lane_minions_killed: i64,
+    pub neutral_minions_killed: i64,
+    pub neutral_minions_team_jungle: i64,
+    pub neutral_minions_enemy_jungle: i64,
+    pub gold_earned: i64,
+    pub gold_spent: i64,
+    pub wards_placed: i64,
+    pub wards_destroyed: i64,
+    pub ability_count: usize,
+    pub inventory_count: usize,
+    pub kda: f64,
+    pub gold_per_min: f64,
+    pub cs_per_min: f64,
+    pub damage_per_min: f64,
+}
+
+pub fn player_rows(m: &PredecessorMatch) -> impl Iterator<Item = PlayerRow<'_>> {
+    m.player_data.iter().map(move |p| {
+        let derived = p.derived_stats(m.game_duration);
+        PlayerRow {
+            match_id: &m.match_id,
+            region: m.region.as_str(),
+            game_mode: m.game_mode.as_str(),
+            winning_team: m.winning_team,
+            game_duration: m.game_duration,
+            player_id: &p.player_id,
+            player_name: p.player_name.as_deref(),
+            team_id: p.team_id,
+            hero_name: &p.hero_name,
+            role_name: p.role_name.as_deref(),
+            kills: p.combat_data.kills,
+            deaths: p.combat_data.deaths,
+            assists: p.combat_data.assists,
+            largest_killing_spree: p.combat_data.largest_killing_spree,
+            largest_multi_kill: p.combat_data.largest_multi_kill,
+            minions_killed: p.minion_data.minions_killed,
+            lane_minions_killed: p.minion_data.lane_minions_killed,
+            neutral_minions_killed: p.minion_data.neutral_minions_killed,
+            neutral_minions_team_jungle: p.minion_data.neutral_minions_team_jungle,
+            neutral_minions_enemy_jungle: p.minion_data.neutral_minions_enemy_jungle,
+            gold_earned: p.income_data.gold_earned,
+            gold_spent: p.income_data.gold_spent,
+            wards_placed: p.wards_data.wards_placed,
+            wards_destroyed: p.wards_data.wards_destroyed,
+            ability_count: p.ability_data.len(),
+            inventory_count: p.inventory_data.as_ref().map_or(0, Vec::len),
+            kda: derived.kda,
+            gold_per_min: derived.gold_per_min,
+            cs_per_min: derived.cs_per_min,
+            damage_per_min: derived.damage_per_min,
+        }
+    })
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WardEvent {
+    Placement,
+    Destruction,
+}
+
+/// One row per ward placed or destroyed, for map heatmaps
+#[derive(Serialize, Debug)]
+pub struct WardRow<'a> {
+    pub match_id: &'a str,
+    pub player_id: &'a str,
+    pub team_id: i64,
+    pub type_id: i64,
+    pub game_time: GameTime,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub event_type: WardEvent,
+}
+
+pub fn ward_rows(m: &PredecessorMatch) -> impl Iterator<Item = WardRow<'_>> {
+    m.player_data.iter().flat_map(move |p| {
+        let row = move |w: &'_ WardData, event_type| WardRow {
+            match_id: &m.match_id,
+            player_id: &p.player_id,
+            team_id: p.team_id,
+            type_id: w.type_id,
+            game_time: w.game_time,
+            x: w.location.x,
+            y: w.location.y,
+            z: w.location.z,
+            event_type,
+        };
+        let wards = &p.wards_data;
+        wards
+            .ward_placements
+            .iter()
+            .map(move |w| row(w, WardEvent::Placement))
+            .chain(
+                wards
+                    .ward_destructions
+                    .iter()
+                    .map(move |w| row(w, WardEvent::Destruction)),
+            )
+    })
+}
+
+/// One row per hero kill. `killer_entity_type` is kept so tower and minion kills can be told apart from hero kills
+#[derive(Serialize, Debug)]
+pub struct KillRow<'a> {
+    pub match_id: &'a str,
+    pub region: &'a Region,
+    pub game_time: GameTime,
+    pub killer_player_id: &'a str,
+    pub killer_hero_name: &'a str,
+    pub killer_entity_type: &'a str,
+    pub killed_player_id: &'a str,
+    pub killed_hero_name: &'a str,
+    pub is_first_blood: bool,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+pub fn kill_rows(m: &PredecessorMatch) -> impl Iterator<Item = KillRow<'_>> {
+    m.hero_kills.iter().map(move |k| KillRow {
+        match_id: &m.match_id,
+        region: &m.region,
+        game_time: k.game_time,
+        killer_player_id: &k.killer_player_id,
+        killer_hero_name: &k.killer_hero_name,
+        killer_entity_type: &k.killer_entity_type,
+        killed_player_id: &k.killed_player_id,
+        killed_hero_name: &k.killed_hero_name,
+        is_first_blood: k.is_first_blood,
+        x: k.location.x,
+        y: k.location.y,
+        z: k.location.z,
+    })
+}
+
+/// One row per team per interval of `gold_earned_at_interval`, for gold difference over time
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GoldRow<'a> {
+    pub match_id: &'a str,
+    pub team_id: i64,
+    pub interval: usize,
+    pub gold: i64,
+}
+
+/// Each team's gold summed over its players at every interval. Players' timelines don't always have the same
+/// length, so every team's is cut to the shortest one in the match: both teams cover the same intervals, and no
+/// interval is missing a player
+pub fn gold_timeline_rows(m: &PredecessorMatch) -> Vec<GoldRow<'_>> {
+    let intervals = m
+        .player_data
+        .iter()
+        .map(|p| p.income_data.gold_earned_at_interval.len())
+        .min()
+        .unwrap_or(0);
+
+    let mut teams: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+    for p in &m.player_data {
+        let gold = teams.entry(p.team_id).or_insert_with(|| vec![0; intervals]);
+        for (total, earned) in gold.iter_mut().zip(&p.income_data.gold_earned_at_interval) {
+            *total += earned;
+        }
+    }
+
+    teams
+        .into_iter()
+        .flat_map(|(team_id, gold)| {
+            gold.into_iter()
+                .enumerate()
+                .map(move |(interval, gold)| GoldRow {
+                    match_id: &m.match_id,
+                    team_id,
+                    interval,
+                    gold,
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,476 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, BufReader},
+    num::NonZeroU32,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use governor::{DefaultDirectRateLimiter, Quota};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, RETRY_AFTER},
+    Client, Proxy, Response, StatusCode, Url,
+};
+use tokio_util::io::{StreamReader, SyncIoBridge};
+use tracing::{debug, warn};
+
+use crate::{
+    backoff::Backoff,
+    circuit::CircuitBreaker,
+    config::{SchemaCheck, Secret},
+    error::RipperError,
+    metrics::Metrics,
+    models::PredecessorMatch,
+    quota::RequestQuota,
+    CONNECT_TIMEOUT, MAX_RETRIES, REQUEST_TIMEOUT_SECS,
+};
+
+/// Folder under the output folder that --keep-raw writes response bodies to
+pub const RAW_DIR: &str = "raw";
+
+/// Shared by every worker, so the limit holds for the whole run however many windows are in flight
+pub type RateLimiter = DefaultDirectRateLimiter;
+
+/// A token bucket allowing `max_rps` requests a second, with bursts of up to one second's worth
+pub fn rate_limiter(max_rps: NonZeroU32) -> RateLimiter {
+    RateLimiter::direct(Quota::per_second(max_rps))
+}
+
+/// Everything about the shared client that can be configured
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub request_timeout: Duration,
+    pub user_agent: Option<String>,
+    /// Sent with every request
+    pub headers: BTreeMap<String, String>,
+    pub api_token: Option<Secret>,
+    /// http(s):// or socks5(h):// proxy for every request. Without one, HTTP_PROXY/HTTPS_PROXY are used
+    pub proxy: Option<String>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        ClientOptions {
+            request_timeout: Duration::from_secs(REQUEST_TIMEOUT_SECS),
+            user_agent: None,
+            headers: BTreeMap::new(),
+            api_token: None,
+            proxy: None,
+        }
+    }
+}
+
+/// Checks that `s` is a proxy URL reqwest can use
+pub fn parse_proxy(s: &str) -> Result<String, String> {
+    let url = Url::parse(s).map_err(|e| format!("invalid proxy URL {:?}: {}", s, e))?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(format!(
+            "proxy {:?} must be http, https, socks5 or socks5h",
+            s
+        ));
+    }
+    Ok(s.to_string())
+}
+
+/// Checks that a `KEY=VALUE` pair makes a valid HTTP header
+pub fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {:?}", s))?;
+    let (name, value) = (name.trim(), value.trim());
+    header_pair(name, value).map_err(|e| e.to_string())?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn header_pair(name: &str, value: &str) -> Result<(HeaderName, HeaderValue), RipperError> {
+    let invalid = || RipperError::InvalidHeader(name.to_string());
+    Ok((
+        HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+        HeaderValue::from_str(value).map_err(|_| invalid())?,
+    ))
+}
+
+pub fn build_client(request_timeout: Duration) -> Result<Client, RipperError> {
+    build_client_with(&ClientOptions {
+        request_timeout,
+        ..ClientOptions::default()
+    })
+}
+
+pub fn build_client_with(options: &ClientOptions) -> Result<Client, RipperError> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &options.headers {
+        let (name, value) = header_pair(name, value)?;
+        headers.insert(name, value);
+    }
+    if let Some(token) = &options.api_token {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token.expose()))
+            .map_err(|_| RipperError::InvalidHeader(AUTHORIZATION.to_string()))?;
+        // Keeps it out of reqwest's own debug output
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    let mut builder = Client::builder()
+        .timeout(options.request_timeout)
+        .connect_timeout(CONNECT_TIMEOUT.min(options.request_timeout))
+        .default_headers(headers);
+    if let Some(user_agent) = &options.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(proxy) = &options.proxy {
+        let proxy = Proxy::all(proxy).map_err(|source| RipperError::InvalidProxy {
+            url: proxy.clone(),
+            source,
+        })?;
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Checks that `s` is an absolute http(s) URL and drops any trailing slash, since the epoch is appended with one
+pub fn parse_base_url(s: &str) -> Result<String, String> {
+    let url = Url::parse(s).map_err(|e| format!("invalid base URL {:?}: {}", s, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("base URL {:?} must be http or https", s));
+    }
+    Ok(s.trim_end_matches('/').to_string())
+}
+
+/// A single failed attempt. `retry_after` is set when a 429 told us exactly how long to wait
+struct AttemptError {
+    source: RipperError,
+    retry_after: Option<Duration>,
+}
+
+impl<E: Into<RipperError>> From<E> for AttemptError {
+    fn from(source: E) -> Self {
+        AttemptError {
+            source: source.into(),
+            retry_after: None,
+        }
+    }
+}
+
+/// Retry-After is either a number of seconds or an HTTP-date
+fn parse_retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc);
+    // A date in the past means we can go right away
+    Some((date - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Where an ignored field sits in a response, with array indices left out so the same field in every match
+/// comes out the same, e.g. `[].playerData[].newStat`
+fn field_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, .. } => format!("{}[]", field_path(parent)),
+        Path::Map { parent, key } => format!("{}.{}", field_path(parent), key),
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => field_path(parent),
+    }
+}
+
+pub(crate) fn parse_matches(
+    body: &[u8],
+    epoch: u64,
+    schema_check: Option<SchemaCheck>,
+) -> Result<Vec<PredecessorMatch>, RipperError> {
+    let Some(schema_check) = schema_check else {
+        return Ok(serde_json::from_slice(body)?);
+    };
+
+    let mut unknown = BTreeSet::new();
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let matches = serde_ignored::deserialize(&mut deserializer, |path| {
+        unknown.insert(field_path(&path));
+    })?;
+    deserializer.end()?;
+
+    if !unknown.is_empty() {
+        let fields: Vec<String> = unknown.into_iter().collect();
+        warn!(
+            epoch,
+            ?fields,
+            "Response has fields the models don't know about"
+        );
+        if schema_check == SchemaCheck::Fail {
+            return Err(RipperError::UnknownFields { epoch, fields });
+        }
+    }
+    Ok(matches)
+}
+
+/// Parses matches straight off the connection, so the raw body is never held in memory alongside the matches
+/// parsed from it. serde_json only reads synchronously, so the parsing happens on a blocking thread. Also returns
+/// the size of the body. Reading stops once the body goes over `max_bytes`
+async fn parse_streaming(
+    response: Response,
+    epoch: u64,
+    max_bytes: Option<u64>,
+) -> Result<(Vec<PredecessorMatch>, u64), RipperError> {
+    let bytes = Arc::new(AtomicU64::new(0));
+    let counter = bytes.clone();
+    let chunks = response.bytes_stream().map(move |chunk| {
+        let chunk = chunk.map_err(io::Error::other)?;
+        let total = counter.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+        if max_bytes.is_some_and(|max_bytes| total > max_bytes) {
+            return Err(io::Error::other("response too large"));
+        }
+        Ok(chunk)
+    });
+    let reader = BufReader::new(SyncIoBridge::new(StreamReader::new(chunks)));
+    let parsed = tokio::task::spawn_blocking(move || serde_json::from_reader(reader))
+        .await
+        .map_err(RipperError::ParseTask)?;
+
+    let bytes = bytes.load(Ordering::Relaxed);
+    match (parsed, max_bytes) {
+        // The parse error is only the cut-off body
+        (Err(_), Some(max_bytes)) if bytes > max_bytes => {
+            Err(RipperError::ResponseTooLarge { epoch, max_bytes })
+        }
+        (parsed, _) => Ok((parsed?, bytes)),
+    }
+}
+
+/// The whole body, for when it's needed at once. Stops reading once it goes over `max_bytes`
+async fn read_body(
+    mut response: Response,
+    epoch: u64,
+    max_bytes: Option<u64>,
+) -> Result<Vec<u8>, RipperError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if let Some(max_bytes) = max_bytes.filter(|&max_bytes| body.len() as u64 > max_bytes) {
+            return Err(RipperError::ResponseTooLarge { epoch, max_bytes });
+        }
+    }
+    Ok(body)
+}
+
+async fn try_get_matches(
+    client: &Client,
+    url: &str,
+    epoch: u64,
+    options: &RequestOptions<'_>,
+) -> Result<(Vec<PredecessorMatch>, u64), AttemptError> {
+    let response = client.get(url).send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        if let Some(metrics) = options.metrics {
+            metrics.record_status(status.as_u16());
+        }
+        let retry_after = if status == StatusCode::TOO_MANY_REQUESTS {
+            parse_retry_after(&response)
+        } else {
+            None
+        };
+        return Err(AttemptError {
+            source: RipperError::ApiStatus {
+                epoch,
+                status: status.as_u16(),
+            },
+            retry_after,
+        });
+    }
+
+    // No use reading any of it when the backend says up front that it's too big
+    if let Some(max_bytes) = options.max_response_bytes {
+        if response.content_length().is_some_and(|len| len > max_bytes) {
+            return Err(RipperError::ResponseTooLarge { epoch, max_bytes }.into());
+        }
+    }
+
+    // Both of these need the whole body at once
+    if options.raw_dir.is_none() && options.schema_check.is_none() {
+        return Ok(parse_streaming(response, epoch, options.max_response_bytes).await?);
+    }
+
+    let body = read_body(response, epoch, options.max_response_bytes).await?;
+    // Saved before parsing, so the body is still there to look at when parsing fails
+    if let Some(raw_dir) = options.raw_dir {
+        tokio::fs::write(raw_dir.join(format!("{}.json", epoch)), &body).await?;
+    }
+    let matches = parse_matches(&body, epoch, options.schema_check)?;
+    Ok((matches, body.len() as u64))
+}
+
+/// Everything about how an epoch is fetched besides the client and the epoch itself
+#[derive(Debug, Clone, Copy)]
+pub struct RequestOptions<'a> {
+    /// Every attempt, retries included, waits for a permit from this when there is one
+    pub limiter: Option<&'a RateLimiter>,
+    pub max_retries: u32,
+    /// The waits between retries, unless a 429 says how long to wait
+    pub backoff: Backoff,
+    /// Turns on the --strict-schema check for unknown fields
+    pub schema_check: Option<SchemaCheck>,
+    /// Every response body is saved here as `{epoch}.json` when set
+    pub raw_dir: Option<&'a Path>,
+    pub metrics: Option<&'a Metrics>,
+    /// Pauses or stops the request when the backend looks to be down
+    pub breaker: Option<&'a CircuitBreaker>,
+    /// Every attempt, retries included, is taken out of this budget. Fails with QuotaExhausted once it's used up
+    pub quota: Option<&'a RequestQuota>,
+    /// Responses with bodies bigger than this are abandoned part way with ResponseTooLarge, see --max-window-bytes
+    pub max_response_bytes: Option<u64>,
+}
+
+impl Default for RequestOptions<'_> {
+    fn default() -> Self {
+        RequestOptions {
+            limiter: None,
+            max_retries: MAX_RETRIES,
+            backoff: Backoff::default(),
+            schema_check: None,
+            raw_dir: None,
+            metrics: None,
+            breaker: None,
+            quota: None,
+            max_response_bytes: None,
+        }
+    }
+}
+
+pub async fn get_matches_since(
+    client: &Client,
+    base_url: &str,
+    epoch: u64,
+    options: &RequestOptions<'_>,
+) -> Result<Vec<PredecessorMatch>, RipperError> {
+    let url = format!("{}/{}", base_url, epoch);
+    let mut attempts = 0;
+    let mut last_delay = Duration::ZERO;
+
+    loop {
+        attempts += 1;
+        if let Some(breaker) = options.breaker {
+            breaker.wait().await?;
+        }
+        if let Some(limiter) = options.limiter {
+            limiter.until_ready().await;
+        }
+        if let Some(quota) = options.quota {
+            let now = Utc::now().timestamp() as u64;
+            if !quota.try_acquire(now) {
+                let available_at = quota.available_at(now).unwrap_or(now);
+                return Err(RipperError::QuotaExhausted { available_at });
+            }
+        }
+        if let Some(metrics) = options.metrics {
+            metrics.requests.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let started = Instant::now();
+        let result = try_get_matches(client, &url, epoch, options).await;
+        if let Some(breaker) = options.breaker {
+            match &result {
+                Err(err) if err.source.is_connection_error() => breaker.record_connection_failure(),
+                _ => breaker.record_response(),
+            }
+        }
+
+        match result {
+            Ok((matches, bytes)) => {
+                let elapsed = started.elapsed();
+                debug!(
+                    epoch,
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    bytes,
+                    match_count = matches.len(),
+                    "Fetched epoch"
+                );
+                if let Some(metrics) = options.metrics {
+                    metrics.record_response(elapsed, bytes);
+                }
+                return Ok(matches);
+            }
+            Err(err) if err.source.is_retryable() && attempts <= options.max_retries => {
+                // Honor the backend's Retry-After on a 429, otherwise fall back to our own backoff
+                let delay = err.retry_after.unwrap_or_else(|| {
+                    options
+                        .backoff
+                        .delay(attempts, last_delay, &mut rand::thread_rng())
+                });
+                last_delay = delay;
+                warn!(
+                    epoch,
+                    attempt = attempts,
+                    error = %err.source,
+                    "Request failed, retrying in {:?}",
+                    delay
+                );
+                if let Some(metrics) = options.metrics {
+                    metrics.retries.fetch_add(1, Ordering::Relaxed);
+                }
+                tokio::time::sleep(delay).await;
+            }
+            Err(err) => {
+                return Err(RipperError::RetriesExhausted {
+                    epoch,
+                    attempts,
+                    source: Box::new(err.source),
+                })
+            }
+        }
+    }
+}
+
+/// Fetches the matches that ended at or after `epoch` from `base_url`, retrying transient failures with the
+/// default policy. `base_url` is usually [`BASE_URL`](crate::BASE_URL), the public backend. The epoch is appended to it as the
+/// last path segment
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::time::Duration;
+///
+/// use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+///
+/// // A mock backend standing in for BASE_URL
+/// let server = MockServer::start().await;
+/// Mock::given(path("/get-matches-since/1680307200"))
+///     .respond_with(ResponseTemplate::new(200).set_body_string(include_str!(concat!(
+///         env!("CARGO_MANIFEST_DIR"),
+///         "/tests/fixtures/matches.json"
+///     ))))
+///     .mount(&server)
+///     .await;
+/// let base_url = format!("{}/get-matches-since", server.uri());
+///
+/// let client = pred_ripper::build_client(Duration::from_secs(30))?;
+/// let matches = pred_ripper::fetch_matches_since(&client, &base_url, 1680307200).await?;
+/// for m in &matches {
+///     println!("{} ended at {}", m.match_id, m.end_time);
+/// }
+/// assert_eq!(matches.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn fetch_matches_since(
+    client: &Client,
+    base_url: &str,
+    epoch: u64,
+) -> Result<Vec<PredecessorMatch>, RipperError> {
+    get_matches_since(client, base_url, epoch, &RequestOptions::default()).await
+}
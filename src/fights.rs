@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{GameTime, HeroKill, Location, PredecessorMatch};
+
+/// How close together kills have to be to count as the same fight. Can be overridden with a `[team_fights]`
+/// table in the config file
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FightThresholds {
+    /// Longest gap in seconds between a kill and the one before it in the same fight
+    pub window_secs: i64,
+    /// Furthest a kill can be, across the map (x and y, height is ignored), from some other kill in the fight
+    pub radius: f64,
+}
+
+impl Default for FightThresholds {
+    /// About what a skirmish looks like on the current map
+    fn default() -> Self {
+        FightThresholds {
+            window_secs: 15,
+            radius: 3000.0,
+        }
+    }
+}
+
+/// A run of kills close together in time and space
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TeamFight {
+    pub start_time: GameTime,
+    pub end_time: GameTime,
+    pub duration_secs: i64,
+    pub kills: usize,
+    /// Everyone who killed or died in the fight, by player id
+    pub participants: BTreeSet<String>,
+    /// Kills by each team's players minus deaths on that team. Kills by towers and minions aren't
+    /// credited to anyone, but the deaths still count
+    pub net_kills: BTreeMap<i64, i64>,
+}
+
+/// The fights in one match, in the order they happened
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MatchFights {
+    pub match_id: String,
+    pub fights: Vec<TeamFight>,
+}
+
+fn horizontal_distance(a: &Location, b: &Location) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// Groups the kills in `m` into fights. Kills are taken in time order, and each joins the fight before it if it's
+/// within `window_secs` of that fight's last kill and within `radius` of any of its kills. Anything else starts a
+/// fight of its own, so a lone pick shows up as a fight with one kill
+pub fn detect_team_fights(m: &PredecessorMatch, thresholds: &FightThresholds) -> MatchFights {
+    let mut kills: Vec<&HeroKill> = m.hero_kills.iter().collect();
+    kills.sort_by_key(|k| k.game_time);
+
+    let mut groups: Vec<Vec<&HeroKill>> = Vec::new();
+    for kill in kills {
+        let joins_last = groups.last().is_some_and(|fight| {
+            let last = fight[fight.len() - 1];
+            kill.game_time.as_seconds() - last.game_time.as_seconds() <= thresholds.window_secs
+                && fight
+                    .iter()
+                    .any(|k| horizontal_distance(&k.location, &kill.location) <= thresholds.radius)
+        });
+        match groups.last_mut() {
+            Some(fight) if joins_last => fight.push(kill),
+            _ => groups.push(vec![kill]),
+        }
+    }
+
+    let teams: HashMap<&str, i64> = m
+        .player_data
+        .iter()
+        .map(|p| (p.player_id.as_str(), p.team_id))
+        .collect();
+
+    let fights = groups
+        .into_iter()
+        .map(|group| {
+            let start_time = group[0].game_time;
+            let end_time = group[group.len() - 1].game_time;
+            let mut participants = BTreeSet::new();
+            let mut net_kills = BTreeMap::new();
+            for kill in &group {
+                if let Some(&team) = teams.get(kill.killer_player_id.as_str()) {
+                    participants.insert(kill.killer_player_id.clone());
+                    *net_kills.entry(team).or_insert(0) += 1;
+                }
+                participants.insert(kill.killed_player_id.clone());
+                if let Some(&team) = teams.get(kill.killed_player_id.as_str()) {
+                    *net_kills.entry(team).or_insert(0) -= 1;
+                }
+            }
+            TeamFight {
+                start_time,
+                end_time,
+                duration_secs: end_time.as_seconds() - start_time.as_seconds(),
+                kills: group.len(),
+                participants,
+                net_kills,
+            }
+        })
+        .collect();
+
+    MatchFights {
+        match_id: m.match_id.clone(),
+        fights,
+    }
+}
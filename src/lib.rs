@@ -0,0 +1,72 @@
+//! Scrapes the Predecessor API for matches.
+//!
+//! The quickest way in is [`fetch_matches_since`] for a single request, [`MatchStream`] to iterate over matches
+//! one at a time, or [`Ripper`] to run a whole collection (work windows, retries, output files and the zip)
+//! from a [`Config`].
+
+use std::time::Duration;
+
+pub mod anonymize;
+pub mod archive;
+pub mod backoff;
+pub mod cache;
+pub mod checkpoint;
+pub mod checksum;
+pub mod circuit;
+pub mod compact;
+pub mod config;
+pub mod cursor;
+#[cfg(feature = "polars")]
+pub mod dataframe;
+pub mod error;
+pub mod export;
+pub mod fetch;
+pub mod fights;
+pub mod manifest;
+pub mod merge;
+pub mod metrics;
+pub mod models;
+pub mod output;
+pub mod progress;
+pub mod quota;
+pub mod report;
+mod ripper;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod status;
+pub mod stream;
+pub mod time;
+pub mod validate;
+pub mod windows;
+
+pub use config::{
+    ArchiveFormat, BackoffStrategy, CacheFormat, Compression, Config, LogFormat, OutputFormat,
+    Partition, SchemaCheck, Secret,
+};
+pub use error::RipperError;
+pub use fetch::{build_client, build_client_with, fetch_matches_since, ClientOptions};
+pub use models::*;
+pub use ripper::Ripper;
+pub use stats::{OutputDirStats, Stats};
+pub use stream::MatchStream;
+pub use windows::{generate_work_windows, WorkWindow};
+
+pub const BASE_URL: &str = "https://backend.production.omeda-aws.com/api/public/get-matches-since";
+pub const FIRST_EPOCH: u64 = 1669882894; // Thursday, December 1, 2022 08:21:34 AM GMT
+pub const WINDOW_SIZE: u64 = 3600; // 1 hour
+pub const POOL_SIZE: u64 = 10; // When the core count can't be read
+pub const MAX_POOL_SIZE: u64 = 32; // Cap on the detected default, so big machines don't hammer the backend
+pub const MAX_RETRIES: u32 = 5;
+pub const BASE_RETRY_DELAY: Duration = Duration::from_millis(500);
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+pub const REQUEST_TIMEOUT_SECS: u64 = 30;
+pub const WRITE_BUFFER: usize = 32;
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const OUTAGE_THRESHOLD: u32 = 20; // Connection failures in a row, across every worker, before pausing
+pub const OUTAGE_COOLDOWN_SECS: u64 = 60;
+pub const OUTAGE_MAX_PAUSES: u32 = 3;
+pub const COMPACT_TARGET_BYTES: u64 = 64 * 1024 * 1024; // Default size `compact` merges small files up to
+pub const POLL_INTERVAL_SECS: u64 = 900; // How often --daily-quota checks for new matches once caught up
@@ -0,0 +1,46 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use pred_ripper::{progress, Config, LogFormat};
+use tracing::Level;
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
+
+/// Stderr, with the progress bar hidden while each line is written so the two don't draw over each other
+struct ProgressAwareStderr;
+
+impl Write for ProgressAwareStderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        progress::suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Logs go to stderr so they never mix with output like --verify's gap list on stdout
+pub fn init(config: &Config) -> io::Result<()> {
+    let writer = match &config.log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            BoxMakeWriter::new((|| ProgressAwareStderr).and(Mutex::new(file)))
+        }
+        None => BoxMakeWriter::new(|| ProgressAwareStderr),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .with_writer(writer)
+        // Colour codes would end up in the log file as garbage
+        .with_ansi(config.log_file.is_none());
+
+    match config.log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::RipperError,
+    output::{read_matches, write_atomically},
+    windows::{file_format, WorkWindow},
+};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Index of every file saved into the output folder, kept at `{output_dir}/manifest.json`
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+    /// Work windows that were collected all the way to their end, see `completed_windows`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<WorkWindow>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Path relative to the output folder
+    pub file: String,
+    pub start_epoch: u64,
+    pub end_epoch: u64,
+    pub match_count: usize,
+    pub bytes: u64,
+    /// Hex SHA-256 of the file. Missing for files saved before checksums were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// The work window whose matches are in the file. Missing for files from --cursor, merge and compact, which
+    /// aren't tied to a single window, and for files saved before windows were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<WorkWindow>,
+}
+
+impl Manifest {
+    /// Loads the manifest from the output folder, or an empty one if there isn't one yet
+    pub fn load(output_dir: &Path) -> Result<Self, RipperError> {
+        match File::open(output_dir.join(MANIFEST_FILE)) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, output_dir: &Path) -> Result<(), RipperError> {
+        write_atomically(&output_dir.join(MANIFEST_FILE), |file| {
+            serde_json::to_writer_pretty(file, self)?;
+            Ok(())
+        })
+    }
+
+    /// Adds an entry, replacing any previous entry for the same file
+    pub fn record(&mut self, entry: ManifestEntry) {
+        self.files.retain(|existing| existing.file != entry.file);
+        self.files.push(entry);
+    }
+
+    /// Marks a work window as collected all the way through. Only called once everything it found is saved
+    pub fn record_window(&mut self, window: WorkWindow) {
+        if !self.windows.contains(&window) {
+            self.windows.push(window);
+        }
+    }
+
+    /// Whether anything here says which window its files came from. Output from before windows were recorded
+    /// only has `completed_ranges` to go on
+    pub fn has_windows(&self) -> bool {
+        !self.windows.is_empty() || self.files.iter().any(|entry| entry.window.is_some())
+    }
+
+    /// The work windows a resumed run can skip. A window counts as complete only if it was collected all the
+    /// way to its end, saved at least one match, and every file it saved still parses. A window that finished
+    /// with no matches is left out so it's retried in case matches have arrived since, and so is one that was
+    /// cut short, whatever it managed to save. Files that can't be read back (csv, parquet) are trusted on their
+    /// count alone
+    pub fn completed_windows(&self, output_dir: &Path) -> Vec<WorkWindow> {
+        self.windows
+            .iter()
+            .filter(|&window| {
+                let entries: Vec<&ManifestEntry> = self
+                    .files
+                    .iter()
+                    .filter(|entry| entry.window.as_ref() == Some(window))
+                    .collect();
+                entries.iter().map(|entry| entry.match_count).sum::<usize>() > 0
+                    && entries.iter().all(|entry| still_parses(output_dir, entry))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The epoch ranges a resumed run can skip, for manifests from before windows were recorded. The ranges
+    /// come from the matches in the files, not the windows that saved them, so this is only a best guess. Only
+    /// files that had matches and still parse are trusted, so a zero-count entry's window is retried
+    pub fn completed_ranges(&self, output_dir: &Path) -> Vec<(u64, u64)> {
+        self.files
+            .iter()
+            .filter(|entry| entry.match_count > 0 && still_parses(output_dir, entry))
+            .map(|entry| (entry.start_epoch, entry.end_epoch))
+            .collect()
+    }
+}
+
+fn still_parses(output_dir: &Path, entry: &ManifestEntry) -> bool {
+    let path = output_dir.join(&entry.file);
+    let Some(format) = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(file_format)
+    else {
+        return false;
+    };
+    match read_matches(&path, format) {
+        Ok(Some(matches)) => !matches.is_empty(),
+        Ok(None) => path.exists(),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use tracing::{info, warn};
+
+use crate::{
+    checksum::write_checksums,
+    config::Config,
+    error::RipperError,
+    manifest::Manifest,
+    models::PredecessorMatch,
+    output::{for_each_saved_batch, read_matches, save_matches},
+    windows::file_format,
+};
+
+/// What a merge did, logged once it's finished
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MergeSummary {
+    /// Matches written to the merged folder
+    pub matches: usize,
+    /// Matches left out because an identical copy was already merged
+    pub duplicates: usize,
+    /// Matches left out because a different version with the same id was already merged
+    pub conflicts: usize,
+}
+
+fn content_hash(m: &PredecessorMatch) -> Result<u64, RipperError> {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(m)?.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Combines the match files in `inputs` into `output_dir`, keeping the first copy of every match id. When the same
+/// id turns up with different contents, the version from the earlier input wins and the conflict is logged.
+///
+/// Batches keep the files they came in, minus any matches already merged. A batch covering the same range as a
+/// file that's already been written is folded into that file, which is then written again with both. The merged folder is
+/// always flat (no --partition), and in the configured --format, which has to be one that can be read back
+pub fn merge_dirs(
+    inputs: &[PathBuf],
+    output_dir: &Path,
+    config: &Config,
+) -> Result<MergeSummary, RipperError> {
+    if !config.format.holds_matches() {
+        return Err(RipperError::Merge(format!(
+            "can't merge into {} files, they can't be read back as matches",
+            config.format.extension()
+        )));
+    }
+    if let Some(input) = inputs.iter().find(|input| same_dir(input, output_dir)) {
+        return Err(RipperError::Merge(format!(
+            "{:?} is both an input and the output",
+            input
+        )));
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+    let mut manifest = Manifest::load(output_dir)?;
+    let mut seen: HashMap<String, u64> = HashMap::new();
+    let mut summary = MergeSummary::default();
+
+    for input in inputs {
+        info!("Merging {:?}", input);
+        for_each_saved_batch(input, |matches| {
+            let mut batch = Vec::new();
+            for m in matches {
+                let hash = content_hash(m)?;
+                match seen.entry(m.match_id.clone()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(hash);
+                        batch.push(m.clone());
+                    }
+                    Entry::Occupied(entry) if *entry.get() == hash => summary.duplicates += 1,
+                    Entry::Occupied(_) => {
+                        warn!(
+                            "Match {} in {:?} differs from the copy already merged, keeping the earlier one",
+                            m.match_id, input
+                        );
+                        summary.conflicts += 1;
+                    }
+                }
+            }
+            if batch.is_empty() {
+                return Ok(());
+            }
+            summary.matches += batch.len();
+
+            let start_epoch = batch[0].end_time.timestamp() as u64;
+            let end_epoch = batch[batch.len() - 1].end_time.timestamp() as u64;
+            // The file's matches go into the batch and the file is written again in full, rather than kept
+            // alongside it, where save_matches would only have given the batch a `.dupN` name of its own
+            let same_range: Vec<String> = manifest
+                .files
+                .iter()
+                .filter(|e| e.start_epoch == start_epoch && e.end_epoch == end_epoch)
+                .map(|e| e.file.clone())
+                .collect();
+            for file in same_range {
+                let path = output_dir.join(&file);
+                let Some(format) = file_format(&file) else {
+                    continue;
+                };
+                if let Some(existing) = read_matches(&path, format)? {
+                    batch.extend(existing);
+                    std::fs::remove_file(&path)?;
+                    manifest.files.retain(|e| e.file != file);
+                }
+            }
+            batch.sort_by_key(|m| m.end_time);
+
+            for (entry, _) in save_matches(
+                output_dir,
+                config.format,
+                config.pretty,
+                None,
+                config.gzip_files,
+                None,
+                &batch,
+            )? {
+                manifest.record(entry);
+            }
+            Ok(())
+        })?;
+    }
+
+    manifest.save(output_dir)?;
+    write_checksums(output_dir, &manifest)?;
+
+    info!(
+        "Merged {} matches into {:?}, skipped {} duplicates and {} conflicting copies",
+        summary.matches, output_dir, summary.duplicates, summary.conflicts
+    );
+    Ok(summary)
+}
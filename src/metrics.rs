@@ -0,0 +1,221 @@
+use std::{
+    fmt::Write as _,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tracing::{info, warn};
+
+use crate::error::RipperError;
+
+/// Sub-buckets per doubling of latency. Percentiles come out within 1/8 of the true value
+const SUB_BUCKETS: u64 = 8;
+/// Latencies from 2^MAX_POWER ms (about 17 minutes) up all land in the last bucket
+const MAX_POWER: u32 = 20;
+const LATENCY_BUCKETS: usize = (SUB_BUCKETS * (MAX_POWER - 2) as u64 + 1) as usize;
+
+/// Request latencies in milliseconds, bucketed so any number of requests take the same fixed space. Values
+/// under 8ms get a bucket each, and every doubling above that is split into 8 equal buckets
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn bucket(ms: u64) -> usize {
+        if ms < SUB_BUCKETS {
+            return ms as usize;
+        }
+        let power = ms.ilog2().min(MAX_POWER);
+        if power == MAX_POWER {
+            return LATENCY_BUCKETS - 1;
+        }
+        let sub = (ms >> (power - 3)) & (SUB_BUCKETS - 1);
+        (SUB_BUCKETS * (power as u64 - 2) + sub) as usize
+    }
+
+    /// The smallest latency that lands in `bucket`
+    fn lower_bound(bucket: usize) -> u64 {
+        let bucket = bucket as u64;
+        if bucket < SUB_BUCKETS {
+            return bucket;
+        }
+        let power = bucket / SUB_BUCKETS + 2;
+        let sub = bucket % SUB_BUCKETS;
+        (SUB_BUCKETS + sub) << (power - 3)
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let ms = latency.as_millis().min(u64::MAX as u128) as u64;
+        self.buckets[Self::bucket(ms)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).sum()
+    }
+
+    /// The latency `percentile` (0 to 100) of the recorded requests took at most, rounded down to the start of its
+    /// bucket. None before anything is recorded
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        // The rank of the request that's at the percentile, counting from 1
+        let rank = ((percentile / 100.0 * count as f64).ceil() as u64).clamp(1, count);
+        let mut seen = 0;
+        for (bucket, counter) in self.buckets.iter().enumerate() {
+            seen += counter.load(Ordering::Relaxed);
+            if seen >= rank {
+                return Some(Duration::from_millis(Self::lower_bound(bucket)));
+            }
+        }
+        None
+    }
+}
+
+/// Live counters for a run. Always kept, and served in the Prometheus text format with --metrics-addr
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Every attempt, retries included
+    pub requests: AtomicU64,
+    pub retries: AtomicU64,
+    pub client_errors: AtomicU64,
+    pub server_errors: AtomicU64,
+    pub matches_saved: AtomicU64,
+    pub bytes_written: AtomicU64,
+    /// Work windows being collected right now
+    pub active_workers: AtomicI64,
+    /// How long successful requests took, from sending to the last byte parsed
+    pub request_latency: LatencyHistogram,
+    /// Body bytes of the successful responses
+    pub response_bytes: AtomicU64,
+    /// Time spent in successful requests, what `response_bytes` came in over
+    pub response_micros: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts a response with a non-success status as a 4xx or a 5xx
+    pub fn record_status(&self, status: u16) {
+        match status {
+            400..=499 => self.client_errors.fetch_add(1, Ordering::Relaxed),
+            500..=599 => self.server_errors.fetch_add(1, Ordering::Relaxed),
+            _ => return,
+        };
+    }
+
+    /// Counts a successful response of `bytes` that took `elapsed`
+    pub fn record_response(&self, elapsed: Duration, bytes: u64) {
+        self.request_latency.record(elapsed);
+        self.response_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.response_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Response bytes per second of request time, as opposed to wall time. A low number means the network or the
+    /// backend is what's slow, rather than the disk or the rate limit
+    pub fn response_bytes_per_sec(&self) -> Option<f64> {
+        let micros = self.response_micros.load(Ordering::Relaxed);
+        (micros > 0)
+            .then(|| self.response_bytes.load(Ordering::Relaxed) as f64 / (micros as f64 / 1e6))
+    }
+
+    pub fn render(&self) -> String {
+        let counters = [
+            (
+                "requests_total",
+                "Requests sent to the backend, retries included",
+                &self.requests,
+            ),
+            ("retries_total", "Requests that were retried", &self.retries),
+            (
+                "client_errors_total",
+                "Responses with a 4xx status",
+                &self.client_errors,
+            ),
+            (
+                "server_errors_total",
+                "Responses with a 5xx status",
+                &self.server_errors,
+            ),
+            (
+                "matches_saved_total",
+                "Matches written to the output folder",
+                &self.matches_saved,
+            ),
+            (
+                "bytes_written_total",
+                "Bytes of match files written",
+                &self.bytes_written,
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP pred_ripper_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE pred_ripper_{} counter", name);
+            let _ = writeln!(
+                out,
+                "pred_ripper_{} {}",
+                name,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "# HELP pred_ripper_active_workers Work windows being collected"
+        );
+        let _ = writeln!(out, "# TYPE pred_ripper_active_workers gauge");
+        let _ = writeln!(
+            out,
+            "pred_ripper_active_workers {}",
+            self.active_workers.load(Ordering::Relaxed)
+        );
+        out
+    }
+}
+
+/// Serves `metrics` to any request on `addr` until the task is dropped. Scrapers only ever GET one path, so
+/// the request itself isn't looked at
+pub async fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<(), RipperError> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            // Just enough of the request to get it off the socket
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await;
+            let body = metrics.render();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            if let Err(err) = stream.write_all(response.as_bytes()).await {
+                warn!("Failed to send metrics: {}", err);
+            }
+        });
+    }
+}
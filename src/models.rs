@@ -1,16 +1,163 @@
-use serde::{Deserialize, Serialize};
+use std::{convert::Infallible, fmt, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Declares an enum over a string field from the backend. Known values are matched case-insensitively and
+/// anything else lands in `Other` untouched, so a new value from the backend never breaks deserialization.
+///
+/// Known values are saved back out in their canonical spelling, not as the backend sent them: `naeast` comes
+/// back as `NAEAST`. The backend has only ever sent the canonical spelling, and --keep-raw has the exact bytes
+/// for anyone who needs them
+macro_rules! string_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $value:literal,)* }) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum $name {
+            $($variant,)*
+            Other(String),
+        }
+
+        impl $name {
+            pub const KNOWN: &'static [&'static str] = &[$($value,)*];
+
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => value,
+                }
+            }
+
+            /// Like `from_str`, but rejects anything that isn't a known value. Used for filters, where a typo
+            /// would otherwise silently match nothing
+            pub fn parse_known(s: &str) -> Result<Self, String> {
+                match s.parse::<Self>() {
+                    Ok($name::Other(_)) => Err(format!(
+                        "unknown {} {:?}, expected one of: {}",
+                        stringify!($name),
+                        s,
+                        Self::KNOWN.join(", ")
+                    )),
+                    Ok(value) => Ok(value),
+                    Err(never) => match never {},
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $(if s.eq_ignore_ascii_case($value) {
+                    return Ok($name::$variant);
+                })*
+                Ok($name::Other(s.to_string()))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        /// Any string is accepted, the known values are listed as examples
+        #[cfg(feature = "schema")]
+        impl schemars::JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+                schemars::schema::SchemaObject {
+                    instance_type: Some(schemars::schema::InstanceType::String.into()),
+                    metadata: Some(Box::new(schemars::schema::Metadata {
+                        examples: Self::KNOWN.iter().map(|value| (*value).into()).collect(),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                }
+                .into()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                Ok(value.parse().unwrap_or_else(|never| match never {}))
+            }
+        }
+    };
+}
+
+string_enum!(GameMode {
+    Pvp => "PVP",
+    Ranked => "RANKED",
+    Coop => "COOP",
+    Solo => "SOLO",
+    Brawl => "BRAWL",
+    Custom => "CUSTOM",
+});
+
+string_enum!(Region {
+    NaEast => "NAEAST",
+    NaCentral => "NACENTRAL",
+    NaWest => "NAWEST",
+    Europe => "EUROPE",
+    Asia => "ASIA",
+    Oceania => "OCEANIA",
+    Sea => "SEA",
+    Brazil => "BRAZIL",
+    MiddleEast => "MIDDLEEAST",
+    Japan => "JAPAN",
+});
+
+string_enum!(MatchEndReason {
+    CoreDestroyed => "CORE_DESTROYED",
+    Surrender => "SURRENDER",
+    Remake => "REMAKE",
+});
+
+/// Time since the start of a match. The backend sends a bare integer with no unit, which lines up with
+/// `game_duration` as seconds. This is the only place that assumption lives
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct GameTime(pub i64);
+
+impl GameTime {
+    pub fn as_seconds(self) -> i64 {
+        self.0
+    }
+
+    /// Negative times (which shouldn't happen, but the backend doesn't promise) clamp to zero
+    pub fn as_duration(self) -> Duration {
+        Duration::from_secs(self.0.max(0) as u64)
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct PredecessorMatch {
     pub winning_team: i64,
     pub game_duration: i64,
-    pub game_mode: String,
+    pub game_mode: GameMode,
     pub match_id: String,
-    pub region: String,
-    pub start_time: String,
-    pub end_time: String,
-    pub match_end_reason: String,
+    pub region: Region,
+    #[serde(with = "crate::time::backend_time")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub start_time: DateTime<Utc>,
+    #[serde(with = "crate::time::backend_time")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub end_time: DateTime<Utc>,
+    pub match_end_reason: MatchEndReason,
     pub player_data: Vec<PlayerData>,
     pub hero_kills: Vec<HeroKill>,
     pub structure_destructions: Vec<StructureDestruction>,
@@ -18,6 +165,7 @@ pub struct PredecessorMatch {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct PlayerData {
     pub player_id: String,
@@ -34,7 +182,42 @@ pub struct PlayerData {
     pub inventory_data: Option<Vec<InventoryData>>,
 }
 
+/// Per-minute and ratio stats that aren't in the API response but everyone ends up computing
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct DerivedStats {
+    pub kda: f64,
+    pub gold_per_min: f64,
+    pub cs_per_min: f64,
+    pub damage_per_min: f64,
+}
+
+impl PlayerData {
+    /// `game_duration` is the match's, in seconds. A zero-length match gives zero for the per-minute stats
+    pub fn derived_stats(&self, game_duration: i64) -> DerivedStats {
+        let combat = &self.combat_data;
+        // No deaths counts as a perfect game, so KDA is just kills + assists
+        let kda = (combat.kills + combat.assists) as f64 / combat.deaths.max(1) as f64;
+
+        let minutes = game_duration as f64 / 60.0;
+        let per_min = |value: i64| {
+            if minutes > 0.0 {
+                value as f64 / minutes
+            } else {
+                0.0
+            }
+        };
+
+        DerivedStats {
+            kda,
+            gold_per_min: per_min(self.income_data.gold_earned),
+            cs_per_min: per_min(self.minion_data.minions_killed),
+            damage_per_min: per_min(self.damage_heal_data.total_damage_dealt_to_heroes),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MinionData {
     pub minions_killed: i64,
@@ -45,6 +228,7 @@ pub struct MinionData {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CombatData {
     pub kills: i64,
@@ -55,6 +239,7 @@ pub struct CombatData {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct DamageHealData {
     magical_damage_taken_from_heroes: i64,
@@ -84,7 +269,32 @@ pub struct DamageHealData {
     total_shielding_received: Option<i64>,
 }
 
+impl DamageHealData {
+    pub fn total_damage_dealt_to_heroes(&self) -> i64 {
+        self.total_damage_dealt_to_heroes
+    }
+
+    /// Whether each of the optional fields was sent, by its name in the API
+    pub fn optional_fields(&self) -> [(&'static str, bool); 6] {
+        [
+            (
+                "largestCriticalStrike",
+                self.largest_critical_strike.is_some(),
+            ),
+            ("totalHealingDone", self.total_healing_done.is_some()),
+            ("itemHealingDone", self.item_healing_done.is_some()),
+            ("crestHealingDone", self.crest_healing_done.is_some()),
+            ("utilityHealingDone", self.utility_healing_done.is_some()),
+            (
+                "totalShieldingReceived",
+                self.total_shielding_received.is_some(),
+            ),
+        ]
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct WardsData {
     pub wards_placed: i64,
@@ -94,14 +304,16 @@ pub struct WardsData {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct WardData {
     pub type_id: i64,
-    pub game_time: i64,
+    pub game_time: GameTime,
     pub location: Location,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Location {
     pub x: f64,
@@ -110,6 +322,7 @@ pub struct Location {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct IncomeData {
     pub gold_earned: i64,
@@ -119,22 +332,25 @@ pub struct IncomeData {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Transaction {
     pub item_id: i64,
     pub transaction_type: i64,
-    pub game_time: i64,
+    pub game_time: GameTime,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct AbilityData {
     pub ability_input_tag: Option<String>,
     pub ability_slot: Option<i64>,
-    pub game_time: i64,
+    pub game_time: GameTime,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct InventoryData {
     pub item_slot: i64,
@@ -142,6 +358,7 @@ pub struct InventoryData {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct HeroKill {
     pub killed_player_id: String,
@@ -151,10 +368,11 @@ pub struct HeroKill {
     pub killer_entity_type: String,
     pub is_first_blood: bool,
     pub location: Location,
-    pub game_time: i64,
+    pub game_time: GameTime,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct StructureDestruction {
     pub destruction_player_id: String,
@@ -162,15 +380,16 @@ pub struct StructureDestruction {
     pub structure_entity_type: String,
     pub location: Location,
     pub team_id: i64,
-    pub game_time: i64,
+    pub game_time: GameTime,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ObjectiveKill {
     pub killed_entity_type: String,
     pub killer_player_id: String,
     pub killer_hero_name: String,
     pub location: Location,
-    pub game_time: i64,
+    pub game_time: GameTime,
 }
@@ -0,0 +1,672 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc, Mutex},
+};
+
+use chrono::DateTime;
+use flate2::{read::GzDecoder, write::GzEncoder};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+#[cfg(feature = "polars")]
+use crate::dataframe;
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteExport;
+use crate::{
+    anonymize::Anonymizer,
+    cache::{cache_path, read_cache, write_cache},
+    checkpoint::CHECKPOINT_FILE,
+    checksum::{append_checksums, write_checksums, HashingWriter},
+    config::{CacheFormat, OutputFormat, Partition},
+    cursor::CURSOR_FILE,
+    error::RipperError,
+    export,
+    manifest::{Manifest, ManifestEntry, MANIFEST_FILE},
+    metrics::Metrics,
+    models::{PredecessorMatch, Region},
+    stats::{Stats, SUMMARY_FILE},
+    windows::{match_files, parse_match_file_name, WorkWindow},
+};
+
+/// Added to the name of every match file written with --gzip-files
+pub const GZIP_SUFFIX: &str = ".gz";
+
+/// Comes before a number after the epochs in a match file's name when another file already had its name, see
+/// `save_matches`
+pub const DUP_SUFFIX: &str = ".dup";
+
+/// Writes to `{path}.tmp` and renames it into place once everything is flushed to disk, so a crash
+/// mid-write never leaves a truncated file at `path` for --resume to mistake as complete
+pub fn write_atomically<F>(path: &Path, write: F) -> Result<(), RipperError>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), RipperError>,
+{
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let result = (|| {
+        let mut file = BufWriter::new(File::create(&tmp_path)?);
+        write(&mut file)?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Folder under the output folder a batch goes into, based on its first match
+fn partition_dir(
+    partition: Option<Partition>,
+    first: &PredecessorMatch,
+    first_epoch: u64,
+) -> PathBuf {
+    match partition {
+        None => PathBuf::new(),
+        // Unknown regions come straight from the backend, so keep them to something safe as a folder name
+        Some(Partition::Region) => first
+            .region
+            .as_str()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>()
+            .into(),
+        Some(Partition::Date) => {
+            let date = DateTime::from_timestamp(first_epoch as i64, 0).unwrap_or_default();
+            date.format("%Y/%m/%d").to_string().split('/').collect()
+        }
+    }
+}
+
+/// Splits `matches` into runs that each come to at most `max_bytes`. Sizes are measured as compact JSON, so
+/// pretty, csv and parquet files only roughly keep to the limit. A single match bigger than `max_bytes` still
+/// gets a file of its own
+fn split_by_size(
+    matches: &[PredecessorMatch],
+    max_bytes: u64,
+) -> Result<Vec<&[PredecessorMatch]>, RipperError> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    // The array's brackets
+    let mut size = 2;
+
+    for (i, m) in matches.iter().enumerate() {
+        // Plus a comma or newline between matches
+        let match_size = serde_json::to_vec(m)?.len() as u64 + 1;
+        if i > start && size + match_size > max_bytes {
+            parts.push(&matches[start..i]);
+            start = i;
+            size = 2;
+        }
+        size += match_size;
+    }
+    parts.push(&matches[start..]);
+
+    Ok(parts)
+}
+
+fn write_matches<W: Write>(
+    mut file: W,
+    format: OutputFormat,
+    pretty: bool,
+    matches: &[PredecessorMatch],
+) -> Result<(), RipperError> {
+    match format {
+        OutputFormat::Json if pretty => serde_json::to_writer_pretty(&mut file, matches)?,
+        OutputFormat::Json => serde_json::to_writer(&mut file, matches)?,
+        OutputFormat::Ndjson => {
+            for m in matches {
+                serde_json::to_writer(&mut file, m)?;
+                file.write_all(b"\n")?;
+            }
+        }
+        OutputFormat::Csv => {
+            // The csv writer emits the header row once, before the first record
+            let mut writer = csv::Writer::from_writer(&mut file);
+            for row in matches.iter().flat_map(export::player_rows) {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        #[cfg(feature = "polars")]
+        OutputFormat::Parquet => {
+            let mut frame = dataframe::matches_to_dataframe(matches)?;
+            polars::prelude::ParquetWriter::new(&mut file).finish(&mut frame)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the file's checksum. With `gzip` it's the checksum of the compressed bytes, as they are on disk
+fn write_match_file(
+    path: &Path,
+    format: OutputFormat,
+    pretty: bool,
+    gzip: bool,
+    matches: &[PredecessorMatch],
+) -> Result<String, RipperError> {
+    let mut sha256 = String::new();
+    write_atomically(path, |file| {
+        let mut file = HashingWriter::new(file);
+        if gzip {
+            let mut encoder = GzEncoder::new(&mut file, flate2::Compression::default());
+            write_matches(&mut encoder, format, pretty, matches)?;
+            encoder.finish()?;
+        } else {
+            write_matches(&mut file, format, pretty, matches)?;
+        }
+        sha256 = file.finish();
+        Ok(())
+    })?;
+    Ok(sha256)
+}
+
+/// `pretty` only affects the json format. Pretty files are several times bigger, so it's meant for spot checks.
+/// With `Partition::Region` every match should be from the same region, MatchWriter splits batches up before they get here.
+///
+/// With `gzip` every file is compressed as it's written and gets a `.gz` on the end of its name.
+///
+/// A file that's already there is never overwritten: the new one gets a `.dupN` after its epochs instead.
+///
+/// With `max_file_bytes`, a batch too big for one file is written as `{start}-{end}.part0.{ext}`, `.part1`, ...
+/// all named after the whole batch. Returns the manifest entry for every file along with the matches in it, which
+/// is nothing at all for an empty batch
+pub fn save_matches<'a>(
+    output_dir: &Path,
+    format: OutputFormat,
+    pretty: bool,
+    partition: Option<Partition>,
+    gzip: bool,
+    max_file_bytes: Option<u64>,
+    matches: &'a [PredecessorMatch],
+) -> Result<Vec<(ManifestEntry, &'a [PredecessorMatch])>, RipperError> {
+    let (Some(first), Some(last)) = (matches.first(), matches.last()) else {
+        info!("No matches to save, not writing a file");
+        return Ok(Vec::new());
+    };
+    let first_match_endtime_epoch = first.end_time.timestamp() as u64;
+    let last_match_endtime_epoch = last.end_time.timestamp() as u64;
+
+    let dir = output_dir.join(partition_dir(partition, first, first_match_endtime_epoch));
+    std::fs::create_dir_all(&dir)?;
+
+    let parts = match max_file_bytes {
+        Some(max_bytes) => split_by_size(matches, max_bytes)?,
+        None => vec![matches],
+    };
+
+    let gzip_suffix = if gzip { GZIP_SUFFIX } else { "" };
+    let file_names = |dup_suffix: &str| -> Vec<PathBuf> {
+        (0..parts.len())
+            .map(|i| {
+                let part_suffix = if parts.len() > 1 {
+                    format!(".part{}", i)
+                } else {
+                    String::new()
+                };
+                dir.join(format!(
+                    "{}-{}{}{}.{}{}",
+                    first_match_endtime_epoch,
+                    last_match_endtime_epoch,
+                    dup_suffix,
+                    part_suffix,
+                    format.extension(),
+                    gzip_suffix
+                ))
+            })
+            .collect()
+    };
+    // Another batch can start and end on the same seconds as this one, e.g. a busy second split across two
+    // windows. Its file is left alone and this one gets `.dup1`, `.dup2`, ... after the epochs instead
+    let mut file_names_to_write = file_names("");
+    let mut dup = 0;
+    while file_names_to_write.iter().any(|name| name.exists()) {
+        dup += 1;
+        file_names_to_write = file_names(&format!("{}{}", DUP_SUFFIX, dup));
+    }
+    if dup > 0 {
+        warn!(
+            "Matches from {} to {} were already saved by another batch, saving these as a separate file",
+            first_match_endtime_epoch, last_match_endtime_epoch
+        );
+    }
+
+    let mut saved = Vec::with_capacity(parts.len());
+    for (part, file_name) in parts.iter().zip(file_names_to_write) {
+        let sha256 = write_match_file(&file_name, format, pretty, gzip, part)?;
+        saved.push((
+            ManifestEntry {
+                file: file_name
+                    .strip_prefix(output_dir)
+                    .unwrap_or(&file_name)
+                    .to_string_lossy()
+                    .into_owned(),
+                start_epoch: first_match_endtime_epoch,
+                end_epoch: last_match_endtime_epoch,
+                match_count: part.len(),
+                bytes: std::fs::metadata(&file_name)?.len(),
+                sha256: Some(sha256),
+                window: None,
+            },
+            *part,
+        ));
+    }
+
+    info!(
+        match_count = matches.len(),
+        file_count = saved.len(),
+        start_epoch = first_match_endtime_epoch,
+        end_epoch = last_match_endtime_epoch,
+        "Saved matches"
+    );
+
+    Ok(saved)
+}
+
+/// A match id as a file name. Ids come straight from the backend, so the characters a path can't hold (separators,
+/// `:*?"<>|` and control characters) are written as `%XX` for each of their UTF-8 bytes, and so is `%` itself, so
+/// two ids never end up with the same name. An id that would take the name of one of the files the ripper keeps
+/// next to the matches (manifest.json, ...) has its first character escaped too
+pub fn match_file_stem(match_id: &str, format: OutputFormat) -> String {
+    let mut stem = String::with_capacity(match_id.len());
+    for c in match_id.chars() {
+        if matches!(
+            c,
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '%'
+        ) || c.is_control()
+        {
+            escape_char(&mut stem, c);
+        } else {
+            stem.push(c);
+        }
+    }
+
+    let file_name = format!("{}.{}", stem, format.extension());
+    if [MANIFEST_FILE, CHECKPOINT_FILE, CURSOR_FILE, SUMMARY_FILE].contains(&file_name.as_str()) {
+        let mut chars = stem.chars();
+        let mut escaped = String::new();
+        escape_char(&mut escaped, chars.next().unwrap_or_default());
+        escaped.push_str(chars.as_str());
+        escaped
+    } else {
+        stem
+    }
+}
+
+fn escape_char(out: &mut String, c: char) {
+    let mut buf = [0; 4];
+    for byte in c.encode_utf8(&mut buf).bytes() {
+        out.push_str(&format!("%{:02X}", byte));
+    }
+}
+
+/// --split-by-match: writes every match to a file of its own, `{match_id}.{ext}`, instead of one file per batch.
+/// A match saved again (a repeated run, --resume) overwrites its own file with the same contents, so saving is
+/// idempotent per match. Each manifest entry's epochs are the match's end time. Nothing in the names says these
+/// are match files, so it's the manifest entries that make them show up in `match_files`
+pub fn save_match_files<'a>(
+    output_dir: &Path,
+    format: OutputFormat,
+    pretty: bool,
+    partition: Option<Partition>,
+    gzip: bool,
+    matches: &'a [PredecessorMatch],
+) -> Result<Vec<(ManifestEntry, &'a [PredecessorMatch])>, RipperError> {
+    let gzip_suffix = if gzip { GZIP_SUFFIX } else { "" };
+    let mut saved = Vec::with_capacity(matches.len());
+
+    for m in matches {
+        let end_epoch = m.end_time.timestamp() as u64;
+        let dir = output_dir.join(partition_dir(partition, m, end_epoch));
+        std::fs::create_dir_all(&dir)?;
+        let file_name = dir.join(format!(
+            "{}.{}{}",
+            match_file_stem(&m.match_id, format),
+            format.extension(),
+            gzip_suffix
+        ));
+
+        let matches = std::slice::from_ref(m);
+        let sha256 = write_match_file(&file_name, format, pretty, gzip, matches)?;
+        saved.push((
+            ManifestEntry {
+                file: file_name
+                    .strip_prefix(output_dir)
+                    .unwrap_or(&file_name)
+                    .to_string_lossy()
+                    .into_owned(),
+                start_epoch: end_epoch,
+                end_epoch,
+                match_count: 1,
+                bytes: std::fs::metadata(&file_name)?.len(),
+                sha256: Some(sha256),
+                window: None,
+            },
+            matches,
+        ));
+    }
+
+    info!(file_count = saved.len(), "Saved a file per match");
+    Ok(saved)
+}
+
+/// Reads a file written by `save_matches` back in, decompressing it first if its name ends in `.gz`. CSV and
+/// Parquet files only hold per-player rows, so they can't be turned back into matches and come back as `None`
+pub fn read_matches(
+    path: &Path,
+    format: OutputFormat,
+) -> Result<Option<Vec<PredecessorMatch>>, RipperError> {
+    let file = File::open(path)?;
+    let file: Box<dyn BufRead> = if path.to_string_lossy().ends_with(GZIP_SUFFIX) {
+        Box::new(BufReader::new(GzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+    match format {
+        OutputFormat::Json => Ok(Some(serde_json::from_reader(file)?)),
+        OutputFormat::Ndjson => {
+            let mut matches = Vec::new();
+            for line in file.lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    matches.push(serde_json::from_str(&line)?);
+                }
+            }
+            Ok(Some(matches))
+        }
+        OutputFormat::Csv => Ok(None),
+        #[cfg(feature = "polars")]
+        OutputFormat::Parquet => Ok(None),
+    }
+}
+
+/// Calls `f` with the matches from every file in the output folder, one file at a time so the whole
+/// collection never has to fit in memory. A file's cache is read instead of the file itself when there is one
+pub fn for_each_saved_batch<F>(output_dir: &Path, mut f: F) -> Result<(), RipperError>
+where
+    F: FnMut(&[PredecessorMatch]) -> Result<(), RipperError>,
+{
+    for (path, format) in match_files(output_dir)? {
+        let matches = match read_cache(&path)? {
+            Some(matches) => Some(matches),
+            None => read_matches(&path, format)?,
+        };
+        match matches {
+            Some(matches) => f(&matches)?,
+            None => warn!(
+                "Skipping {:?}, {} files can't be read back as matches",
+                path,
+                format.extension()
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// The ids of the matches saved in the output folder that ended at or after `since`. A run that picks a range back
+/// up (--resume, --update, --cursor) starts deduplicating with these, since a window that failed or was killed
+/// before its checkpoint is fetched again from before batches it already saved, and --update starts right on the
+/// newest saved match. Files are picked by the end epoch in the
+/// manifest or their name, and any with neither are read anyway
+pub fn saved_match_ids(output_dir: &Path, since: u64) -> Result<HashSet<String>, RipperError> {
+    let manifest = Manifest::load(output_dir)?;
+    let end_epochs: HashMap<&Path, u64> = manifest
+        .files
+        .iter()
+        .map(|entry| (Path::new(entry.file.as_str()), entry.end_epoch))
+        .collect();
+    let mut ids = HashSet::new();
+
+    for (path, format) in match_files(output_dir)? {
+        let relative = path.strip_prefix(output_dir).unwrap_or(&path);
+        let end_epoch = end_epochs.get(relative).copied().or_else(|| {
+            let (_, end_epoch, _) = parse_match_file_name(path.file_name()?.to_str()?)?;
+            Some(end_epoch)
+        });
+        if end_epoch.is_some_and(|end_epoch| end_epoch < since) {
+            continue;
+        }
+        let matches = match read_cache(&path)? {
+            Some(matches) => Some(matches),
+            None => read_matches(&path, format)?,
+        };
+        ids.extend(matches.into_iter().flatten().map(|m| m.match_id));
+    }
+    Ok(ids)
+}
+
+/// Removes the match files in the output folder that no longer parse, e.g. ones truncated by a kill before writes
+/// were atomic, along with their caches and manifest entries. Used before --resume so their windows are fetched
+/// again instead of being trusted. Returns how many were removed
+pub fn remove_corrupt_files(output_dir: &Path) -> Result<usize, RipperError> {
+    let mut manifest = Manifest::load(output_dir)?;
+    let mut removed = 0;
+
+    for (path, format) in match_files(output_dir)? {
+        let err = match read_matches(&path, format) {
+            Ok(_) => continue,
+            Err(err) => err,
+        };
+        warn!(
+            "Removing corrupt match file {:?} so its window is fetched again: {}",
+            path, err
+        );
+        std::fs::remove_file(&path)?;
+        let cache = cache_path(&path);
+        if cache.exists() {
+            std::fs::remove_file(cache)?;
+        }
+        let relative = path.strip_prefix(output_dir).unwrap_or(&path);
+        manifest
+            .files
+            .retain(|entry| Path::new(&entry.file) != relative);
+        removed += 1;
+    }
+
+    if removed > 0 {
+        manifest.save(output_dir)?;
+        write_checksums(output_dir, &manifest)?;
+    }
+    Ok(removed)
+}
+
+/// --single-file: every match as a line of NDJSON in one file, instead of a file per batch. A batch is serialized
+/// before the lock is taken and written whole while it's held, so any number of writers can share one of these
+/// without their lines interleaving. Lines come in the order batches are appended, which across parallel work
+/// windows isn't end time order
+pub struct SingleFile {
+    file: Mutex<BufWriter<File>>,
+}
+
+impl SingleFile {
+    /// Starts `path` over empty
+    pub fn create(path: &Path) -> Result<Self, RipperError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(SingleFile {
+            file: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// Adds a line for each match. Flushed before returning, so the file only ever ends in a whole line.
+    /// Returns the bytes written
+    pub fn append(&self, matches: &[PredecessorMatch]) -> Result<u64, RipperError> {
+        let mut lines = Vec::new();
+        for m in matches {
+            serde_json::to_writer(&mut lines, m)?;
+            lines.push(b'\n');
+        }
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&lines)?;
+        file.flush()?;
+        Ok(lines.len() as u64)
+    }
+}
+
+/// What the fetchers hand the MatchWriter
+pub(crate) enum WriterMessage {
+    /// New matches collected by a work window
+    Batch(WorkWindow, Vec<PredecessorMatch>),
+    /// The window reached its end, and every batch it found was sent ahead of this
+    WindowDone(WorkWindow),
+}
+
+/// Owns everything on the disk side. Runs on its own blocking thread, fed by the fetchers through a
+/// bounded channel, so when it falls behind the fetchers wait on a full channel instead of piling up memory
+pub(crate) struct MatchWriter {
+    pub output_dir: PathBuf,
+    pub format: OutputFormat,
+    pub pretty: bool,
+    pub partition: Option<Partition>,
+    pub gzip: bool,
+    pub cache_format: Option<CacheFormat>,
+    pub max_file_bytes: Option<u64>,
+    /// Write a file per match, named after its id, instead of a file per batch
+    pub split_by_match: bool,
+    pub anonymizer: Option<Anonymizer>,
+    pub manifest: Manifest,
+    /// Takes the place of the match files, the manifest and caches when set
+    pub single_file: Option<SingleFile>,
+    #[cfg(feature = "sqlite")]
+    pub sqlite: Option<SqliteExport>,
+    pub stats: Stats,
+    pub metrics: Arc<Metrics>,
+}
+
+impl MatchWriter {
+    /// Saves a batch of matches, recording the work window they came from in the manifest when there is one
+    pub fn save(
+        &mut self,
+        window: Option<&WorkWindow>,
+        mut matches: Vec<PredecessorMatch>,
+    ) -> Result<(), RipperError> {
+        // Before anything is written anywhere, the SQLite export included
+        if let Some(anonymizer) = &self.anonymizer {
+            for m in &mut matches {
+                anonymizer.anonymize_match(m);
+            }
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let Some(sqlite) = &mut self.sqlite {
+            sqlite.insert_matches(&matches)?;
+        }
+
+        if let Some(single_file) = &self.single_file {
+            let bytes = single_file.append(&matches)?;
+            self.stats.record_matches(&matches, bytes);
+            self.metrics
+                .matches_saved
+                .fetch_add(matches.len() as u64, Ordering::Relaxed);
+            self.metrics
+                .bytes_written
+                .fetch_add(bytes, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        // Region folders can only hold one region each, so a mixed batch becomes one file per region
+        let batches = if self.partition == Some(Partition::Region) {
+            let mut by_region: BTreeMap<Region, Vec<PredecessorMatch>> = BTreeMap::new();
+            for m in matches {
+                by_region.entry(m.region.clone()).or_default().push(m);
+            }
+            by_region.into_values().collect()
+        } else {
+            vec![matches]
+        };
+
+        let mut entries = Vec::new();
+        for matches in batches {
+            let saved = if self.split_by_match {
+                save_match_files(
+                    &self.output_dir,
+                    self.format,
+                    self.pretty,
+                    self.partition,
+                    self.gzip,
+                    &matches,
+                )?
+            } else {
+                save_matches(
+                    &self.output_dir,
+                    self.format,
+                    self.pretty,
+                    self.partition,
+                    self.gzip,
+                    self.max_file_bytes,
+                    &matches,
+                )?
+            };
+            for (mut entry, matches) in saved {
+                entry.window = window.cloned();
+                if let Some(cache_format) = self.cache_format {
+                    write_cache(&self.output_dir.join(&entry.file), cache_format, matches)?;
+                }
+                self.stats.record_matches(matches, entry.bytes);
+                self.metrics
+                    .matches_saved
+                    .fetch_add(matches.len() as u64, Ordering::Relaxed);
+                self.metrics
+                    .bytes_written
+                    .fetch_add(entry.bytes, Ordering::Relaxed);
+                self.manifest.record(entry.clone());
+                entries.push(entry);
+            }
+        }
+
+        // Only appended to here. The manifest is written as windows finish and once more when the run ends,
+        // rather than rewritten in full after every batch
+        append_checksums(&self.output_dir, &entries)
+    }
+
+    /// Records that `window` is complete, so --resume can skip it
+    pub fn finish_window(&mut self, window: WorkWindow) -> Result<(), RipperError> {
+        if self.single_file.is_some() {
+            return Ok(());
+        }
+        self.manifest.record_window(window);
+        self.manifest.save(&self.output_dir)
+    }
+
+    /// Writes out the manifest, and checksums.txt afresh from it to drop lines for files saved over since.
+    /// Called once the run is over, however it ended, so the manifest has every file that made it to disk
+    pub fn save_manifest(&self) -> Result<(), RipperError> {
+        if self.single_file.is_some() {
+            return Ok(());
+        }
+        self.manifest.save(&self.output_dir)?;
+        write_checksums(&self.output_dir, &self.manifest)
+    }
+
+    /// Saves batches until every sender has been dropped, then hands back what was written.
+    /// Stops at the first batch that can't be saved and returns its error. Dropping the receiver makes the
+    /// fetchers' next send fail, so no window gets counted as done past matches that never made it to disk
+    pub fn run(
+        mut self,
+        mut receiver: mpsc::Receiver<WriterMessage>,
+    ) -> Result<Stats, RipperError> {
+        while let Some(message) = receiver.blocking_recv() {
+            let result = match message {
+                WriterMessage::Batch(window, matches) => self.save(Some(&window), matches),
+                WriterMessage::WindowDone(window) => self.finish_window(window),
+            };
+            if let Err(err) = result {
+                warn!("Failed to save matches, stopping the run: {}", err);
+                if let Err(manifest_err) = self.save_manifest() {
+                    warn!("Failed to save the manifest: {}", manifest_err);
+                }
+                return Err(err);
+            }
+        }
+        self.save_manifest()?;
+        Ok(self.stats)
+    }
+}
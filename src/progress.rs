@@ -0,0 +1,90 @@
+use std::{
+    io::IsTerminal,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        LazyLock,
+    },
+    time::Instant,
+};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Every bar is drawn through this, so log lines can be printed around them with `suspend`
+static MULTI: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Hides the progress bars while `f` runs, so anything it prints to stderr doesn't get drawn over
+pub fn suspend<F: FnOnce() -> R, R>(f: F) -> R {
+    MULTI.suspend(f)
+}
+
+/// Windows completed out of the total, plus matches collected and throughput. When hidden the counts are
+/// still tracked, there's just nothing drawn
+pub struct Progress {
+    bar: ProgressBar,
+    matches: AtomicUsize,
+    started: Instant,
+}
+
+impl Progress {
+    /// Only drawn when `visible` and stderr is a terminal
+    pub fn new(total_windows: u64, visible: bool) -> Self {
+        let bar = if visible && std::io::stderr().is_terminal() {
+            let bar = MULTI.add(ProgressBar::new(total_windows));
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "{elapsed_precise} [{bar:40}] {pos}/{len} windows, {msg}",
+                )
+                .expect("template is valid")
+                .progress_chars("=> "),
+            );
+            bar
+        } else {
+            ProgressBar::with_draw_target(Some(total_windows), ProgressDrawTarget::hidden())
+        };
+
+        let progress = Progress {
+            bar,
+            matches: AtomicUsize::new(0),
+            started: Instant::now(),
+        };
+        progress.update_message();
+        progress
+    }
+
+    pub fn window_finished(&self) {
+        self.bar.inc(1);
+    }
+
+    pub fn add_matches(&self, count: usize) {
+        self.matches.fetch_add(count, Ordering::Relaxed);
+        self.update_message();
+    }
+
+    pub fn windows_finished(&self) -> u64 {
+        self.bar.position()
+    }
+
+    pub fn total_windows(&self) -> u64 {
+        self.bar.length().unwrap_or(0)
+    }
+
+    pub fn matches(&self) -> usize {
+        self.matches.load(Ordering::Relaxed)
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+
+    fn update_message(&self) {
+        let matches = self.matches();
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            matches as f64 / elapsed
+        } else {
+            0.0
+        };
+        self.bar
+            .set_message(format!("{} matches ({:.1}/s)", matches, rate));
+    }
+}
@@ -0,0 +1,98 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// The rolling window --daily-quota is counted over
+pub const QUOTA_WINDOW_SECS: u64 = 86400;
+
+/// Requests made in one minute. Usage is tracked a minute at a time so the checkpoint stays small however
+/// big the quota is
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaBucket {
+    /// Unix epoch of the start of the minute
+    pub minute: u64,
+    pub requests: u64,
+}
+
+impl QuotaBucket {
+    /// A bucket counts until a whole window has passed since the end of its minute, so the quota is never
+    /// exceeded however the requests fell within it
+    fn expires_at(&self) -> u64 {
+        self.minute + 60 + QUOTA_WINDOW_SECS
+    }
+}
+
+/// At most `limit` requests in any rolling 24 hours, shared by every worker. Times are unix epochs passed in
+/// by the caller
+#[derive(Debug)]
+pub struct RequestQuota {
+    limit: u64,
+    usage: Mutex<VecDeque<QuotaBucket>>,
+}
+
+impl RequestQuota {
+    /// `usage` is what earlier runs recorded, see `usage`
+    pub fn new(limit: u64, usage: Vec<QuotaBucket>) -> Self {
+        RequestQuota {
+            limit,
+            usage: Mutex::new(usage.into()),
+        }
+    }
+
+    fn expire(usage: &mut VecDeque<QuotaBucket>, now: u64) {
+        while usage
+            .front()
+            .is_some_and(|bucket| bucket.expires_at() <= now)
+        {
+            usage.pop_front();
+        }
+    }
+
+    /// Requests made in the window ending at `now`
+    pub fn used(&self, now: u64) -> u64 {
+        let mut usage = self.usage.lock().unwrap();
+        Self::expire(&mut usage, now);
+        usage.iter().map(|bucket| bucket.requests).sum()
+    }
+
+    /// Takes a request out of the budget. False when it's used up
+    pub fn try_acquire(&self, now: u64) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        Self::expire(&mut usage, now);
+        if usage.iter().map(|bucket| bucket.requests).sum::<u64>() >= self.limit {
+            return false;
+        }
+
+        let minute = now - now % 60;
+        match usage.back_mut() {
+            Some(bucket) if bucket.minute == minute => bucket.requests += 1,
+            _ => usage.push_back(QuotaBucket {
+                minute,
+                requests: 1,
+            }),
+        }
+        true
+    }
+
+    /// When the next request will fit in the budget, or None if one fits now
+    pub fn available_at(&self, now: u64) -> Option<u64> {
+        let mut usage = self.usage.lock().unwrap();
+        Self::expire(&mut usage, now);
+        let mut used: u64 = usage.iter().map(|bucket| bucket.requests).sum();
+        if used < self.limit {
+            return None;
+        }
+        for bucket in usage.iter() {
+            used -= bucket.requests;
+            if used < self.limit {
+                return Some(bucket.expires_at());
+            }
+        }
+        None
+    }
+
+    /// The buckets still inside the window, oldest first, for saving in the checkpoint
+    pub fn usage(&self) -> Vec<QuotaBucket> {
+        self.usage.lock().unwrap().iter().copied().collect()
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    error::RipperError,
+    export,
+    fights::{detect_team_fights, FightThresholds},
+    models::{CombatData, PredecessorMatch},
+    output::{for_each_saved_batch, write_atomically},
+};
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    /// Games, wins, kills, deaths and assists per hero, written to hero_stats.json
+    Heroes,
+    /// Every ward placement and destruction with its location, written to wards.csv
+    Wards,
+    /// One row per hero kill with its location and first blood flag, written to kills.csv
+    Kills,
+    /// How often each optional field is present rather than null, written to completeness.json
+    Completeness,
+    /// Kills close together in time and space grouped into fights, per match, written to team_fights.json
+    Fights,
+    /// Each team's summed gold at every interval of the match, written to gold_timeline.csv
+    #[serde(rename = "gold-timeline")]
+    GoldTimeline,
+}
+
+impl ReportKind {
+    /// Written to the current directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ReportKind::Heroes => "hero_stats.json",
+            ReportKind::Wards => "wards.csv",
+            ReportKind::Kills => "kills.csv",
+            ReportKind::Completeness => "completeness.json",
+            ReportKind::Fights => "team_fights.json",
+            ReportKind::GoldTimeline => "gold_timeline.csv",
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+pub struct HeroStats {
+    pub games: u64,
+    pub wins: u64,
+    pub kills: i64,
+    pub deaths: i64,
+    pub assists: i64,
+    pub average_game_duration: f64,
+    #[serde(skip)]
+    total_game_duration: i64,
+}
+
+impl HeroStats {
+    fn add(&mut self, m: &PredecessorMatch, team_id: i64, combat: &CombatData) {
+        self.games += 1;
+        if team_id == m.winning_team {
+            self.wins += 1;
+        }
+        self.kills += combat.kills;
+        self.deaths += combat.deaths;
+        self.assists += combat.assists;
+        self.total_game_duration += m.game_duration;
+        self.average_game_duration = self.total_game_duration as f64 / self.games as f64;
+    }
+}
+
+/// Adds every player in `m` to the running per-hero totals
+pub fn accumulate_hero_stats(stats: &mut HashMap<String, HeroStats>, m: &PredecessorMatch) {
+    for p in &m.player_data {
+        stats
+            .entry(p.hero_name.clone())
+            .or_default()
+            .add(m, p.team_id, &p.combat_data);
+    }
+}
+
+pub fn aggregate_hero_stats(matches: &[PredecessorMatch]) -> HashMap<String, HeroStats> {
+    let mut stats = HashMap::new();
+    for m in matches {
+        accumulate_hero_stats(&mut stats, m);
+    }
+    stats
+}
+
+/// How often one optional field was there
+#[derive(Serialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct FieldCompleteness {
+    pub present: u64,
+    pub missing: u64,
+    pub percent_present: f64,
+}
+
+impl FieldCompleteness {
+    fn record(&mut self, present: bool) {
+        if present {
+            self.present += 1;
+        } else {
+            self.missing += 1;
+        }
+        self.percent_present = self.present as f64 * 100.0 / (self.present + self.missing) as f64;
+    }
+}
+
+/// Running counts of how often every optional field in the model is present, keyed by its path in the API's
+/// JSON, e.g. `playerData[].roleName`. A field inside a list is counted once per element
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Completeness {
+    pub fields: BTreeMap<String, FieldCompleteness>,
+}
+
+impl Completeness {
+    fn record(&mut self, field: &str, present: bool) {
+        self.fields
+            .entry(field.to_string())
+            .or_default()
+            .record(present);
+    }
+
+    pub fn add_match(&mut self, m: &PredecessorMatch) {
+        for p in &m.player_data {
+            self.record("playerData[].roleName", p.role_name.is_some());
+            self.record("playerData[].playerName", p.player_name.is_some());
+            self.record("playerData[].inventoryData", p.inventory_data.is_some());
+            for (field, present) in p.damage_heal_data.optional_fields() {
+                self.record(&format!("playerData[].damageHealData.{}", field), present);
+            }
+            for ability in &p.ability_data {
+                self.record(
+                    "playerData[].abilityData[].abilityInputTag",
+                    ability.ability_input_tag.is_some(),
+                );
+                self.record(
+                    "playerData[].abilityData[].abilitySlot",
+                    ability.ability_slot.is_some(),
+                );
+            }
+        }
+    }
+}
+
+type CsvWriter<'a> = csv::Writer<&'a mut BufWriter<File>>;
+
+/// Serializes every row, returning how many there were
+fn serialize_rows<R: Serialize>(
+    writer: &mut CsvWriter<'_>,
+    rows: impl Iterator<Item = R>,
+) -> Result<usize, RipperError> {
+    let mut count = 0;
+    for row in rows {
+        writer.serialize(row)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Streams every saved match through `write_rows` into a single CSV. Returns the number of rows written
+fn write_csv_report<F>(
+    path: &Path,
+    output_dir: &Path,
+    mut write_rows: F,
+) -> Result<usize, RipperError>
+where
+    F: FnMut(&mut CsvWriter<'_>, &PredecessorMatch) -> Result<usize, RipperError>,
+{
+    let mut count = 0;
+    write_atomically(path, |file| {
+        let mut writer = csv::Writer::from_writer(file);
+        for_each_saved_batch(output_dir, |matches| {
+            for m in matches {
+                count += write_rows(&mut writer, m)?;
+            }
+            Ok(())
+        })?;
+        writer.flush()?;
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+/// Builds a report from the files in the output folder and writes it to the current directory. `fight_thresholds`
+/// only matters for `ReportKind::Fights`
+pub fn write_report(
+    kind: ReportKind,
+    output_dir: &Path,
+    fight_thresholds: &FightThresholds,
+) -> Result<PathBuf, RipperError> {
+    let path = PathBuf::from(kind.file_name());
+
+    match kind {
+        ReportKind::Heroes => {
+            let mut stats = HashMap::new();
+            for_each_saved_batch(output_dir, |matches| {
+                for m in matches {
+                    accumulate_hero_stats(&mut stats, m);
+                }
+                Ok(())
+            })?;
+
+            // Sorted so the report diffs cleanly between runs
+            let stats: BTreeMap<_, _> = stats.into_iter().collect();
+            write_atomically(&path, |file| {
+                serde_json::to_writer_pretty(&mut *file, &stats)?;
+                Ok(())
+            })?;
+            info!("Wrote stats for {} heroes to {:?}", stats.len(), path);
+        }
+        ReportKind::Completeness => {
+            let mut completeness = Completeness::default();
+            for_each_saved_batch(output_dir, |matches| {
+                for m in matches {
+                    completeness.add_match(m);
+                }
+                Ok(())
+            })?;
+
+            write_atomically(&path, |file| {
+                serde_json::to_writer_pretty(&mut *file, &completeness)?;
+                Ok(())
+            })?;
+            info!(
+                "Wrote completeness of {} optional fields to {:?}",
+                completeness.fields.len(),
+                path
+            );
+        }
+        ReportKind::Fights => {
+            let mut matches_fights = Vec::new();
+            for_each_saved_batch(output_dir, |matches| {
+                for m in matches {
+                    matches_fights.push(detect_team_fights(m, fight_thresholds));
+                }
+                Ok(())
+            })?;
+
+            write_atomically(&path, |file| {
+                serde_json::to_writer_pretty(&mut *file, &matches_fights)?;
+                Ok(())
+            })?;
+            let fights: usize = matches_fights.iter().map(|m| m.fights.len()).sum();
+            info!(
+                "Wrote {} team fights in {} matches to {:?}",
+                fights,
+                matches_fights.len(),
+                path
+            );
+        }
+        ReportKind::Wards => {
+            let rows = write_csv_report(&path, output_dir, |writer, m| {
+                serialize_rows(writer, export::ward_rows(m))
+            })?;
+            info!("Wrote {} ward events to {:?}", rows, path);
+        }
+        ReportKind::Kills => {
+            let rows = write_csv_report(&path, output_dir, |writer, m| {
+                serialize_rows(writer, export::kill_rows(m))
+            })?;
+            info!("Wrote {} hero kills to {:?}", rows, path);
+        }
+        ReportKind::GoldTimeline => {
+            let rows = write_csv_report(&path, output_dir, |writer, m| {
+                serialize_rows(writer, export::gold_timeline_rows(m).into_iter())
+            })?;
+            info!("Wrote {} team gold intervals to {:?}", rows, path);
+        }
+    }
+
+    Ok(path)
+}
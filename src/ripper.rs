@@ -0,0 +1,1266 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{create_dir_all, remove_dir_all},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::AtomicUsize,
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use futures::{future, stream, StreamExt};
+use reqwest::Client;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteExport;
+use crate::{
+    archive::{append_zip_matches, compression_pool, tar_gz_matches, zip_matches},
+    backoff::Backoff,
+    checkpoint::{Checkpoint, WindowCheckpoint},
+    checksum::verify_checksums,
+    circuit::CircuitBreaker,
+    compact::{compact_dir, CompactSummary},
+    config::{ArchiveFormat, Config, SchemaCheck},
+    cursor::Cursor,
+    error::RipperError,
+    fetch::{
+        build_client_with, get_matches_since, parse_matches, rate_limiter, RateLimiter,
+        RequestOptions, RAW_DIR,
+    },
+    manifest::Manifest,
+    merge::{merge_dirs, MergeSummary},
+    metrics::{serve as serve_metrics, Metrics},
+    models::{GameMode, PredecessorMatch, Region},
+    output::{remove_corrupt_files, saved_match_ids, MatchWriter, SingleFile, WriterMessage},
+    progress::Progress,
+    quota::RequestQuota,
+    report::{write_report, ReportKind},
+    stats::{FailedWindow, OutputDirStats, Stats},
+    status::{StatusSocket, WindowEvent},
+    validate::validate_output_dir,
+    windows::{
+        downloaded_ranges, filter_completed_windows, find_coverage_gaps, generate_work_windows,
+        WorkWindow,
+    },
+};
+
+/// Resolves on the first shutdown signal. On Unix that's SIGINT (Ctrl-C), SIGTERM (systemd, Docker) or SIGHUP
+#[cfg(unix)]
+async fn shutdown_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigint = signal(SignalKind::interrupt()).unwrap();
+    let mut sigterm = signal(SignalKind::terminate()).unwrap();
+    let mut sighup = signal(SignalKind::hangup()).unwrap();
+
+    tokio::select! {
+        _ = sigint.recv() => "SIGINT",
+        _ = sigterm.recv() => "SIGTERM",
+        _ = sighup.recv() => "SIGHUP",
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> &'static str {
+    tokio::signal::ctrl_c().await.unwrap();
+    "Ctrl-C"
+}
+
+/// The returned flag flips on the first shutdown signal so workers can drain gracefully
+fn setup_shutdown_handler() -> Arc<AtomicBool> {
+    let ctrl_c_received = Arc::new(AtomicBool::new(false));
+    let ctrl_c_received_clone = ctrl_c_received.clone();
+    tokio::spawn(async move {
+        let signal = shutdown_signal().await;
+        info!(
+            "{} received. Finishing open requests and exiting...",
+            signal
+        );
+        ctrl_c_received_clone.store(true, Ordering::Relaxed);
+    });
+    ctrl_c_received
+}
+
+/// Sleeps until `epoch`, checking for a shutdown every second. False if one came first
+async fn sleep_until_epoch(epoch: u64, shutdown: &AtomicBool) -> bool {
+    while (Utc::now().timestamp() as u64) < epoch {
+        if shutdown.load(Ordering::Relaxed) {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    !shutdown.load(Ordering::Relaxed)
+}
+
+/// Where to ask from after a batch: the end of its last match. If the whole batch ended on the second we
+/// asked for, asking again would return the same batch forever, so that nudges forward a second instead
+pub(crate) fn next_epoch(current_epoch: u64, matches: &[PredecessorMatch]) -> u64 {
+    let last_end_epoch = matches.last().map_or(0, |m| m.end_time.timestamp() as u64);
+    if last_end_epoch > current_epoch {
+        return last_end_epoch;
+    }
+    warn!(
+        "Epoch {} did not advance, skipping ahead to {}",
+        current_epoch,
+        current_epoch + 1
+    );
+    current_epoch + 1
+}
+
+/// The `{epoch}.json` responses saved by --keep-raw in `raw_dir`, oldest epoch first
+fn raw_responses(raw_dir: &Path) -> Result<Vec<(u64, PathBuf)>, RipperError> {
+    let mut responses = Vec::new();
+    for entry in std::fs::read_dir(raw_dir)? {
+        let path = entry?.path();
+        let epoch = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|epoch| epoch.parse().ok());
+        if let Some(epoch) = epoch {
+            responses.push((epoch, path));
+        }
+    }
+    responses.sort();
+    Ok(responses)
+}
+
+/// Drops any match whose match_id was already saved by this or another work window, since the API's
+/// "since" is inclusive and the boundary match of one request shows up again in the next.
+/// The seen set lives for the whole run, so memory grows with the number of matches collected
+/// (roughly 100 bytes per match_id, i.e. ~100MB per million matches). That's the price of exactly-once output
+fn dedup_matches(
+    seen_match_ids: &Mutex<HashSet<String>>,
+    matches: Vec<PredecessorMatch>,
+) -> Vec<PredecessorMatch> {
+    let mut seen_match_ids = seen_match_ids.lock().unwrap();
+    matches
+        .into_iter()
+        .filter(|m| seen_match_ids.insert(m.match_id.clone()))
+        .collect()
+}
+
+/// True if `value` is one of `allowed`. An empty list allows everything
+fn is_allowed<T: PartialEq>(allowed: &[T], value: &T) -> bool {
+    allowed.is_empty() || allowed.contains(value)
+}
+
+/// The --region, --game-mode and --min/max-duration filters
+struct MatchFilter {
+    regions: Vec<Region>,
+    game_modes: Vec<GameMode>,
+    min_duration: Option<u64>,
+    max_duration: Option<u64>,
+}
+
+impl MatchFilter {
+    fn new(config: &Config) -> Self {
+        MatchFilter {
+            regions: config.regions.clone(),
+            game_modes: config.game_modes.clone(),
+            min_duration: config.min_duration,
+            max_duration: config.max_duration,
+        }
+    }
+
+    /// Drops the matches that don't pass every filter
+    fn apply(&self, matches: Vec<PredecessorMatch>) -> Vec<PredecessorMatch> {
+        matches
+            .into_iter()
+            .filter(|m| is_allowed(&self.regions, &m.region))
+            .filter(|m| is_allowed(&self.game_modes, &m.game_mode))
+            .filter(|m| {
+                self.min_duration
+                    .is_none_or(|min| m.game_duration >= min as i64)
+            })
+            .filter(|m| {
+                self.max_duration
+                    .is_none_or(|max| m.game_duration <= max as i64)
+            })
+            .collect()
+    }
+}
+
+const CLEAR_ATTEMPTS: u32 = 3;
+const CLEAR_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Removing a folder fails on Windows while another process has a file in it open, which is often only
+/// momentary (a virus scanner, an indexer). Tries a few times before giving up with an error that says why
+async fn clear_output_dir(output_dir: &Path) -> Result<(), RipperError> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match remove_dir_all(output_dir) {
+            Ok(()) => return Ok(()),
+            Err(source) if attempts >= CLEAR_ATTEMPTS => {
+                return Err(RipperError::ClearOutputDir {
+                    path: output_dir.to_path_buf(),
+                    source,
+                })
+            }
+            Err(err) => {
+                warn!(
+                    "Could not clear {:?} ({}), trying again in {:?}",
+                    output_dir, err, CLEAR_RETRY_DELAY
+                );
+                tokio::time::sleep(CLEAR_RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Everything the fetchers share while collecting work windows
+struct RunContext {
+    client: Client,
+    base_url: String,
+    limiter: Option<RateLimiter>,
+    max_retries: u32,
+    backoff: Backoff,
+    breaker: CircuitBreaker,
+    /// How far to jump ahead after an empty response
+    empty_step: u64,
+    strict_schema: Option<SchemaCheck>,
+    /// Where response bodies are saved with --keep-raw
+    raw_dir: Option<PathBuf>,
+    /// With --adaptive-window, the furthest a run of empty responses can grow the jump to
+    max_stride: Option<u64>,
+    seen_match_ids: Mutex<HashSet<String>>,
+    ctrl_c_received: Arc<AtomicBool>,
+    /// Set by --daily-quota
+    quota: Option<RequestQuota>,
+    /// Once the quota is used up every window is stopped the same way a ctrl-c would stop them, but without
+    /// touching ctrl_c_received, which belongs to whoever asked for the run
+    quota_used_up: AtomicBool,
+    /// End of the last work window in the run, the only one that keeps matches ending right on its end
+    last_epoch: u64,
+    /// Sub-ranges each window is fetched as at once, see --window-splits
+    window_splits: u32,
+    max_response_bytes: Option<u64>,
+    status: Option<StatusSocket>,
+    /// Batches to save are handed off to the MatchWriter so a slow disk doesn't hold up the network
+    writer: mpsc::Sender<WriterMessage>,
+    /// Epochs to pick windows back up from, keyed by (start_epoch, end_epoch). Only filled on --resume
+    resume_from: HashMap<(u64, u64), u64>,
+    /// The next epoch for every window that's currently being collected
+    active_windows: Mutex<HashMap<(u64, u64), u64>>,
+    filter: MatchFilter,
+    /// Matches handed to the writer so far, for --limit
+    matches_collected: AtomicUsize,
+    limit: Option<usize>,
+    progress: Progress,
+    metrics: Arc<Metrics>,
+}
+
+impl RunContext {
+    fn stopping(&self) -> bool {
+        self.ctrl_c_received.load(Ordering::Relaxed) || self.quota_used_up.load(Ordering::Relaxed)
+    }
+
+    fn stop_for_quota(&self, available_at: u64) {
+        if !self.quota_used_up.swap(true, Ordering::Relaxed) {
+            info!("Daily quota used up, stopping until epoch {}", available_at);
+        }
+    }
+
+    fn request_options(&self) -> RequestOptions<'_> {
+        RequestOptions {
+            limiter: self.limiter.as_ref(),
+            max_retries: self.max_retries,
+            backoff: self.backoff,
+            schema_check: self.strict_schema,
+            raw_dir: self.raw_dir.as_deref(),
+            metrics: Some(&self.metrics),
+            breaker: Some(&self.breaker),
+            quota: self.quota.as_ref(),
+            max_response_bytes: self.max_response_bytes,
+        }
+    }
+
+    fn record_progress(&self, work_window: &WorkWindow, current_epoch: u64) {
+        self.active_windows.lock().unwrap().insert(
+            (work_window.start_epoch, work_window.end_epoch),
+            current_epoch,
+        );
+    }
+
+    /// Once --limit is reached every window is stopped the same way a ctrl-c would stop them
+    fn count_matches(&self, count: usize) {
+        self.progress.add_matches(count);
+        let total = self.matches_collected.fetch_add(count, Ordering::Relaxed) + count;
+        if let Some(limit) = self.limit {
+            if total >= limit && !self.ctrl_c_received.swap(true, Ordering::Relaxed) {
+                info!("Reached the limit of {} matches, stopping", limit);
+            }
+        }
+    }
+
+    /// The jump after `empty_streak` empty responses in a row. Without --adaptive-window it's always empty_step
+    fn empty_stride(&self, empty_streak: u32) -> u64 {
+        match self.max_stride {
+            Some(max_stride) => self
+                .empty_step
+                .saturating_mul(1 << empty_streak.saturating_sub(1).min(16))
+                .min(max_stride.max(self.empty_step)),
+            None => self.empty_step,
+        }
+    }
+
+    /// Whether `m` is `work_window`'s to save. Windows are half-open, so a match ending right on a boundary is
+    /// saved by the window after it, which asks for it itself. Past the last window there's no one else to
+    fn in_window(&self, work_window: &WorkWindow, m: &PredecessorMatch) -> bool {
+        let end_epoch = m.end_time.timestamp() as u64;
+        end_epoch < work_window.end_epoch
+            || (end_epoch == work_window.end_epoch && end_epoch == self.last_epoch)
+    }
+
+    async fn send(&self, message: WriterMessage) -> Result<(), RipperError> {
+        self.writer
+            .send(message)
+            .await
+            .map_err(|_| RipperError::WriterClosed)
+    }
+
+    /// `match_count` is the new matches the window saved, for --status-socket
+    fn finish_window(&self, work_window: &WorkWindow, match_count: usize) {
+        self.progress.window_finished();
+        self.active_windows
+            .lock()
+            .unwrap()
+            .remove(&(work_window.start_epoch, work_window.end_epoch));
+
+        if let Some(status) = &self.status {
+            status.send(&WindowEvent {
+                start_epoch: work_window.start_epoch,
+                end_epoch: work_window.end_epoch,
+                match_count,
+                windows_finished: self.progress.windows_finished(),
+                windows_total: self.progress.total_windows(),
+                total_matches: self.matches_collected.load(Ordering::Relaxed),
+            });
+        }
+    }
+}
+
+async fn get_matches_for_work_window(
+    ctx: &RunContext,
+    work_window: &WorkWindow,
+) -> Result<(), RipperError> {
+    let mut current_epoch = match ctx
+        .resume_from
+        .get(&(work_window.start_epoch, work_window.end_epoch))
+    {
+        Some(&epoch) => {
+            info!(
+                "Resuming work window {:?} from checkpoint epoch {}",
+                work_window, epoch
+            );
+            epoch
+        }
+        None => work_window.start_epoch,
+    };
+
+    info!(window = ?work_window, "Getting matches for work window");
+    ctx.record_progress(work_window, current_epoch);
+    let mut empty_streak = 0;
+    let mut window_matches = 0;
+    let mut failure = None;
+    if ctx.window_splits > 1 {
+        return get_matches_in_splits(ctx, work_window, current_epoch).await;
+    }
+    let request_options = ctx.request_options();
+    ctx.metrics.active_workers.fetch_add(1, Ordering::Relaxed);
+
+    loop {
+        // If we received a ctrl-c or ran out of quota, stop the loop
+        if ctx.stopping() {
+            break;
+        }
+
+        // Get the matches for the current epoch. get_matches_since retries transient failures before giving up
+        match get_matches_since(&ctx.client, &ctx.base_url, current_epoch, &request_options).await {
+            Ok(matches) => {
+                if !matches.is_empty() {
+                    empty_streak = 0;
+                    info!(
+                        window = ?work_window,
+                        epoch = current_epoch,
+                        match_count = matches.len(),
+                        "Fetched matches"
+                    );
+
+                    // Advance based on the full batch, even if every match in it was a duplicate
+                    current_epoch = next_epoch(current_epoch, &matches);
+
+                    // Filtering happens after the epoch is advanced, so a batch that's filtered out
+                    // entirely still moves us along
+                    let matches = matches
+                        .into_iter()
+                        .filter(|m| ctx.in_window(work_window, m))
+                        .collect();
+                    let matches = ctx.filter.apply(matches);
+                    let new_matches = dedup_matches(&ctx.seen_match_ids, matches);
+                    let new_count = new_matches.len();
+                    if !new_matches.is_empty() {
+                        ctx.send(WriterMessage::Batch(work_window.clone(), new_matches))
+                            .await?;
+                    }
+                    ctx.record_progress(work_window, current_epoch);
+                    ctx.count_matches(new_count);
+                    window_matches += new_count;
+                    // Everything from here on is the next window's
+                    if current_epoch >= work_window.end_epoch {
+                        info!(window = ?work_window, "Reached the end of work window");
+                        break;
+                    }
+                } else {
+                    // Quiet stretches (old or low-population ranges) can come back empty while later in the
+                    // window still has matches, so step forward and ask again until we're past the window
+                    empty_streak += 1;
+                    current_epoch += ctx.empty_stride(empty_streak);
+                    if current_epoch >= work_window.end_epoch {
+                        info!(window = ?work_window, "No more matches in work window");
+                        break;
+                    }
+                    warn!(
+                        window = ?work_window,
+                        epoch = current_epoch,
+                        "No matches found, skipping ahead"
+                    );
+                    ctx.record_progress(work_window, current_epoch);
+                }
+            }
+            Err(RipperError::QuotaExhausted { available_at }) => {
+                ctx.stop_for_quota(available_at);
+                break;
+            }
+            Err(err) => {
+                warn!(window = ?work_window, epoch = current_epoch, error = %err, "Giving up on work window");
+                failure = Some(err);
+                break;
+            }
+        }
+    }
+
+    ctx.metrics.active_workers.fetch_sub(1, Ordering::Relaxed);
+    // Windows cut short by a shutdown stay active so they end up in the checkpoint
+    if ctx.stopping() {
+        return failure.map_or(Ok(()), Err);
+    }
+    ctx.finish_window(work_window, window_matches);
+
+    match failure {
+        Some(err) => Err(err),
+        // Queued behind the window's batches, so it's only marked done in the manifest once they're on disk
+        None => {
+            ctx.send(WriterMessage::WindowDone(work_window.clone()))
+                .await
+        }
+    }
+}
+
+/// Splits `start_epoch..end_epoch` into `splits` sub-ranges of about the same length, each as its start and the
+/// start of the next. The last has no stop of its own and ends where the window does
+fn split_range(start_epoch: u64, end_epoch: u64, splits: u32) -> Vec<(u64, Option<u64>)> {
+    let step = end_epoch
+        .saturating_sub(start_epoch)
+        .div_ceil(splits as u64)
+        .max(1);
+    let starts: Vec<u64> = (start_epoch..end_epoch.max(start_epoch + 1))
+        .step_by(step as usize)
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| (start, starts.get(i + 1).copied()))
+        .collect()
+}
+
+/// Collects the matches that ended from `start_epoch` up to (not including) `stop`. Matches ending right on
+/// `stop` are left to the next sub-range, which asks for them itself, so nothing at a boundary is dropped or
+/// counted twice. Without a stop this ends with the window, just like a whole window does
+async fn get_sub_range(
+    ctx: &RunContext,
+    work_window: &WorkWindow,
+    start_epoch: u64,
+    stop: Option<u64>,
+) -> Result<Vec<PredecessorMatch>, RipperError> {
+    let request_options = ctx.request_options();
+    let end_epoch = stop.unwrap_or(work_window.end_epoch);
+    let mut current_epoch = start_epoch;
+    let mut empty_streak = 0;
+    let mut collected = Vec::new();
+
+    while !ctx.stopping() {
+        let matches =
+            get_matches_since(&ctx.client, &ctx.base_url, current_epoch, &request_options).await?;
+        if matches.is_empty() {
+            empty_streak += 1;
+            current_epoch += ctx.empty_stride(empty_streak);
+            if current_epoch >= end_epoch {
+                break;
+            }
+            continue;
+        }
+
+        empty_streak = 0;
+        current_epoch = next_epoch(current_epoch, &matches);
+        match stop {
+            Some(stop) => collected.extend(
+                matches
+                    .into_iter()
+                    .filter(|m| (m.end_time.timestamp() as u64) < stop),
+            ),
+            None => collected.extend(
+                matches
+                    .into_iter()
+                    .filter(|m| ctx.in_window(work_window, m)),
+            ),
+        }
+        if current_epoch >= end_epoch {
+            break;
+        }
+    }
+
+    Ok(collected)
+}
+
+/// --window-splits: fetches the rest of a window from `start_epoch` as several sub-ranges at once, then saves
+/// everything they found as a single batch in end time order. A window that's stopped part way saves nothing
+/// and stays in the checkpoint from `start_epoch`, so it's fetched again in full on --resume
+async fn get_matches_in_splits(
+    ctx: &RunContext,
+    work_window: &WorkWindow,
+    start_epoch: u64,
+) -> Result<(), RipperError> {
+    let sub_ranges = split_range(start_epoch, work_window.end_epoch, ctx.window_splits);
+    ctx.metrics.active_workers.fetch_add(1, Ordering::Relaxed);
+    let result = future::try_join_all(
+        sub_ranges
+            .into_iter()
+            .map(|(start, stop)| get_sub_range(ctx, work_window, start, stop)),
+    )
+    .await;
+    ctx.metrics.active_workers.fetch_sub(1, Ordering::Relaxed);
+
+    let result = match result {
+        Ok(_) if ctx.stopping() => Ok(0),
+        Ok(parts) => {
+            let mut matches: Vec<PredecessorMatch> = parts.into_iter().flatten().collect();
+            // Stable, so matches ending on the same second keep the order the backend sent them in
+            matches.sort_by_key(|m| m.end_time);
+            info!(
+                window = ?work_window,
+                match_count = matches.len(),
+                "Fetched matches"
+            );
+
+            let new_matches = dedup_matches(&ctx.seen_match_ids, ctx.filter.apply(matches));
+            let new_count = new_matches.len();
+            if !new_matches.is_empty() {
+                ctx.send(WriterMessage::Batch(work_window.clone(), new_matches))
+                    .await?;
+            }
+            ctx.count_matches(new_count);
+            Ok(new_count)
+        }
+        Err(RipperError::QuotaExhausted { available_at }) => {
+            ctx.stop_for_quota(available_at);
+            Ok(0)
+        }
+        Err(err) => {
+            warn!(window = ?work_window, error = %err, "Giving up on work window");
+            Err(err)
+        }
+    };
+
+    if ctx.stopping() {
+        return result.map(|_| ());
+    }
+    ctx.finish_window(work_window, *result.as_ref().unwrap_or(&0));
+    result?;
+    ctx.send(WriterMessage::WindowDone(work_window.clone()))
+        .await
+}
+
+/// Runs a whole collection from a `Config`: fetching, saving, checkpointing and zipping
+pub struct Ripper {
+    config: Config,
+}
+
+impl Ripper {
+    pub fn new(config: Config) -> Self {
+        Ripper { config }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Collects every work window in the configured range, stopping early on SIGINT/SIGTERM/SIGHUP.
+    /// Returns the totals for the run
+    pub async fn run(&self) -> Result<Stats, RipperError> {
+        self.run_until(setup_shutdown_handler()).await
+    }
+
+    /// Same as `run`, but stops when `shutdown` is set instead of listening for signals.
+    /// Useful when embedding the ripper in a service that has its own shutdown handling
+    pub async fn run_until(&self, shutdown: Arc<AtomicBool>) -> Result<Stats, RipperError> {
+        let started = Instant::now();
+        let config = &self.config;
+        // Also done when the CLI loads it, but a Config built in code hasn't been through that
+        config.check()?;
+        let output_dir = config.output_dir.as_path();
+
+        // Only worked out before the folder might be wiped below, since --update reads what's already there
+        let start_epoch = self.start_epoch()?;
+        // The same goes for the quota, which has to survive a fresh start
+        let quota = config
+            .daily_quota
+            .map(|limit| {
+                Ok::<_, RipperError>(RequestQuota::new(
+                    limit,
+                    Checkpoint::load(output_dir)?.quota_usage,
+                ))
+            })
+            .transpose()?;
+
+        // Start from a clean matches folder unless we're picking up where a previous run left off
+        if !config.resume && !config.update && output_dir.exists() {
+            clear_output_dir(output_dir).await?;
+        }
+        create_dir_all(output_dir)?;
+        // Has to happen before the writer loads the manifest, which it would otherwise save again with the
+        // corrupt files still in it
+        if config.resume {
+            let removed = remove_corrupt_files(output_dir)?;
+            if removed > 0 {
+                info!("Removed {} corrupt match files", removed);
+            }
+        }
+        let raw_dir = config.keep_raw.then(|| output_dir.join(RAW_DIR));
+        if let Some(raw_dir) = &raw_dir {
+            create_dir_all(raw_dir)?;
+        }
+
+        let metrics = Arc::new(Metrics::default());
+        let metrics_server = config
+            .metrics_addr
+            .map(|addr| tokio::spawn(serve_metrics(addr, metrics.clone())));
+
+        let writer = self.match_writer(metrics.clone())?;
+        let (sender, receiver) = mpsc::channel(config.write_buffer);
+        let writer_handle = tokio::task::spawn_blocking(move || writer.run(receiver));
+
+        let resume_from = if config.resume {
+            Checkpoint::load(output_dir)?
+                .windows
+                .into_iter()
+                .map(|w| ((w.start_epoch, w.end_epoch), w.current_epoch))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        let work_windows = self.work_windows(start_epoch);
+        info!("Generated {} work windows", work_windows.len());
+        let total_windows = work_windows.len();
+        let last_epoch = work_windows.iter().map(|w| w.end_epoch).max().unwrap_or(0);
+
+        let work_windows = if config.resume {
+            let manifest = Manifest::load(output_dir)?;
+            let remaining = if manifest.has_windows() {
+                let completed = manifest.completed_windows(output_dir);
+                work_windows
+                    .into_iter()
+                    .filter(|window| !completed.contains(window))
+                    .collect()
+            } else if manifest.files.is_empty() {
+                // Folders from before the manifest existed only have their file names to go on
+                filter_completed_windows(work_windows, &downloaded_ranges(output_dir)?)
+            } else {
+                filter_completed_windows(work_windows, &manifest.completed_ranges(output_dir))
+            };
+            info!(
+                "Resuming: {} of {} work windows left to fetch",
+                remaining.len(),
+                total_windows
+            );
+            remaining
+        } else {
+            work_windows
+        };
+
+        // Windows fetched again can run into batches they saved before the last run stopped, and --update starts
+        // on the newest saved match, which "since" being inclusive hands back again
+        let seen_match_ids = match work_windows.iter().map(|w| w.start_epoch).min() {
+            Some(since) if config.resume || config.update => saved_match_ids(output_dir, since)?,
+            _ => HashSet::new(),
+        };
+
+        let progress = Progress::new(work_windows.len() as u64, config.progress);
+        // Only a nice to have, so a socket that can't be made doesn't stop the run
+        let status =
+            config
+                .status_socket
+                .as_deref()
+                .and_then(|path| match StatusSocket::bind(path) {
+                    Ok(status) => {
+                        info!("Sending progress events on {:?}", path);
+                        Some(status)
+                    }
+                    Err(err) => {
+                        warn!("Couldn't create the status socket {:?}: {}", path, err);
+                        None
+                    }
+                });
+
+        let ctx = RunContext {
+            client: build_client_with(&config.client_options())?,
+            base_url: config.base_url.clone(),
+            limiter: config.max_rps.map(rate_limiter),
+            max_retries: config.max_retries,
+            backoff: config.backoff(),
+            breaker: CircuitBreaker::new(
+                config.outage_threshold,
+                Duration::from_secs(config.outage_cooldown_secs),
+                config.outage_max_pauses,
+            ),
+            empty_step: config.empty_step,
+            strict_schema: config.strict_schema,
+            raw_dir,
+            max_stride: config.adaptive_window.then_some(config.max_stride),
+            seen_match_ids: Mutex::new(seen_match_ids),
+            ctrl_c_received: shutdown,
+            quota,
+            quota_used_up: AtomicBool::new(false),
+            window_splits: config.window_splits,
+            last_epoch,
+            max_response_bytes: config.max_window_bytes,
+            status,
+            writer: sender,
+            resume_from,
+            active_windows: Mutex::new(HashMap::new()),
+            filter: MatchFilter::new(config),
+            matches_collected: AtomicUsize::new(0),
+            limit: config.limit,
+            progress,
+            metrics,
+        };
+
+        // Drive the work windows through a stream with at most pool_size of them in flight at once
+        // Each window still checks ctrl_c_received between requests, and we check it here as well
+        // so windows that haven't started yet are skipped entirely after a ctrl-c
+        let failed_windows: Vec<FailedWindow> = stream::iter(&work_windows)
+            .map(|work_window| {
+                let ctx = &ctx;
+                async move {
+                    // A closed writer means saving failed, and the run is about to return that error
+                    if ctx.stopping() || ctx.writer.is_closed() {
+                        return None;
+                    }
+                    let err = get_matches_for_work_window(ctx, work_window).await.err()?;
+                    warn!("Work window {:?} failed: {}", work_window, err);
+                    Some(FailedWindow {
+                        start_epoch: work_window.start_epoch,
+                        end_epoch: work_window.end_epoch,
+                        error: err.to_string(),
+                    })
+                }
+            })
+            .buffer_unordered(config.pool_size as usize)
+            .filter_map(future::ready)
+            .collect()
+            .await;
+
+        let interrupted = ctx.stopping();
+        let quota_usage = ctx
+            .quota
+            .as_ref()
+            .map(RequestQuota::usage)
+            .unwrap_or_default();
+        let backend_down = ctx.breaker.gave_up();
+        let active_windows = ctx.active_windows.into_inner().unwrap();
+        ctx.progress.finish();
+        let windows_processed = ctx.progress.windows_finished() as usize;
+
+        // Dropping the sender closes the channel, letting the writer finish whatever is still queued
+        drop(ctx.writer);
+        // A batch that couldn't be saved fails the run before the checkpoint, which would otherwise claim the
+        // windows it belonged to had got further than what's on disk
+        let mut stats = writer_handle.await??;
+
+        // Everything handed to the writer is on disk now, so record where the unfinished windows got to.
+        // An uninterrupted run has none, but may still have quota usage to carry over
+        let checkpoint = Checkpoint {
+            windows: if interrupted {
+                active_windows
+                    .into_iter()
+                    .map(
+                        |((start_epoch, end_epoch), current_epoch)| WindowCheckpoint {
+                            start_epoch,
+                            end_epoch,
+                            current_epoch,
+                        },
+                    )
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            quota_usage,
+        };
+        if interrupted || !checkpoint.quota_usage.is_empty() {
+            checkpoint.save(output_dir)?;
+            if interrupted {
+                info!(
+                    "Saved a checkpoint for {} unfinished work windows",
+                    checkpoint.windows.len()
+                );
+            }
+        } else {
+            Checkpoint::clear(output_dir)?;
+        }
+
+        stats.windows_processed = windows_processed;
+        stats.windows_skipped = total_windows - windows_processed;
+        stats.requests = ctx.metrics.requests.load(Ordering::Relaxed);
+        stats.retries = ctx.metrics.retries.load(Ordering::Relaxed);
+        stats.set_timings(&ctx.metrics);
+        stats.failed_windows = failed_windows;
+        stats.failed_windows.sort_by_key(|w| w.start_epoch);
+        stats.set_elapsed(started.elapsed());
+        stats.log();
+        if config.summary {
+            stats.save(output_dir)?;
+        }
+
+        if let Some(metrics_server) = metrics_server {
+            metrics_server.abort();
+        }
+
+        // Zip the matches
+        if !config.no_zip {
+            self.zip()?;
+        }
+
+        // Only now, so a partial run still gets its summary and archive
+        if backend_down {
+            return Err(RipperError::BackendUnavailable {
+                pauses: config.outage_max_pauses,
+            });
+        }
+        if config.fail_on_error && !stats.failed_windows.is_empty() {
+            return Err(RipperError::WindowsFailed(stats.failed_windows.len()));
+        }
+
+        Ok(stats)
+    }
+
+    /// --daily-quota: runs once to catch up to now, then keeps polling for new matches with the --update logic
+    /// until SIGINT/SIGTERM/SIGHUP. Whenever the quota runs out the run stops with a checkpoint, and the next one
+    /// waits until enough requests have rolled out of the last 24 hours, then resumes from it
+    pub async fn run_daemon(&self) -> Result<(), RipperError> {
+        let shutdown = setup_shutdown_handler();
+        let Some(limit) = self.config.daily_quota else {
+            self.run_until(shutdown).await?;
+            return Ok(());
+        };
+
+        let mut config = self.config.clone();
+        loop {
+            Ripper::new(config.clone())
+                .run_until(shutdown.clone())
+                .await?;
+            if shutdown.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            // Everything after the first run builds on what's there, including any windows the quota cut short
+            config.update = true;
+            config.resume = true;
+
+            let now = Utc::now().timestamp() as u64;
+            let usage = Checkpoint::load(&config.output_dir)?.quota_usage;
+            let wake_at = match RequestQuota::new(limit, usage).available_at(now) {
+                Some(available_at) => {
+                    info!(
+                        "Daily quota of {} requests used up, waiting until epoch {}",
+                        limit, available_at
+                    );
+                    available_at
+                }
+                None => {
+                    info!(
+                        "Caught up, checking for new matches in {}s",
+                        config.poll_interval_secs
+                    );
+                    now + config.poll_interval_secs
+                }
+            };
+            if !sleep_until_epoch(wake_at, &shutdown).await {
+                return Ok(());
+            }
+        }
+    }
+
+    /// --cursor: collects the range one request at a time instead of in parallel windows, keeping a single cursor
+    /// in `cursor.json` that only ever moves forward once everything before it is on disk. Picks up from the cursor
+    /// when there is one, otherwise starts a fresh output folder from the start epoch.
+    /// Stops early on SIGINT/SIGTERM/SIGHUP
+    pub async fn run_cursor(&self) -> Result<Stats, RipperError> {
+        self.run_cursor_until(setup_shutdown_handler()).await
+    }
+
+    /// Same as `run_cursor`, but stops when `shutdown` is set instead of listening for signals
+    pub async fn run_cursor_until(&self, shutdown: Arc<AtomicBool>) -> Result<Stats, RipperError> {
+        let started = Instant::now();
+        let config = &self.config;
+        config.check()?;
+        let output_dir = config.output_dir.as_path();
+
+        let mut seen_match_ids = HashSet::new();
+        let mut cursor = match Cursor::load(output_dir)? {
+            Some(cursor) => {
+                info!("Picking up from cursor epoch {}", cursor.epoch);
+                // A batch saved right before a kill can be ahead of the cursor, which only moves once it's on disk
+                seen_match_ids = saved_match_ids(output_dir, cursor.epoch)?;
+                cursor
+            }
+            None => {
+                if output_dir.exists() {
+                    clear_output_dir(output_dir).await?;
+                }
+                Cursor {
+                    epoch: config.start_epoch,
+                }
+            }
+        };
+        create_dir_all(output_dir)?;
+        let raw_dir = config.keep_raw.then(|| output_dir.join(RAW_DIR));
+        if let Some(raw_dir) = &raw_dir {
+            create_dir_all(raw_dir)?;
+        }
+        let end_epoch = config
+            .end_epoch
+            .unwrap_or_else(|| Utc::now().timestamp() as u64);
+
+        let metrics = Arc::new(Metrics::default());
+        let mut writer = self.match_writer(metrics.clone())?;
+        let client = build_client_with(&config.client_options())?;
+        let limiter = config.max_rps.map(rate_limiter);
+        let breaker = CircuitBreaker::new(
+            config.outage_threshold,
+            Duration::from_secs(config.outage_cooldown_secs),
+            config.outage_max_pauses,
+        );
+        let request_options = RequestOptions {
+            limiter: limiter.as_ref(),
+            max_retries: config.max_retries,
+            backoff: config.backoff(),
+            schema_check: config.strict_schema,
+            raw_dir: raw_dir.as_deref(),
+            metrics: Some(&metrics),
+            breaker: Some(&breaker),
+            quota: None,
+            max_response_bytes: config.max_window_bytes,
+        };
+        let filter = MatchFilter::new(config);
+        let seen_match_ids = Mutex::new(seen_match_ids);
+        let mut matches_collected = 0;
+
+        // The manifest is saved once the loop is over, whether or not a request failed along the way
+        let result: Result<(), RipperError> = async {
+            while cursor.epoch < end_epoch && !shutdown.load(Ordering::Relaxed) {
+                let matches =
+                    get_matches_since(&client, &config.base_url, cursor.epoch, &request_options)
+                        .await?;
+                let next = if matches.is_empty() {
+                    (cursor.epoch + config.empty_step).min(end_epoch)
+                } else {
+                    next_epoch(cursor.epoch, &matches)
+                };
+
+                // Matches ending right on the new cursor are left for the next request, which asks for them itself
+                let matches = matches
+                    .into_iter()
+                    .filter(|m| (m.end_time.timestamp() as u64) < next)
+                    .collect();
+                let new_matches = dedup_matches(&seen_match_ids, filter.apply(matches));
+                matches_collected += new_matches.len();
+                if !new_matches.is_empty() {
+                    writer.save(None, new_matches)?;
+                }
+                // Only once the batch is on disk, so the cursor never runs ahead of what's saved
+                cursor.epoch = next;
+                cursor.save(output_dir)?;
+
+                if config.limit.is_some_and(|limit| matches_collected >= limit) {
+                    info!(
+                        "Reached the limit of {} matches, stopping",
+                        matches_collected
+                    );
+                    break;
+                }
+            }
+            Ok(())
+        }
+        .await;
+        writer.save_manifest()?;
+        result?;
+        info!("Everything before epoch {} is collected", cursor.epoch);
+
+        let mut stats = writer.stats;
+        stats.requests = metrics.requests.load(Ordering::Relaxed);
+        stats.retries = metrics.retries.load(Ordering::Relaxed);
+        stats.set_timings(&metrics);
+        stats.set_elapsed(started.elapsed());
+        stats.log();
+        if config.summary {
+            stats.save(output_dir)?;
+        }
+        if !config.no_zip {
+            self.zip()?;
+        }
+        Ok(stats)
+    }
+
+    /// Everything on the disk side of a run, writing to the output folder
+    fn match_writer(&self, metrics: Arc<Metrics>) -> Result<MatchWriter, RipperError> {
+        let config = &self.config;
+        Ok(MatchWriter {
+            output_dir: config.output_dir.clone(),
+            format: config.format,
+            pretty: config.pretty,
+            partition: config.partition,
+            gzip: config.gzip_files,
+            cache_format: config.cache_format,
+            max_file_bytes: config.max_file_bytes,
+            split_by_match: config.split_by_match,
+            anonymizer: config.anonymizer(),
+            manifest: Manifest::load(&config.output_dir)?,
+            single_file: config
+                .single_file
+                .as_deref()
+                .map(SingleFile::create)
+                .transpose()?,
+            #[cfg(feature = "sqlite")]
+            sqlite: config
+                .sqlite
+                .as_deref()
+                .map(SqliteExport::open)
+                .transpose()?,
+            stats: Stats::default(),
+            metrics,
+        })
+    }
+
+    /// The `replay` subcommand: runs the responses a --keep-raw run saved in `raw_dir` back through parsing,
+    /// the filters and the writer, into a fresh output folder, without asking the backend for anything. They're
+    /// taken in epoch order, one file per response, so the output is what a live run with the same responses
+    /// would have written (short of --window-splits, which saves a whole window as one file).
+    ///
+    /// The output folder is cleared first, so it can't be the one the responses are in
+    pub fn replay(&self, raw_dir: &Path) -> Result<Stats, RipperError> {
+        let started = Instant::now();
+        let config = &self.config;
+        let output_dir = config.output_dir.as_path();
+
+        let raw_dir = raw_dir.canonicalize()?;
+        if output_dir
+            .canonicalize()
+            .is_ok_and(|output_dir| raw_dir.starts_with(output_dir))
+        {
+            return Err(RipperError::Replay(format!(
+                "{:?} is inside the output folder, which is cleared first. Pick another --output-dir",
+                raw_dir
+            )));
+        }
+        let responses = raw_responses(&raw_dir)?;
+        info!("Replaying {} saved responses", responses.len());
+
+        remove_dir_all(output_dir).or_else(|err| match err.kind() {
+            io::ErrorKind::NotFound => Ok(()),
+            _ => Err(err),
+        })?;
+        create_dir_all(output_dir)?;
+        let mut writer = self.match_writer(Arc::new(Metrics::default()))?;
+        let filter = MatchFilter::new(config);
+        let seen_match_ids = Mutex::new(HashSet::new());
+
+        for (epoch, path) in responses {
+            let body = std::fs::read(&path)?;
+            let matches = parse_matches(&body, epoch, config.strict_schema)?;
+            let new_matches = dedup_matches(&seen_match_ids, filter.apply(matches));
+            if !new_matches.is_empty() {
+                writer.save(None, new_matches)?;
+            }
+        }
+        writer.save_manifest()?;
+
+        let mut stats = writer.stats;
+        stats.set_elapsed(started.elapsed());
+        stats.log();
+        if config.summary {
+            stats.save(output_dir)?;
+        }
+        if !config.no_zip {
+            self.zip()?;
+        }
+        Ok(stats)
+    }
+
+    /// Archives the output folder to the configured path, as a zip or a tar.gz
+    pub fn zip(&self) -> Result<(), RipperError> {
+        let config = &self.config;
+        match config.archive_format {
+            ArchiveFormat::Zip => compression_pool(config.compress_threads)?.install(|| {
+                if config.append_zip {
+                    append_zip_matches(&config.output_dir, &config.zip_path(), config.compression)
+                } else {
+                    zip_matches(&config.output_dir, &config.zip_path(), config.compression)
+                }
+            }),
+            ArchiveFormat::Targz => tar_gz_matches(&config.output_dir, &config.zip_path()),
+        }
+    }
+
+    /// Where a run starts. With --update that's the newest end epoch already downloaded, so only matches
+    /// since the last run are fetched. An empty output folder falls back to the configured start epoch
+    pub fn start_epoch(&self) -> Result<u64, RipperError> {
+        let config = &self.config;
+        if !config.update || !config.output_dir.exists() {
+            return Ok(config.start_epoch);
+        }
+
+        let manifest = Manifest::load(&config.output_dir)?;
+        let newest = match manifest.files.iter().map(|entry| entry.end_epoch).max() {
+            Some(newest) => Some(newest),
+            None => downloaded_ranges(&config.output_dir)?
+                .into_iter()
+                .map(|(_, end)| end)
+                .max(),
+        };
+
+        // Windows a shutdown or the quota cut short can sit behind the newest file, so go back far enough to finish them
+        let unfinished = if config.resume {
+            Checkpoint::load(&config.output_dir)?
+                .windows
+                .iter()
+                .map(|window| window.start_epoch)
+                .min()
+        } else {
+            None
+        };
+
+        match newest {
+            Some(newest) => {
+                let since = unfinished.map_or(newest, |unfinished| unfinished.min(newest));
+                info!("Updating: fetching matches since epoch {}", since);
+                Ok(since.max(config.start_epoch))
+            }
+            None => Ok(config.start_epoch),
+        }
+    }
+
+    /// The work windows from `start_epoch` to the configured end, in the order they're started in
+    fn work_windows(&self, start_epoch: u64) -> Vec<WorkWindow> {
+        let config = &self.config;
+        let mut work_windows =
+            generate_work_windows(start_epoch, config.end_epoch, config.window_size);
+        if config.newest_first {
+            work_windows.reverse();
+        }
+        work_windows
+    }
+
+    /// Logs the work windows a run would collect, in the order they'd be started, without touching the
+    /// network or the disk
+    pub fn dry_run(&self) -> Result<Vec<WorkWindow>, RipperError> {
+        let config = &self.config;
+        let work_windows = self.work_windows(self.start_epoch()?);
+
+        info!("Would collect {} work windows", work_windows.len());
+        if let (Some(first), Some(last)) = (work_windows.first(), work_windows.last()) {
+            info!("First work window: {:?}", first);
+            info!("Last work window: {:?}", last);
+        }
+        // Every window needs at least one request, plus one more for each full batch it returns
+        info!(
+            "At least {} requests, {} at a time",
+            work_windows.len(),
+            config.pool_size.min(work_windows.len() as u64)
+        );
+
+        Ok(work_windows)
+    }
+
+    /// Builds a report from the files already in the output folder. Returns where it was written
+    pub fn report(&self, kind: ReportKind) -> Result<PathBuf, RipperError> {
+        write_report(kind, &self.config.output_dir, &self.config.team_fights)
+    }
+
+    /// Totals for the matches already in the output folder, without downloading anything
+    pub fn stats(&self) -> Result<OutputDirStats, RipperError> {
+        OutputDirStats::from_output_dir(&self.config.output_dir)
+    }
+
+    /// Checks the matches already in the output folder for inconsistencies. Returns how many had issues
+    pub fn validate(&self) -> Result<usize, RipperError> {
+        validate_output_dir(&self.config.output_dir, &self.config.map_bounds)
+    }
+
+    /// Merges the match files in several output folders into `output_dir`, dropping repeated matches
+    pub fn merge(
+        &self,
+        inputs: &[PathBuf],
+        output_dir: &Path,
+    ) -> Result<MergeSummary, RipperError> {
+        merge_dirs(inputs, output_dir, &self.config)
+    }
+
+    /// Merges runs of small match files in the output folder into files of up to `target_bytes`
+    pub fn compact(&self, target_bytes: u64) -> Result<CompactSummary, RipperError> {
+        compact_dir(&self.config.output_dir, target_bytes, &self.config)
+    }
+
+    /// Re-hashes the files in the output folder against the manifest. Returns how many didn't match
+    pub fn verify_checksums(&self) -> Result<usize, RipperError> {
+        verify_checksums(&self.config.output_dir)
+    }
+
+    /// Returns the configured work windows that have no output on disk, without downloading anything
+    pub fn verify(&self) -> Result<Vec<WorkWindow>, RipperError> {
+        let config = &self.config;
+        let work_windows =
+            generate_work_windows(config.start_epoch, config.end_epoch, config.window_size);
+
+        // Prefer the manifest, but fall back to the file names for folders from before it existed
+        let manifest = Manifest::load(&config.output_dir)?;
+        let downloaded = if !config.output_dir.exists() {
+            Vec::new()
+        } else if manifest.files.is_empty() {
+            downloaded_ranges(&config.output_dir)?
+        } else {
+            manifest
+                .files
+                .iter()
+                .map(|entry| (entry.start_epoch, entry.end_epoch))
+                .collect()
+        };
+
+        let gaps = find_coverage_gaps(&work_windows, &downloaded);
+        info!(
+            "{} of {} work windows have no output in {:?}",
+            gaps.len(),
+            work_windows.len(),
+            config.output_dir
+        );
+
+        Ok(gaps)
+    }
+}
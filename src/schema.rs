@@ -0,0 +1,28 @@
+use std::{io::Write, path::Path};
+
+use schemars::{schema::RootSchema, schema_for};
+
+use crate::{error::RipperError, models::PredecessorMatch, output::write_atomically};
+
+/// JSON Schema for one match as it's saved, with the nested types under `definitions`
+pub fn match_schema() -> RootSchema {
+    schema_for!(PredecessorMatch)
+}
+
+/// The `schema` subcommand: writes `match_schema` to `output`, or stdout without one
+pub fn write_schema(output: Option<&Path>) -> Result<(), RipperError> {
+    let schema = match_schema();
+    match output {
+        Some(path) => write_atomically(path, |file| {
+            serde_json::to_writer_pretty(&mut *file, &schema)?;
+            writeln!(file)?;
+            Ok(())
+        }),
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, &schema)?;
+            writeln!(stdout)?;
+            Ok(())
+        }
+    }
+}
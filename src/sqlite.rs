@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::{error::RipperError, models::PredecessorMatch, time::BACKEND_TIME_FORMAT};
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS matches (
+    match_id TEXT PRIMARY KEY,
+    winning_team INTEGER NOT NULL,
+    game_duration INTEGER NOT NULL,
+    game_mode TEXT NOT NULL,
+    region TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    match_end_reason TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS player_data (
+    match_id TEXT NOT NULL REFERENCES matches(match_id) ON DELETE CASCADE,
+    player_id TEXT NOT NULL,
+    team_id INTEGER NOT NULL,
+    hero_name TEXT NOT NULL,
+    role_name TEXT,
+    player_name TEXT,
+    kills INTEGER NOT NULL,
+    deaths INTEGER NOT NULL,
+    assists INTEGER NOT NULL,
+    largest_killing_spree INTEGER NOT NULL,
+    largest_multi_kill INTEGER NOT NULL,
+    minions_killed INTEGER NOT NULL,
+    lane_minions_killed INTEGER NOT NULL,
+    neutral_minions_killed INTEGER NOT NULL,
+    gold_earned INTEGER NOT NULL,
+    gold_spent INTEGER NOT NULL,
+    wards_placed INTEGER NOT NULL,
+    wards_destroyed INTEGER NOT NULL,
+    PRIMARY KEY (match_id, player_id)
+);
+
+CREATE TABLE IF NOT EXISTS hero_kills (
+    match_id TEXT NOT NULL REFERENCES matches(match_id) ON DELETE CASCADE,
+    killed_player_id TEXT NOT NULL,
+    killed_hero_name TEXT NOT NULL,
+    killer_player_id TEXT NOT NULL,
+    killer_hero_name TEXT NOT NULL,
+    killer_entity_type TEXT NOT NULL,
+    is_first_blood INTEGER NOT NULL,
+    x REAL NOT NULL,
+    y REAL NOT NULL,
+    z REAL NOT NULL,
+    game_time INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS structure_destructions (
+    match_id TEXT NOT NULL REFERENCES matches(match_id) ON DELETE CASCADE,
+    destruction_player_id TEXT NOT NULL,
+    destruction_hero_name TEXT NOT NULL,
+    structure_entity_type TEXT NOT NULL,
+    team_id INTEGER NOT NULL,
+    x REAL NOT NULL,
+    y REAL NOT NULL,
+    z REAL NOT NULL,
+    game_time INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS objective_kills (
+    match_id TEXT NOT NULL REFERENCES matches(match_id) ON DELETE CASCADE,
+    killed_entity_type TEXT NOT NULL,
+    killer_player_id TEXT NOT NULL,
+    killer_hero_name TEXT NOT NULL,
+    x REAL NOT NULL,
+    y REAL NOT NULL,
+    z REAL NOT NULL,
+    game_time INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_player_data_match_id ON player_data(match_id);
+CREATE INDEX IF NOT EXISTS idx_hero_kills_match_id ON hero_kills(match_id);
+CREATE INDEX IF NOT EXISTS idx_structure_destructions_match_id ON structure_destructions(match_id);
+CREATE INDEX IF NOT EXISTS idx_objective_kills_match_id ON objective_kills(match_id);
+";
+
+/// Mirrors saved matches into a SQLite database, one transaction per window
+pub struct SqliteExport {
+    conn: Connection,
+}
+
+impl SqliteExport {
+    pub fn open(path: &Path) -> Result<Self, RipperError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn from_connection(conn: Connection) -> Result<Self, RipperError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteExport { conn })
+    }
+
+    /// For querying what's been exported so far
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Inserts a window's matches. A match that's already in the database is replaced along with
+    /// all of its child rows, so re-running over the same range never duplicates anything
+    pub fn insert_matches(&mut self, matches: &[PredecessorMatch]) -> Result<(), RipperError> {
+        let tx = self.conn.transaction()?;
+
+        {
+            let mut delete_match = tx.prepare_cached("DELETE FROM matches WHERE match_id = ?1")?;
+            let mut insert_match = tx.prepare_cached(
+                "INSERT INTO matches (match_id, winning_team, game_duration, game_mode, region, start_time, end_time, match_end_reason)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            let mut insert_player = tx.prepare_cached(
+                "INSERT INTO player_data (match_id, player_id, team_id, hero_name, role_name, player_name, kills, deaths, assists,
+                    largest_killing_spree, largest_multi_kill, minions_killed, lane_minions_killed, neutral_minions_killed,
+                    gold_earned, gold_spent, wards_placed, wards_destroyed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            )?;
+            let mut insert_hero_kill = tx.prepare_cached(
+                "INSERT INTO hero_kills (match_id, killed_player_id, killed_hero_name, killer_player_id, killer_hero_name,
+                    killer_entity_type, is_first_blood, x, y, z, game_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            let mut insert_structure = tx.prepare_cached(
+                "INSERT INTO structure_destructions (match_id, destruction_player_id, destruction_hero_name,
+                    structure_entity_type, team_id, x, y, z, game_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            let mut insert_objective = tx.prepare_cached(
+                "INSERT INTO objective_kills (match_id, killed_entity_type, killer_player_id, killer_hero_name, x, y, z, game_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+
+            for m in matches {
+                // Cascades to the child tables
+                delete_match.execute(params![m.match_id])?;
+                insert_match.execute(params![
+                    m.match_id,
+                    m.winning_team,
+                    m.game_duration,
+                    m.game_mode.as_str(),
+                    m.region.as_str(),
+                    m.start_time.format(BACKEND_TIME_FORMAT).to_string(),
+                    m.end_time.format(BACKEND_TIME_FORMAT).to_string(),
+                    m.match_end_reason.as_str(),
+                ])?;
+
+                for p in &m.player_data {
+                    insert_player.execute(params![
+                        m.match_id,
+                        p.player_id,
+                        p.team_id,
+                        p.hero_name,
+                        p.role_name,
+                        p.player_name,
+                        p.combat_data.kills,
+                        p.combat_data.deaths,
+                        p.combat_data.assists,
+                        p.combat_data.largest_killing_spree,
+                        p.combat_data.largest_multi_kill,
+                        p.minion_data.minions_killed,
+                        p.minion_data.lane_minions_killed,
+                        p.minion_data.neutral_minions_killed,
+                        p.income_data.gold_earned,
+                        p.income_data.gold_spent,
+                        p.wards_data.wards_placed,
+                        p.wards_data.wards_destroyed,
+                    ])?;
+                }
+
+                for k in &m.hero_kills {
+                    insert_hero_kill.execute(params![
+                        m.match_id,
+                        k.killed_player_id,
+                        k.killed_hero_name,
+                        k.killer_player_id,
+                        k.killer_hero_name,
+                        k.killer_entity_type,
+                        k.is_first_blood,
+                        k.location.x,
+                        k.location.y,
+                        k.location.z,
+                        k.game_time.as_seconds(),
+                    ])?;
+                }
+
+                for s in &m.structure_destructions {
+                    insert_structure.execute(params![
+                        m.match_id,
+                        s.destruction_player_id,
+                        s.destruction_hero_name,
+                        s.structure_entity_type,
+                        s.team_id,
+                        s.location.x,
+                        s.location.y,
+                        s.location.z,
+                        s.game_time.as_seconds(),
+                    ])?;
+                }
+
+                for o in &m.objective_kills {
+                    insert_objective.execute(params![
+                        m.match_id,
+                        o.killed_entity_type,
+                        o.killer_player_id,
+                        o.killer_hero_name,
+                        o.location.x,
+                        o.location.y,
+                        o.location.z,
+                        o.game_time.as_seconds(),
+                    ])?;
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+}
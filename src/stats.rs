@@ -0,0 +1,159 @@
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    error::RipperError,
+    metrics::Metrics,
+    models::PredecessorMatch,
+    output::{for_each_saved_batch, write_atomically},
+    windows::match_files,
+};
+
+/// Written to the output folder at the end of a run when --summary is given
+pub const SUMMARY_FILE: &str = "summary.json";
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FailedWindow {
+    pub start_epoch: u64,
+    pub end_epoch: u64,
+    pub error: String,
+}
+
+/// Totals for a whole run, logged once everything has been written
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub total_matches: usize,
+    pub windows_processed: usize,
+    /// Windows that were already downloaded (--resume) or never finished because of a shutdown
+    pub windows_skipped: usize,
+    /// Windows given up on after a request failed for good. These count as processed too
+    pub failed_windows: Vec<FailedWindow>,
+    pub matches_per_region: BTreeMap<String, usize>,
+    pub matches_per_game_mode: BTreeMap<String, usize>,
+    pub bytes_written: u64,
+    /// Requests sent to the backend, retries included
+    pub requests: u64,
+    pub retries: u64,
+    /// Median and 95th percentile time of a successful request, see `LatencyHistogram`
+    pub latency_p50_ms: Option<u64>,
+    pub latency_p95_ms: Option<u64>,
+    /// How fast response bodies came in while requests were going, see `Metrics::response_bytes_per_sec`
+    pub response_bytes_per_sec: Option<f64>,
+    pub elapsed_secs: f64,
+}
+
+impl Stats {
+    /// Counts a batch that was just saved to a file of `bytes` bytes
+    pub fn record_matches(&mut self, matches: &[PredecessorMatch], bytes: u64) {
+        self.total_matches += matches.len();
+        self.bytes_written += bytes;
+        count_matches(
+            &mut self.matches_per_region,
+            &mut self.matches_per_game_mode,
+            matches,
+        );
+    }
+
+    /// Fills in the request timings from a run's metrics
+    pub fn set_timings(&mut self, metrics: &Metrics) {
+        let percentile_ms = |percentile| {
+            metrics
+                .request_latency
+                .percentile(percentile)
+                .map(|latency| latency.as_millis() as u64)
+        };
+        self.latency_p50_ms = percentile_ms(50.0);
+        self.latency_p95_ms = percentile_ms(95.0);
+        self.response_bytes_per_sec = metrics.response_bytes_per_sec();
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed_secs = elapsed.as_secs_f64();
+    }
+
+    pub fn log(&self) {
+        info!(
+            "Collected {} matches ({} bytes) in {:.1}s. {} work windows processed, {} skipped. {} requests, {} retried",
+            self.total_matches,
+            self.bytes_written,
+            self.elapsed_secs,
+            self.windows_processed,
+            self.windows_skipped,
+            self.requests,
+            self.retries
+        );
+        if let (Some(p50), Some(p95)) = (self.latency_p50_ms, self.latency_p95_ms) {
+            info!(
+                "Requests took {}ms (p50), {}ms (p95), responses came in at {:.0} bytes/s",
+                p50,
+                p95,
+                self.response_bytes_per_sec.unwrap_or_default()
+            );
+        }
+        if !self.failed_windows.is_empty() {
+            warn!("{} work windows failed:", self.failed_windows.len());
+            for failed in &self.failed_windows {
+                warn!(
+                    "  {}-{}: {}",
+                    failed.start_epoch, failed.end_epoch, failed.error
+                );
+            }
+        }
+        for (region, count) in &self.matches_per_region {
+            info!("  region {}: {} matches", region, count);
+        }
+        for (game_mode, count) in &self.matches_per_game_mode {
+            info!("  game mode {}: {} matches", game_mode, count);
+        }
+    }
+
+    pub fn save(&self, output_dir: &Path) -> Result<(), RipperError> {
+        write_atomically(&output_dir.join(SUMMARY_FILE), |file| {
+            serde_json::to_writer_pretty(&mut *file, self)?;
+            Ok(())
+        })
+    }
+}
+
+/// Totals for the matches already saved in the output folder, printed by the `stats` subcommand. Unlike `Stats`
+/// there's no run behind these, so there are no windows, requests or timings
+#[derive(Serialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct OutputDirStats {
+    pub total_matches: usize,
+    pub matches_per_region: BTreeMap<String, usize>,
+    pub matches_per_game_mode: BTreeMap<String, usize>,
+    /// Size of the match files on disk, not counting caches, the manifest or anything else in the folder
+    pub bytes_written: u64,
+}
+
+impl OutputDirStats {
+    pub fn from_output_dir(output_dir: &Path) -> Result<Self, RipperError> {
+        let mut stats = OutputDirStats::default();
+        for_each_saved_batch(output_dir, |matches| {
+            stats.total_matches += matches.len();
+            count_matches(
+                &mut stats.matches_per_region,
+                &mut stats.matches_per_game_mode,
+                matches,
+            );
+            Ok(())
+        })?;
+        for (path, _) in match_files(output_dir)? {
+            stats.bytes_written += std::fs::metadata(path)?.len();
+        }
+        Ok(stats)
+    }
+}
+
+fn count_matches(
+    per_region: &mut BTreeMap<String, usize>,
+    per_game_mode: &mut BTreeMap<String, usize>,
+    matches: &[PredecessorMatch],
+) {
+    for m in matches {
+        *per_region.entry(m.region.to_string()).or_default() += 1;
+        *per_game_mode.entry(m.game_mode.to_string()).or_default() += 1;
+    }
+}
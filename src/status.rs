@@ -0,0 +1,98 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast, task::JoinHandle};
+
+/// Events a slow consumer can fall behind by before it starts missing some
+const EVENT_BUFFER: usize = 256;
+
+/// Sent on --status-socket every time a work window finishes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WindowEvent {
+    pub start_epoch: u64,
+    pub end_epoch: u64,
+    /// New matches the window saved
+    pub match_count: usize,
+    pub windows_finished: u64,
+    pub windows_total: u64,
+    /// New matches saved by every window so far
+    pub total_matches: usize,
+}
+
+/// --status-socket: a Unix socket any number of consumers can connect to. Each gets every event from when it
+/// connected on, one JSON object per line. The socket file is removed again when this is dropped
+pub struct StatusSocket {
+    path: PathBuf,
+    events: broadcast::Sender<String>,
+    accept: JoinHandle<()>,
+}
+
+impl StatusSocket {
+    #[cfg(unix)]
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        use tokio::{io::AsyncWriteExt, net::UnixListener, sync::broadcast::error::RecvError};
+
+        // One left behind by a run that was killed would make the bind fail
+        if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        let (events, _) = broadcast::channel::<String>(EVENT_BUFFER);
+
+        let sender = events.clone();
+        let accept = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut receiver = sender.subscribe();
+                tokio::spawn(async move {
+                    loop {
+                        match receiver.recv().await {
+                            Ok(line) => {
+                                // The consumer went away
+                                if stream.write_all(line.as_bytes()).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(StatusSocket {
+            path: path.to_path_buf(),
+            events,
+            accept,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(_path: &Path) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "status sockets need Unix domain sockets",
+        ))
+    }
+
+    /// Sends `event` to everyone connected. Nobody being connected isn't an error
+    pub fn send(&self, event: &WindowEvent) {
+        if let Ok(mut line) = serde_json::to_string(event) {
+            line.push('\n');
+            let _ = self.events.send(line);
+        }
+    }
+}
+
+impl Drop for StatusSocket {
+    fn drop(&mut self) {
+        // Consumers still get whatever was sent before this, their tasks end once the channel is closed
+        self.accept.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{HashSet, VecDeque};
+
+use chrono::Utc;
+use reqwest::Client;
+use tokio::runtime::Runtime;
+
+use crate::{
+    error::RipperError,
+    fetch::{get_matches_since, RequestOptions},
+    models::PredecessorMatch,
+    ripper::next_epoch,
+    BASE_URL, WINDOW_SIZE,
+};
+
+/// Every match from a start epoch on, one at a time, fetched a page at a time as they're needed. Requests are
+/// retried the same way a run retries them, and a request that fails for good ends the iterator with its error.
+///
+/// Blocks on a runtime of its own, so it's for synchronous code. From async code, page through
+/// `fetch_matches_since` instead.
+///
+/// ```no_run
+/// # fn example() -> Result<(), pred_ripper::RipperError> {
+/// use std::time::Duration;
+///
+/// let client = pred_ripper::build_client(Duration::from_secs(30))?;
+/// for m in pred_ripper::MatchStream::new(client, 1680307200)?.until(1680310800) {
+///     println!("{}", m?.match_id);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct MatchStream {
+    runtime: Runtime,
+    client: Client,
+    base_url: String,
+    epoch: u64,
+    end_epoch: u64,
+    /// Matches that ended on `epoch`. The backend's "since" is inclusive, so the next page starts with them again
+    boundary_ids: HashSet<String>,
+    page: VecDeque<PredecessorMatch>,
+    done: bool,
+}
+
+impl MatchStream {
+    /// From `start_epoch` on the public backend, up to the matches that had ended by now
+    pub fn new(client: Client, start_epoch: u64) -> Result<Self, RipperError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(MatchStream {
+            runtime,
+            client,
+            base_url: BASE_URL.to_string(),
+            epoch: start_epoch,
+            end_epoch: Utc::now().timestamp() as u64,
+            boundary_ids: HashSet::new(),
+            page: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Another backend, e.g. a staging server or a local mock. See `Config::base_url`
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Stop after the last match that ended at or before `end_epoch`, instead of now
+    pub fn until(mut self, end_epoch: u64) -> Self {
+        self.end_epoch = end_epoch;
+        self
+    }
+
+    fn fetch_page(&mut self) -> Result<(), RipperError> {
+        let matches = self.runtime.block_on(get_matches_since(
+            &self.client,
+            &self.base_url,
+            self.epoch,
+            &RequestOptions::default(),
+        ))?;
+
+        // Quiet stretches can come back empty with matches still to come, the same as in a work window
+        if matches.is_empty() {
+            self.epoch += WINDOW_SIZE;
+            self.done = self.epoch > self.end_epoch;
+            return Ok(());
+        }
+
+        self.epoch = next_epoch(self.epoch, &matches);
+        let boundary_ids = matches
+            .iter()
+            .filter(|m| m.end_time.timestamp() as u64 == self.epoch)
+            .map(|m| m.match_id.clone())
+            .collect();
+        for m in matches {
+            if self.boundary_ids.contains(&m.match_id) {
+                continue;
+            }
+            if m.end_time.timestamp() as u64 > self.end_epoch {
+                self.done = true;
+                break;
+            }
+            self.page.push_back(m);
+        }
+        self.boundary_ids = boundary_ids;
+        Ok(())
+    }
+}
+
+impl Iterator for MatchStream {
+    type Item = Result<PredecessorMatch, RipperError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(m) = self.page.pop_front() {
+                return Some(Ok(m));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
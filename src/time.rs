@@ -0,0 +1,93 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+use crate::error::RipperError;
+
+const HUMAN_TIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S%.f",
+];
+
+/// How the backend writes match times, e.g. `2023-04-01 00:30:00`. Fractional seconds are only written when
+/// there are any
+pub const BACKEND_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// A date on its own, taken by the CLI and the config file as a whole day
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn date_to_unix_epoch(value: &str, time: NaiveTime) -> Option<u64> {
+    let date = NaiveDate::parse_from_str(value, DATE_FORMAT).ok()?;
+    Some(date.and_time(time).and_utc().timestamp() as u64)
+}
+
+/// Parses a time from the backend (or the CLI) into UTC.
+/// Anything with an explicit offset (RFC 3339, e.g. `2023-04-01T00:00:00.123Z`) is taken as-is,
+/// otherwise the time is assumed to be in `timezone`
+pub fn parse_human_time<Tz: TimeZone>(
+    human_time: &str,
+    timezone: &Tz,
+) -> Result<DateTime<Utc>, RipperError> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(human_time) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    HUMAN_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(human_time, format).ok())
+        .and_then(|naive| timezone.from_local_datetime(&naive).earliest())
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| RipperError::TimeParse(human_time.to_string()))
+}
+
+pub fn human_to_unix_epoch(human_time: &str) -> Result<u64, RipperError> {
+    Ok(parse_human_time(human_time, &Utc)?.timestamp() as u64)
+}
+
+/// Parses either a unix epoch, a human time (see `parse_human_time`) or a date, as taken by --start-epoch and the
+/// config file. A date on its own means midnight UTC at the start of it
+pub fn parse_epoch(value: &str) -> Result<u64, String> {
+    if let Ok(epoch) = value.parse::<u64>() {
+        return Ok(epoch);
+    }
+    if let Some(epoch) = date_to_unix_epoch(value, NaiveTime::MIN) {
+        return Ok(epoch);
+    }
+
+    human_to_unix_epoch(value).map_err(|_| {
+        format!(
+            "expected a unix epoch, a date like \"2023-04-01\" or a time like \"2023-04-01 00:00:00\", got \"{}\"",
+            value
+        )
+    })
+}
+
+/// Same as `parse_epoch`, except a date on its own means the end of that day (midnight at the start of the next),
+/// so the whole day is included. For --until
+pub fn parse_until(value: &str) -> Result<u64, String> {
+    match date_to_unix_epoch(value, NaiveTime::MIN) {
+        Some(epoch) => Ok(epoch + 86400),
+        None => parse_epoch(value),
+    }
+}
+
+/// Serde for the match times in the models, e.g. `#[serde(with = "crate::time::backend_time")]`. Reads anything
+/// `parse_human_time` does and writes `BACKEND_TIME_FORMAT`, so saved files look like the responses they came from
+pub mod backend_time {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&time.format(BACKEND_TIME_FORMAT))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse_human_time(&value, &Utc).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    error::RipperError,
+    models::{Location, PredecessorMatch},
+    output::for_each_saved_batch,
+};
+
+/// The box every position in a match should fall inside. The map changes between patches, so these can be
+/// overridden with a `[map_bounds]` table in the config file
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MapBounds {
+    pub min_x: f64,
+    pub max_x: f64,
+    pub min_y: f64,
+    pub max_y: f64,
+    pub min_z: f64,
+    pub max_z: f64,
+}
+
+impl Default for MapBounds {
+    /// Comfortably around the playable area of the current map
+    fn default() -> Self {
+        MapBounds {
+            min_x: -25000.0,
+            max_x: 25000.0,
+            min_y: -25000.0,
+            max_y: 25000.0,
+            min_z: -5000.0,
+            max_z: 10000.0,
+        }
+    }
+}
+
+/// False for the 0/0/0 the backend sends when it has no position, and for anything outside `bounds`
+pub fn validate_location(location: &Location, bounds: &MapBounds) -> bool {
+    let Location { x, y, z } = *location;
+    if x == 0.0 && y == 0.0 && z == 0.0 {
+        return false;
+    }
+    (bounds.min_x..=bounds.max_x).contains(&x)
+        && (bounds.min_y..=bounds.max_y).contains(&y)
+        && (bounds.min_z..=bounds.max_z).contains(&z)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocatedEvent {
+    WardPlacement,
+    WardDestruction,
+    HeroKill,
+}
+
+impl fmt::Display for LocatedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LocatedEvent::WardPlacement => "ward placement",
+            LocatedEvent::WardDestruction => "ward destruction",
+            LocatedEvent::HeroKill => "hero kill",
+        })
+    }
+}
+
+/// Something about a match that doesn't add up. None of these stop a match from being saved
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    NoPlayers,
+    TooManyTeams(Vec<i64>),
+    WinningTeamNotPlaying(i64),
+    InconsistentTeam {
+        player_id: String,
+        teams: Vec<i64>,
+    },
+    UnknownKilledPlayer(String),
+    UnknownKillerPlayer(String),
+    /// `player_id` is the ward's owner, or the victim for a kill
+    BadLocation {
+        event: LocatedEvent,
+        player_id: String,
+        location: Location,
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::NoPlayers => write!(f, "match has no players"),
+            ValidationIssue::TooManyTeams(teams) => write!(f, "more than two teams: {:?}", teams),
+            ValidationIssue::WinningTeamNotPlaying(team) => {
+                write!(f, "winning team {} has no players", team)
+            }
+            ValidationIssue::InconsistentTeam { player_id, teams } => {
+                write!(f, "player {} is on several teams: {:?}", player_id, teams)
+            }
+            ValidationIssue::UnknownKilledPlayer(id) => {
+                write!(f, "hero kill victim {} isn't in the match", id)
+            }
+            ValidationIssue::UnknownKillerPlayer(id) => {
+                write!(f, "hero kill killer {} isn't in the match", id)
+            }
+            ValidationIssue::BadLocation {
+                event,
+                player_id,
+                location,
+            } => write!(
+                f,
+                "{} for {} is off the map at ({}, {}, {})",
+                event, player_id, location.x, location.y, location.z
+            ),
+        }
+    }
+}
+
+pub fn validate_match(
+    m: &PredecessorMatch,
+    bounds: &MapBounds,
+) -> Result<(), Vec<ValidationIssue>> {
+    let mut issues = Vec::new();
+    let mut check_location = |event, player_id: &str, location: &Location| {
+        if !validate_location(location, bounds) {
+            issues.push(ValidationIssue::BadLocation {
+                event,
+                player_id: player_id.to_string(),
+                location: *location,
+            });
+        }
+    };
+    for p in &m.player_data {
+        for w in &p.wards_data.ward_placements {
+            check_location(LocatedEvent::WardPlacement, &p.player_id, &w.location);
+        }
+        for w in &p.wards_data.ward_destructions {
+            check_location(LocatedEvent::WardDestruction, &p.player_id, &w.location);
+        }
+    }
+    for k in &m.hero_kills {
+        check_location(LocatedEvent::HeroKill, &k.killed_player_id, &k.location);
+    }
+
+    if m.player_data.is_empty() {
+        issues.push(ValidationIssue::NoPlayers);
+    }
+
+    let mut player_teams: HashMap<&str, Vec<i64>> = HashMap::new();
+    for p in &m.player_data {
+        let teams = player_teams.entry(&p.player_id).or_default();
+        if !teams.contains(&p.team_id) {
+            teams.push(p.team_id);
+        }
+    }
+    for (player_id, teams) in &player_teams {
+        if teams.len() > 1 {
+            issues.push(ValidationIssue::InconsistentTeam {
+                player_id: player_id.to_string(),
+                teams: teams.clone(),
+            });
+        }
+    }
+
+    let mut teams: Vec<i64> = m
+        .player_data
+        .iter()
+        .map(|p| p.team_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    teams.sort();
+    if teams.len() > 2 {
+        issues.push(ValidationIssue::TooManyTeams(teams.clone()));
+    }
+    if !m.player_data.is_empty() && !teams.contains(&m.winning_team) {
+        issues.push(ValidationIssue::WinningTeamNotPlaying(m.winning_team));
+    }
+
+    for k in &m.hero_kills {
+        if !player_teams.contains_key(k.killed_player_id.as_str()) {
+            issues.push(ValidationIssue::UnknownKilledPlayer(
+                k.killed_player_id.clone(),
+            ));
+        }
+        // Towers and minions get kills too, and those come without a player id
+        if !k.killer_player_id.is_empty() && !player_teams.contains_key(k.killer_player_id.as_str())
+        {
+            issues.push(ValidationIssue::UnknownKillerPlayer(
+                k.killer_player_id.clone(),
+            ));
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+/// Checks every saved match in the output folder, logging each issue. Returns how many matches had issues
+pub fn validate_output_dir(output_dir: &Path, bounds: &MapBounds) -> Result<usize, RipperError> {
+    let mut checked = 0;
+    let mut invalid = 0;
+
+    for_each_saved_batch(output_dir, |matches| {
+        for m in matches {
+            checked += 1;
+            if let Err(issues) = validate_match(m, bounds) {
+                invalid += 1;
+                for issue in issues {
+                    warn!("Match {}: {}", m.match_id, issue);
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    info!("{} of {} matches have issues", invalid, checked);
+    Ok(invalid)
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{
+    config::OutputFormat,
+    error::RipperError,
+    manifest::Manifest,
+    output::{DUP_SUFFIX, GZIP_SUFFIX},
+};
+
+/// A range of time one worker collects matches for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WorkWindow {
+    pub start_epoch: u64,
+    pub end_epoch: u64,
+}
+
+pub fn generate_work_window(starting_epoch: u64, window_size: u64) -> WorkWindow {
+    WorkWindow {
+        start_epoch: starting_epoch,
+        end_epoch: starting_epoch + window_size,
+    }
+}
+
+pub fn generate_work_windows(
+    starting_epoch: u64,
+    end_epoch: Option<u64>,
+    window_size: u64,
+) -> Vec<WorkWindow> {
+    let mut work_windows: Vec<WorkWindow> = Vec::new();
+    let mut starting_epoch = starting_epoch;
+    let end_epoch = end_epoch.unwrap_or_else(|| chrono::Utc::now().timestamp() as u64);
+    loop {
+        let work_window = generate_work_window(starting_epoch, window_size);
+        if work_window.end_epoch <= end_epoch {
+            work_windows.push(work_window.clone());
+            starting_epoch = work_window.end_epoch;
+        } else {
+            break;
+        }
+    }
+    work_windows
+}
+
+/// Splits a `{start}-{end}.{ext}` (or `{start}-{end}.partN.{ext}`, `{start}-{end}.dupN.{ext}`) file name written by
+/// `save_matches` into its epochs and format. Gzipped files (`.{ext}.gz`) are recognized too
+pub fn parse_match_file_name(name: &str) -> Option<(u64, u64, OutputFormat)> {
+    let name = name.strip_suffix(GZIP_SUFFIX).unwrap_or(name);
+    let (stem, format) = OutputFormat::ALL.iter().find_map(|&format| {
+        name.strip_suffix(format.extension())
+            .and_then(|n| n.strip_suffix('.'))
+            .map(|stem| (stem, format))
+    })?;
+    let stem = match stem.rsplit_once(".part") {
+        Some((stem, part)) if part.parse::<usize>().is_ok() => stem,
+        _ => stem,
+    };
+    let stem = match stem.rsplit_once(DUP_SUFFIX) {
+        Some((stem, dup)) if dup.parse::<usize>().is_ok() => stem,
+        _ => stem,
+    };
+    let (start, end) = stem.split_once('-')?;
+    Some((start.parse().ok()?, end.parse().ok()?, format))
+}
+
+/// The format of a `{name}.{ext}` (or `.{ext}.gz`) file, going by its extension alone. The --split-by-match files
+/// are named after their match ids, so this is all their names can say
+pub fn file_format(name: &str) -> Option<OutputFormat> {
+    let name = name.strip_suffix(GZIP_SUFFIX).unwrap_or(name);
+    OutputFormat::ALL.iter().copied().find(|format| {
+        name.strip_suffix(format.extension())
+            .is_some_and(|n| n.ends_with('.'))
+    })
+}
+
+/// Every match file in the output folder and any partition folders under it, sorted by path. That's any file
+/// named like a batch, and any file the manifest lists, which is how the --split-by-match files are found
+pub fn match_files(path: &Path) -> Result<Vec<(PathBuf, OutputFormat)>, RipperError> {
+    let manifest = Manifest::load(path)?;
+    let listed: HashSet<PathBuf> = manifest
+        .files
+        .iter()
+        .map(|entry| path.join(&entry.file))
+        .collect();
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(path) {
+        let entry = entry.map_err(io::Error::from)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Some(name) = entry.file_name().to_str() else {
+            continue;
+        };
+        let format = match parse_match_file_name(name) {
+            Some((_, _, format)) => Some(format),
+            None if listed.contains(entry.path()) => file_format(name),
+            None => None,
+        };
+        if let Some(format) = format {
+            files.push((entry.into_path(), format));
+        }
+    }
+
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// Reads the `{start}-{end}.json` (or `.ndjson`) file names in the matches folder back into epoch ranges
+pub fn downloaded_ranges(path: &Path) -> Result<Vec<(u64, u64)>, RipperError> {
+    Ok(match_files(path)?
+        .iter()
+        .filter_map(|(path, _)| path.file_name()?.to_str().and_then(parse_match_file_name))
+        .map(|(start, end, _)| (start, end))
+        .collect())
+}
+
+/// Drops every work window that is already covered by downloaded files. Only for output saved before the manifest
+/// recorded work windows, see `Manifest::completed_windows`. Consecutive files overlap on their boundary match, so touching ranges are merged before checking coverage
+pub fn filter_completed_windows(
+    work_windows: Vec<WorkWindow>,
+    downloaded: &[(u64, u64)],
+) -> Vec<WorkWindow> {
+    let mut ranges = downloaded.to_vec();
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    work_windows
+        .into_iter()
+        .filter(|window| {
+            !merged
+                .iter()
+                .any(|&(start, end)| start <= window.start_epoch && end >= window.end_epoch)
+        })
+        .collect()
+}
+
+/// Returns the work windows that nothing on disk overlaps at all.
+/// A window that was only partly saved isn't a gap since its first matches landed (--resume will
+/// still re-fetch it). A window that legitimately had zero matches looks exactly like one that failed,
+/// so those are reported too and are worth a second look rather than an automatic alarm
+pub fn find_coverage_gaps(windows: &[WorkWindow], downloaded: &[(u64, u64)]) -> Vec<WorkWindow> {
+    windows
+        .iter()
+        .filter(|window| {
+            !downloaded
+                .iter()
+                .any(|&(start, end)| start < window.end_epoch && end >= window.start_epoch)
+        })
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,43 @@
+use pred_ripper::{anonymize::Anonymizer, PredecessorMatch, Secret};
+
+fn fixture_match() -> PredecessorMatch {
+    let mut matches: Vec<PredecessorMatch> =
+        serde_json::from_str(include_str!("fixtures/matches.json")).unwrap();
+    matches.remove(0)
+}
+
+#[test]
+fn ids_are_replaced_consistently_and_names_removed() {
+    let anonymizer = Anonymizer::new(Secret::new("salt"));
+    let mut m = fixture_match();
+    let original_killer = m.hero_kills[0].killer_player_id.clone();
+    let original_victim = m.hero_kills[0].killed_player_id.clone();
+
+    anonymizer.anonymize_match(&mut m);
+
+    let pseudonym = |id: &str| {
+        m.player_data
+            .iter()
+            .find(|p| p.player_id == anonymizer.pseudonym(id))
+            .map(|p| p.player_id.clone())
+    };
+    assert_eq!(
+        Some(m.hero_kills[0].killer_player_id.clone()),
+        pseudonym(&original_killer)
+    );
+    assert_eq!(
+        Some(m.hero_kills[0].killed_player_id.clone()),
+        pseudonym(&original_victim)
+    );
+    assert_ne!(m.hero_kills[0].killer_player_id, original_killer);
+    assert!(m.player_data.iter().all(|p| p.player_name.is_none()));
+}
+
+#[test]
+fn pseudonyms_depend_on_the_salt() {
+    let a = Anonymizer::new(Secret::new("one"));
+    let b = Anonymizer::new(Secret::new("two"));
+    assert_eq!(a.pseudonym("player"), a.pseudonym("player"));
+    assert_ne!(a.pseudonym("player"), b.pseudonym("player"));
+    assert_eq!(a.pseudonym(""), "");
+}
@@ -0,0 +1,258 @@
+use std::{
+    fs::File,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use pred_ripper::{
+    archive::{append_zip_matches, compression_pool, tar_gz_matches, zip_matches, zip_time},
+    Compression,
+};
+use tempfile::TempDir;
+use zip::ZipArchive;
+
+#[test]
+fn archiving_the_same_files_twice_gives_the_same_bytes() {
+    let output_dir = TempDir::new().unwrap();
+    std::fs::create_dir(output_dir.path().join("2023")).unwrap();
+    for name in ["b.json", "a.json", "2023/c.json"] {
+        std::fs::write(output_dir.path().join(name), name).unwrap();
+    }
+    let archives = TempDir::new().unwrap();
+    let archive = |name: &str| {
+        let path = archives.path().join(name);
+        if name.ends_with(".zip") {
+            zip_matches(output_dir.path(), &path, Compression::Deflate).unwrap();
+        } else {
+            tar_gz_matches(output_dir.path(), &path).unwrap();
+        }
+        std::fs::read(path).unwrap()
+    };
+
+    let (first_zip, first_tar) = (archive("first.zip"), archive("first.tar.gz"));
+    // Zip timestamps have a two second resolution
+    thread::sleep(Duration::from_millis(2100));
+    File::options()
+        .write(true)
+        .open(output_dir.path().join("a.json"))
+        .unwrap()
+        .set_modified(SystemTime::now())
+        .unwrap();
+
+    assert_eq!(first_zip, archive("second.zip"));
+    assert_eq!(first_tar, archive("second.tar.gz"));
+}
+
+fn zip_entries(path: &std::path::Path) -> Vec<(String, String)> {
+    let mut zip = ZipArchive::new(File::open(path).unwrap()).unwrap();
+    (0..zip.len())
+        .map(|i| {
+            let mut entry = zip.by_index(i).unwrap();
+            let mut contents = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut contents).unwrap();
+            (entry.name().to_owned(), contents)
+        })
+        .collect()
+}
+
+#[test]
+fn appending_adds_new_files_to_an_existing_zip() {
+    let output_dir = TempDir::new().unwrap();
+    std::fs::write(output_dir.path().join("a.json"), "a").unwrap();
+    let archives = TempDir::new().unwrap();
+    let zip_path = archives.path().join("matches.zip");
+    // No zip yet, so one is made
+    append_zip_matches(output_dir.path(), &zip_path, Compression::Deflate).unwrap();
+    let first_len = std::fs::metadata(&zip_path).unwrap().len();
+
+    std::fs::write(output_dir.path().join("b.json"), "b").unwrap();
+    append_zip_matches(output_dir.path(), &zip_path, Compression::Deflate).unwrap();
+
+    assert_eq!(
+        zip_entries(&zip_path),
+        [("a.json".into(), "a".into()), ("b.json".into(), "b".into())]
+    );
+    assert!(std::fs::metadata(&zip_path).unwrap().len() > first_len);
+}
+
+#[test]
+fn appending_rewrites_changed_files() {
+    let output_dir = TempDir::new().unwrap();
+    for name in ["a.json", "b.json", "c.json"] {
+        std::fs::write(output_dir.path().join(name), name).unwrap();
+    }
+    let archives = TempDir::new().unwrap();
+    let zip_path = archives.path().join("matches.zip");
+    zip_matches(output_dir.path(), &zip_path, Compression::Deflate).unwrap();
+
+    std::fs::write(output_dir.path().join("b.json"), "changed").unwrap();
+    std::fs::remove_file(output_dir.path().join("c.json")).unwrap();
+    std::fs::write(output_dir.path().join("d.json"), "d.json").unwrap();
+    append_zip_matches(output_dir.path(), &zip_path, Compression::Deflate).unwrap();
+
+    assert_eq!(
+        zip_entries(&zip_path),
+        [
+            ("a.json".into(), "a.json".into()),
+            ("b.json".into(), "changed".into()),
+            ("d.json".into(), "d.json".into()),
+        ]
+    );
+    // Same bytes as zipping the folder from scratch
+    let fresh = archives.path().join("fresh.zip");
+    zip_matches(output_dir.path(), &fresh, Compression::Deflate).unwrap();
+    assert_eq!(
+        std::fs::read(&zip_path).unwrap(),
+        std::fs::read(fresh).unwrap()
+    );
+}
+
+#[test]
+fn compression_runs_on_the_configured_number_of_threads() {
+    let output_dir = TempDir::new().unwrap();
+    for i in 0..100 {
+        std::fs::write(output_dir.path().join(format!("{i}.json")), i.to_string()).unwrap();
+    }
+    let archives = TempDir::new().unwrap();
+    let pool = compression_pool(Some(2)).unwrap();
+    let all_cores = compression_pool(None).unwrap();
+
+    let pooled = archives.path().join("pooled.zip");
+    pool.install(|| zip_matches(output_dir.path(), &pooled, Compression::Deflate))
+        .unwrap();
+    let global = archives.path().join("global.zip");
+    zip_matches(output_dir.path(), &global, Compression::Deflate).unwrap();
+
+    assert_eq!(pool.current_num_threads(), 2);
+    assert_eq!(
+        all_cores.current_num_threads(),
+        thread::available_parallelism().unwrap().get()
+    );
+    assert_eq!(
+        std::fs::read(pooled).unwrap(),
+        std::fs::read(global).unwrap()
+    );
+}
+
+#[test]
+fn match_file_entries_are_stamped_with_their_end_epoch() {
+    let output_dir = TempDir::new().unwrap();
+    // Ends 2023-04-01 01:00:00 UTC
+    std::fs::write(output_dir.path().join("1680307200-1680310800.json"), "[]").unwrap();
+    std::fs::write(output_dir.path().join("manifest.json"), "{}").unwrap();
+    let archives = TempDir::new().unwrap();
+    let zip_path = archives.path().join("matches.zip");
+    zip_matches(output_dir.path(), &zip_path, Compression::Deflate).unwrap();
+
+    let mut zip = ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+    let time = zip
+        .by_name("1680307200-1680310800.json")
+        .unwrap()
+        .last_modified();
+    assert_eq!((time.year(), time.month(), time.day()), (2023, 4, 1));
+    assert_eq!((time.hour(), time.minute(), time.second()), (1, 0, 0));
+    // Files that aren't named after a range keep the fixed default
+    let time = zip.by_name("manifest.json").unwrap().last_modified();
+    assert_eq!(time.year(), 1980);
+}
+
+#[test]
+fn epochs_a_zip_cant_hold_have_no_timestamp() {
+    assert!(zip_time(0).is_none());
+    assert!(zip_time(u64::MAX).is_none());
+    assert_eq!(zip_time(315532800).unwrap().year(), 1980);
+}
+
+#[test]
+fn every_compression_method_reads_back_the_same_files() {
+    let output_dir = TempDir::new().unwrap();
+    let contents = "[{\"matchId\":\"m1\"}]".repeat(50);
+    for name in ["a.json", "b.json"] {
+        std::fs::write(output_dir.path().join(name), &contents).unwrap();
+    }
+    let archives = TempDir::new().unwrap();
+
+    for (compression, method) in [
+        (Compression::Stored, zip::CompressionMethod::Stored),
+        (Compression::Deflate, zip::CompressionMethod::Deflated),
+        (Compression::Zstd, zip::CompressionMethod::Zstd),
+    ] {
+        let zip_path = archives.path().join(format!("{compression:?}.zip"));
+        zip_matches(output_dir.path(), &zip_path, compression).unwrap();
+
+        assert_eq!(
+            zip_entries(&zip_path),
+            [
+                ("a.json".into(), contents.clone()),
+                ("b.json".into(), contents.clone())
+            ]
+        );
+        let mut zip = ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(zip.by_index(0).unwrap().compression(), method);
+    }
+}
+
+#[test]
+fn compressing_on_one_thread_or_many_gives_the_same_zip() {
+    let output_dir = TempDir::new().unwrap();
+    for day in ["01", "02"] {
+        let dir = output_dir.path().join("2023/04").join(day);
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..40 {
+            let contents = format!("[{{\"matchId\":\"{day}-{i}\"}}]").repeat(i + 1);
+            std::fs::write(dir.join(format!("{i}.json")), contents).unwrap();
+        }
+    }
+    let archives = TempDir::new().unwrap();
+    let zip_on = |threads: usize| {
+        let path = archives.path().join(format!("{threads}.zip"));
+        compression_pool(Some(threads))
+            .unwrap()
+            .install(|| zip_matches(output_dir.path(), &path, Compression::Zstd))
+            .unwrap();
+        std::fs::read(path).unwrap()
+    };
+
+    let serial = zip_on(1);
+
+    assert_eq!(serial, zip_on(4));
+    let names: Vec<String> = zip_entries(&archives.path().join("1.zip"))
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+    assert_eq!(names.len(), 80);
+}
+
+#[test]
+fn a_tar_gz_unpacks_back_to_the_output_folder() {
+    let output_dir = TempDir::new().unwrap();
+    std::fs::create_dir_all(output_dir.path().join("2023/04/01")).unwrap();
+    let files = [
+        ("1680307200-1680310800.json", "[1]"),
+        ("2023/04/01/1680314400-1680318000.json", "[2]"),
+        ("manifest.json", "{}"),
+    ];
+    for (name, contents) in files {
+        std::fs::write(output_dir.path().join(name), contents).unwrap();
+    }
+    let archives = TempDir::new().unwrap();
+    let archive_path = archives.path().join("matches.tar.gz");
+
+    tar_gz_matches(output_dir.path(), &archive_path).unwrap();
+
+    let unpacked = TempDir::new().unwrap();
+    tar::Archive::new(flate2::read::GzDecoder::new(
+        File::open(&archive_path).unwrap(),
+    ))
+    .unpack(unpacked.path())
+    .unwrap();
+    for (name, contents) in files {
+        assert_eq!(
+            std::fs::read_to_string(unpacked.path().join(name)).unwrap(),
+            contents
+        );
+    }
+}
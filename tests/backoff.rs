@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use pred_ripper::{backoff::Backoff, BackoffStrategy};
+use rand::{rngs::StdRng, SeedableRng};
+
+fn backoff(strategy: BackoffStrategy, jitter: f64) -> Backoff {
+    Backoff {
+        strategy,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        multiplier: 2.0,
+        jitter,
+    }
+}
+
+/// The waits before the first `retries` retries, from a seeded rng
+fn delays(backoff: &Backoff, retries: u32, seed: u64) -> Vec<Duration> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut previous = Duration::ZERO;
+    (1..=retries)
+        .map(|attempt| {
+            previous = backoff.delay(attempt, previous, &mut rng);
+            previous
+        })
+        .collect()
+}
+
+fn millis(delays: &[u64]) -> Vec<Duration> {
+    delays.iter().map(|&ms| Duration::from_millis(ms)).collect()
+}
+
+#[test]
+fn fixed_waits_the_same_every_time() {
+    let delays = delays(&backoff(BackoffStrategy::Fixed, 0.0), 4, 1);
+
+    assert_eq!(delays, millis(&[100, 100, 100, 100]));
+}
+
+#[test]
+fn exponential_grows_by_the_multiplier_up_to_the_max() {
+    let delays = delays(&backoff(BackoffStrategy::Exponential, 0.0), 6, 1);
+
+    assert_eq!(delays, millis(&[100, 200, 400, 800, 1000, 1000]));
+}
+
+#[test]
+fn jitter_adds_up_to_its_fraction_and_is_repeatable() {
+    let backoff = backoff(BackoffStrategy::Exponential, 0.5);
+    let jittered = delays(&backoff, 6, 7);
+
+    assert_eq!(jittered, delays(&backoff, 6, 7));
+    for (delay, base) in jittered
+        .iter()
+        .zip(millis(&[100, 200, 400, 800, 1000, 1000]))
+    {
+        assert!(*delay >= base && *delay <= base.mul_f64(1.5), "{:?}", delay);
+    }
+}
+
+#[test]
+fn decorrelated_stays_between_the_base_and_a_multiple_of_the_last_delay() {
+    let backoff = backoff(BackoffStrategy::Decorrelated, 0.5);
+    let delays = delays(&backoff, 20, 42);
+
+    assert_eq!(delays, self::delays(&backoff, 20, 42));
+    assert_ne!(delays, self::delays(&backoff, 20, 43));
+    let mut previous = Duration::from_millis(100);
+    for &delay in &delays {
+        assert!(delay >= Duration::from_millis(100), "{:?}", delay);
+        assert!(
+            delay <= (previous * 2).min(Duration::from_secs(1)),
+            "{:?}",
+            delay
+        );
+        previous = delay;
+    }
+}
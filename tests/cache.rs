@@ -0,0 +1,29 @@
+//! The --cache-format bincode files kept next to match files
+
+use pred_ripper::{
+    cache::{cache_path, read_cache, write_cache},
+    CacheFormat, PredecessorMatch,
+};
+use tempfile::TempDir;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn a_batch_reads_back_from_bincode_the_same() {
+    let output_dir = TempDir::new().unwrap();
+    let match_file = output_dir.path().join("1680309000-1680310800.json");
+    let mut matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    // Optional fields left empty go through as well
+    matches[1].player_data[0].role_name = None;
+    matches[1].player_data[0].inventory_data = None;
+    assert!(read_cache(&match_file).unwrap().is_none());
+
+    write_cache(&match_file, CacheFormat::Bincode, &matches).unwrap();
+
+    assert!(cache_path(&match_file).ends_with("1680309000-1680310800.bin"));
+    let cached = read_cache(&match_file).unwrap().unwrap();
+    assert_eq!(
+        serde_json::to_value(&cached).unwrap(),
+        serde_json::to_value(&matches).unwrap()
+    );
+}
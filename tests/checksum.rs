@@ -0,0 +1,146 @@
+//! checksums.txt and --verify-checksums
+
+use std::sync::{atomic::AtomicBool, Arc};
+
+use pred_ripper::{
+    checksum::{append_checksums, hash_file, verify_checksums, write_checksums, CHECKSUMS_FILE},
+    manifest::{Manifest, ManifestEntry},
+    output::save_matches,
+    Config, OutputFormat, PredecessorMatch, Ripper,
+};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC. The fixture's matches end half an hour and an hour after this
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn a_single_changed_byte_fails_verification() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let mut manifest = Manifest::default();
+    for (entry, _) in save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        None,
+        &matches,
+    )
+    .unwrap()
+    {
+        manifest.record(entry);
+    }
+    manifest.save(output_dir.path()).unwrap();
+    let file = output_dir.path().join(&manifest.files[0].file);
+    // The checksum recorded while writing is the same as hashing the file afterwards
+    assert_eq!(
+        manifest.files[0].sha256.as_deref(),
+        Some(hash_file(&file).unwrap().as_str())
+    );
+    assert_eq!(verify_checksums(output_dir.path()).unwrap(), 0);
+
+    let mut bytes = std::fs::read(&file).unwrap();
+    bytes[10] ^= 1;
+    std::fs::write(&file, bytes).unwrap();
+
+    assert_eq!(verify_checksums(output_dir.path()).unwrap(), 1);
+}
+
+#[test]
+fn new_lines_are_appended_and_a_rewrite_drops_stale_ones() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let save = |matches: &[PredecessorMatch]| {
+        save_matches(
+            output_dir.path(),
+            OutputFormat::Json,
+            false,
+            None,
+            false,
+            None,
+            matches,
+        )
+        .unwrap()
+        .remove(0)
+        .0
+    };
+    let first = save(&matches[..1]);
+    let second = save(&matches[1..]);
+    let checksums = || std::fs::read_to_string(output_dir.path().join(CHECKSUMS_FILE)).unwrap();
+
+    append_checksums(output_dir.path(), std::slice::from_ref(&first)).unwrap();
+    let after_first = checksums();
+    append_checksums(output_dir.path(), std::slice::from_ref(&second)).unwrap();
+
+    assert!(checksums().starts_with(&after_first));
+    assert_eq!(checksums().lines().count(), 2);
+
+    // A file saved over under the same name leaves a stale line behind until checksums.txt is rewritten
+    let mut manifest = Manifest::default();
+    manifest.record(first.clone());
+    let rewritten = ManifestEntry {
+        sha256: Some("0".repeat(64)),
+        ..first
+    };
+    append_checksums(output_dir.path(), std::slice::from_ref(&rewritten)).unwrap();
+    manifest.record(rewritten.clone());
+    manifest.record(second);
+    assert_eq!(checksums().lines().count(), 3);
+    write_checksums(output_dir.path(), &manifest).unwrap();
+    assert_eq!(checksums().lines().count(), 2);
+    assert!(checksums().starts_with(&"0".repeat(64)));
+}
+
+#[tokio::test]
+async fn a_run_leaves_a_checksum_line_for_every_file_in_the_manifest() {
+    let server = MockServer::start().await;
+    for (epoch, body) in [(START_EPOCH, FIXTURE), (START_EPOCH + 3600, FIXTURE)] {
+        Mock::given(method("GET"))
+            .and(path(format!("/get-matches-since/{}", epoch)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+
+    Ripper::new(Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 2 * 3600),
+        output_dir: output_dir.path().to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        ..Config::default()
+    })
+    .run_until(Arc::new(AtomicBool::new(false)))
+    .await
+    .unwrap();
+
+    let manifest = Manifest::load(output_dir.path()).unwrap();
+    assert!(!manifest.files.is_empty());
+    let mut expected: Vec<String> = manifest
+        .files
+        .iter()
+        .map(|entry| {
+            let sha256 = hash_file(&output_dir.path().join(&entry.file)).unwrap();
+            format!("{}  {}", sha256, entry.file)
+        })
+        .collect();
+    expected.sort();
+    let checksums = std::fs::read_to_string(output_dir.path().join(CHECKSUMS_FILE)).unwrap();
+    let mut lines: Vec<&str> = checksums.lines().collect();
+    lines.sort();
+    assert_eq!(lines, expected);
+}
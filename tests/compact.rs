@@ -0,0 +1,90 @@
+use pred_ripper::{
+    compact::{compact_dir, CompactSummary},
+    manifest::Manifest,
+    output::{for_each_saved_batch, save_matches},
+    windows::match_files,
+    Config, OutputFormat, PredecessorMatch,
+};
+use tempfile::TempDir;
+
+/// m1 and m2 from the fixture, plus m3 which ends an hour after m2
+fn matches() -> (PredecessorMatch, PredecessorMatch, PredecessorMatch) {
+    let mut fixture: Vec<PredecessorMatch> =
+        serde_json::from_str(include_str!("fixtures/matches.json")).unwrap();
+    let m2 = fixture.pop().unwrap();
+    let m1 = fixture.pop().unwrap();
+    let mut m3 = m1.clone();
+    m3.match_id = "m3".to_string();
+    m3.end_time = m2.end_time + chrono::TimeDelta::hours(1);
+    (m1, m2, m3)
+}
+
+#[test]
+fn small_files_are_merged_deduped_and_in_order() {
+    let (m1, m2, m3) = matches();
+    let output_dir = TempDir::new().unwrap();
+    let mut manifest = Manifest::default();
+    // Saved out of order, with m1 in two of them like neighbouring windows sharing a boundary match
+    for batch in [vec![m3], vec![m1.clone()], vec![m1, m2]] {
+        for (entry, _) in save_matches(
+            output_dir.path(),
+            OutputFormat::Json,
+            false,
+            None,
+            false,
+            None,
+            &batch,
+        )
+        .unwrap()
+        {
+            manifest.record(entry);
+        }
+    }
+    manifest.save(output_dir.path()).unwrap();
+
+    let summary = compact_dir(output_dir.path(), 1024 * 1024, &Config::default()).unwrap();
+
+    assert_eq!(
+        summary,
+        CompactSummary {
+            files_merged: 3,
+            files_written: 1,
+            duplicates: 1,
+        }
+    );
+    assert_eq!(match_files(output_dir.path()).unwrap().len(), 1);
+    let mut ids = Vec::new();
+    for_each_saved_batch(output_dir.path(), |batch| {
+        ids.extend(batch.iter().map(|m| m.match_id.clone()));
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(ids, ["m1", "m2", "m3"]);
+
+    let manifest = Manifest::load(output_dir.path()).unwrap();
+    assert_eq!(manifest.files.len(), 1);
+    assert_eq!(manifest.files[0].match_count, 3);
+}
+
+#[test]
+fn files_at_the_target_size_are_left_alone() {
+    let (m1, m2, _) = matches();
+    let output_dir = TempDir::new().unwrap();
+    for batch in [vec![m1], vec![m2]] {
+        save_matches(
+            output_dir.path(),
+            OutputFormat::Json,
+            false,
+            None,
+            false,
+            None,
+            &batch,
+        )
+        .unwrap();
+    }
+
+    let summary = compact_dir(output_dir.path(), 16, &Config::default()).unwrap();
+
+    assert_eq!(summary, CompactSummary::default());
+    assert_eq!(match_files(output_dir.path()).unwrap().len(), 2);
+}
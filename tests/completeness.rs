@@ -0,0 +1,53 @@
+//! `--report completeness`: how often each optional field is present
+
+use pred_ripper::{
+    report::{Completeness, FieldCompleteness},
+    PredecessorMatch,
+};
+use serde_json::Value;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn counts_present_and_missing_optional_fields() {
+    let mut raw: Vec<Value> = serde_json::from_str(FIXTURE).unwrap();
+    // Four players in all: one without a role, two with inventories and one with healing
+    raw[0]["playerData"][0]["roleName"] = Value::Null;
+    raw[0]["playerData"][1]["inventoryData"] = serde_json::json!([{"itemSlot": 0, "itemId": 1}]);
+    raw[1]["playerData"][0]["inventoryData"] = serde_json::json!([]);
+    raw[1]["playerData"][1]["damageHealData"]["totalHealingDone"] = 500.into();
+    let matches: Vec<PredecessorMatch> = serde_json::from_value(Value::Array(raw)).unwrap();
+
+    let mut completeness = Completeness::default();
+    for m in &matches {
+        completeness.add_match(m);
+    }
+
+    let field = |name: &str| completeness.fields[name];
+    assert_eq!(
+        field("playerData[].roleName"),
+        FieldCompleteness {
+            present: 3,
+            missing: 1,
+            percent_present: 75.0
+        }
+    );
+    assert_eq!(field("playerData[].playerName").percent_present, 100.0);
+    assert_eq!(field("playerData[].inventoryData").percent_present, 50.0);
+    assert_eq!(
+        field("playerData[].damageHealData.totalHealingDone").percent_present,
+        25.0
+    );
+    assert_eq!(
+        field("playerData[].damageHealData.itemHealingDone").percent_present,
+        0.0
+    );
+    assert_eq!(
+        field("playerData[].abilityData[].abilitySlot"),
+        FieldCompleteness {
+            present: 4,
+            missing: 0,
+            percent_present: 100.0
+        }
+    );
+}
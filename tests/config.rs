@@ -0,0 +1,98 @@
+//! `ripper.toml`: loading it, flags overriding it, and catching values the flags wouldn't allow
+
+use std::process::Command;
+
+use pred_ripper::{config::default_pool_size, Config, OutputFormat, RipperError, MAX_POOL_SIZE};
+use tempfile::TempDir;
+
+const SAMPLE: &str = r#"
+start_epoch = 1680307200
+end_epoch = 1680310800
+window_size = 900
+pool_size = 4
+format = "ndjson"
+"#;
+
+#[test]
+fn sample_config_file_is_parsed() {
+    let config = Config::parse(SAMPLE).unwrap();
+
+    assert_eq!(config.start_epoch, 1680307200);
+    assert_eq!(config.end_epoch, Some(1680310800));
+    assert_eq!(config.window_size, 900);
+    assert_eq!(config.pool_size, 4);
+    assert_eq!(config.format, OutputFormat::Ndjson);
+    // Anything left out keeps its default
+    assert_eq!(config.empty_step, Config::default().empty_step);
+    config.check().unwrap();
+}
+
+/// The work windows --verify prints with the sample config file, an empty output folder and `args`
+fn verify_gaps(args: &[&str]) -> Vec<String> {
+    let dir = TempDir::new().unwrap();
+    let config_path = dir.path().join("ripper.toml");
+    std::fs::write(&config_path, SAMPLE).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pred-ripper"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--output-dir")
+        .arg(dir.path().join("matches"))
+        .arg("--verify")
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn flags_override_the_config_file() {
+    assert_eq!(verify_gaps(&[]).len(), 4);
+    assert_eq!(
+        verify_gaps(&["--window-size", "1800"]),
+        ["1680307200-1680309000", "1680309000-1680310800"]
+    );
+}
+
+#[test]
+fn values_the_flags_would_reject_are_errors() {
+    for (toml, message) in [
+        ("window_size = 0", "window_size has to be at least 1"),
+        ("empty_step = 0", "empty_step has to be at least 1"),
+        ("write_buffer = 0", "write_buffer has to be at least 1"),
+        ("pool_size = 0", "pool_size has to be at least 1"),
+        (
+            "cursor = true\nresume = true",
+            "cursor can't be used with resume",
+        ),
+        (
+            "cursor = true\nupdate = true",
+            "cursor can't be used with update",
+        ),
+        (
+            "single_file = \"all.ndjson\"\nresume = true",
+            "single_file can't be used with resume",
+        ),
+    ] {
+        let err = Config::parse(toml).unwrap().check().unwrap_err();
+        assert!(matches!(err, RipperError::InvalidConfig(_)), "{}", err);
+        assert!(err.to_string().contains(message), "{}", err);
+    }
+}
+
+#[test]
+fn pool_size_defaults_to_the_core_count_unless_set() {
+    let cores = std::thread::available_parallelism().unwrap().get() as u64;
+
+    assert_eq!(default_pool_size(), cores.min(MAX_POOL_SIZE));
+    assert_eq!(Config::parse("").unwrap().pool_size, default_pool_size());
+    assert_eq!(Config::parse("pool_size = 3").unwrap().pool_size, 3);
+    // Only the detected default is capped
+    assert_eq!(Config::parse("pool_size = 100").unwrap().pool_size, 100);
+}
@@ -0,0 +1,141 @@
+//! --cursor: one request at a time behind a single persisted cursor
+
+use std::{
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use pred_ripper::{cursor::Cursor, Config, PredecessorMatch, Ripper};
+use serde_json::Value;
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC. The fixture's matches end half an hour and an hour after this
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn config(server: &MockServer, output_dir: &Path) -> Config {
+    Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 7200),
+        output_dir: output_dir.to_path_buf(),
+        cursor: true,
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        ..Config::default()
+    }
+}
+
+async fn respond(server: &MockServer, epoch: u64, matches: &[Value]) {
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", epoch)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(matches))
+        .mount(server)
+        .await;
+}
+
+/// End epochs of every match saved in the output folder, sorted
+fn saved_end_epochs(output_dir: &Path) -> Vec<u64> {
+    let mut end_epochs = Vec::new();
+    pred_ripper::output::for_each_saved_batch(output_dir, |matches| {
+        end_epochs.extend(matches.iter().map(|m| m.end_time.timestamp() as u64));
+        Ok(())
+    })
+    .unwrap();
+    end_epochs.sort();
+    end_epochs
+}
+
+#[tokio::test]
+async fn resuming_after_an_interruption_leaves_no_gap_below_the_cursor() {
+    let fixture: Vec<Value> = serde_json::from_str(FIXTURE).unwrap();
+    let expected: Vec<u64> = serde_json::from_str::<Vec<PredecessorMatch>>(FIXTURE)
+        .unwrap()
+        .iter()
+        .map(|m| m.end_time.timestamp() as u64)
+        .collect();
+    let server = MockServer::start().await;
+    // "Since" is inclusive, so each match comes back again when asking from its own end time
+    respond(&server, START_EPOCH, &fixture[..1]).await;
+    respond(&server, expected[0], &fixture).await;
+    respond(&server, expected[1], &fixture[1..]).await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+
+    // Cut short by --limit after the first match is saved
+    let interrupted = Config {
+        limit: Some(1),
+        ..config(&server, output_dir.path())
+    };
+    Ripper::new(interrupted)
+        .run_cursor_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap();
+    let cursor = Cursor::load(output_dir.path()).unwrap().unwrap();
+    let saved = saved_end_epochs(output_dir.path());
+    assert_eq!(cursor.epoch, expected[1]);
+    assert_eq!(
+        saved,
+        expected
+            .iter()
+            .copied()
+            .filter(|&end| end < cursor.epoch)
+            .collect::<Vec<_>>()
+    );
+
+    Ripper::new(config(&server, output_dir.path()))
+        .run_cursor_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap();
+    let cursor = Cursor::load(output_dir.path()).unwrap().unwrap();
+    assert_eq!(cursor.epoch, START_EPOCH + 7200);
+    assert_eq!(saved_end_epochs(output_dir.path()), expected);
+}
+
+#[tokio::test]
+async fn a_batch_saved_past_the_cursor_is_not_saved_again() {
+    let fixture: Vec<Value> = serde_json::from_str(FIXTURE).unwrap();
+    let expected: Vec<u64> = serde_json::from_str::<Vec<PredecessorMatch>>(FIXTURE)
+        .unwrap()
+        .iter()
+        .map(|m| m.end_time.timestamp() as u64)
+        .collect();
+    let server = MockServer::start().await;
+    respond(&server, START_EPOCH, &fixture[..1]).await;
+    respond(&server, expected[0], &fixture).await;
+    respond(&server, expected[1], &fixture[1..]).await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+    Ripper::new(Config {
+        limit: Some(1),
+        ..config(&server, output_dir.path())
+    })
+    .run_cursor_until(Arc::new(AtomicBool::new(false)))
+    .await
+    .unwrap();
+    // As if the process died after saving the first batch but before moving the cursor past it
+    Cursor { epoch: START_EPOCH }
+        .save(output_dir.path())
+        .unwrap();
+
+    Ripper::new(config(&server, output_dir.path()))
+        .run_cursor_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap();
+
+    assert_eq!(saved_end_epochs(output_dir.path()), expected);
+}
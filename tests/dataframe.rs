@@ -0,0 +1,26 @@
+//! The player-level polars frame behind --format parquet
+#![cfg(feature = "polars")]
+
+use pred_ripper::{dataframe::matches_to_dataframe, PredecessorMatch};
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn a_frame_has_a_row_per_player_per_match() {
+    let mut matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    matches[1].player_data[0].role_name = None;
+
+    let frame = matches_to_dataframe(&matches).unwrap();
+
+    assert_eq!(frame.height(), 4);
+    let match_ids = frame.column("match_id").unwrap().str().unwrap();
+    assert_eq!(match_ids.get(0), Some("m1"));
+    assert_eq!(match_ids.get(3), Some("m2"));
+    let kills = frame.column("kills").unwrap().i64().unwrap();
+    assert_eq!(
+        kills.get(0),
+        Some(matches[0].player_data[0].combat_data.kills)
+    );
+    // A missing role is a null rather than an empty string
+    assert_eq!(frame.column("role_name").unwrap().null_count(), 1);
+}
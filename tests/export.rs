@@ -0,0 +1,97 @@
+//! The flattened tables: player rows for csv, ward events, and hero kills
+
+use pred_ripper::{
+    export::{kill_rows, ward_rows, WardEvent},
+    models::Region,
+    output::save_matches,
+    OutputFormat, PredecessorMatch,
+};
+use tempfile::TempDir;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn fixture_matches() -> Vec<PredecessorMatch> {
+    serde_json::from_str(FIXTURE).unwrap()
+}
+
+#[test]
+fn a_csv_file_has_a_header_and_a_row_per_player() {
+    let output_dir = TempDir::new().unwrap();
+    let m = fixture_matches().remove(0);
+    assert_eq!(m.player_data.len(), 2);
+
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Csv,
+        false,
+        None,
+        false,
+        None,
+        std::slice::from_ref(&m),
+    )
+    .unwrap();
+
+    let mut reader = csv::Reader::from_path(output_dir.path().join(&saved[0].0.file)).unwrap();
+    let header = reader.headers().unwrap().clone();
+    assert_eq!(&header[0], "match_id");
+    assert!(header.iter().any(|column| column == "ability_count"));
+    let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(rows.len(), 2);
+    let player_column = header.iter().position(|c| c == "player_id").unwrap();
+    assert_eq!(&rows[0][0], "m1");
+    assert_eq!(&rows[0][player_column], m.player_data[0].player_id);
+    assert_eq!(&rows[1][player_column], m.player_data[1].player_id);
+}
+
+#[test]
+fn a_placement_and_a_destruction_are_two_ward_rows() {
+    let mut m = fixture_matches().remove(0);
+    // Player a placed one ward and destroyed another, b did nothing
+    m.player_data[1].wards_data.ward_placements.clear();
+    m.player_data[1].wards_data.ward_destructions.clear();
+
+    let rows: Vec<_> = ward_rows(&m).collect();
+    assert_eq!(
+        rows.iter().map(|row| row.event_type).collect::<Vec<_>>(),
+        [WardEvent::Placement, WardEvent::Destruction]
+    );
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row).unwrap();
+    }
+    let csv = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "match_id,player_id,team_id,type_id,game_time,x,y,z,event_type"
+    );
+    assert_eq!(lines[1], "m1,a,0,1,200,1.0,2.0,3.0,placement");
+    assert_eq!(lines[2], "m1,a,0,2,300,1.0,2.0,3.0,destruction");
+    assert_eq!(lines.len(), 3);
+
+    m.player_data[0].wards_data.ward_placements.clear();
+    m.player_data[0].wards_data.ward_destructions.clear();
+    assert_eq!(ward_rows(&m).count(), 0);
+}
+
+#[test]
+fn kill_rows_keep_first_blood_the_location_and_who_made_the_kill() {
+    let mut m = fixture_matches().remove(0);
+    let mut tower_kill = m.hero_kills[0].clone();
+    tower_kill.is_first_blood = false;
+    tower_kill.killer_entity_type = "TOWER".to_string();
+    tower_kill.location.x = -4500.5;
+    m.hero_kills.push(tower_kill);
+
+    let rows: Vec<_> = kill_rows(&m).collect();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!((rows[0].match_id, rows[0].region), ("m1", &Region::NaEast));
+    assert!(rows[0].is_first_blood);
+    assert_eq!((rows[0].x, rows[0].y, rows[0].z), (1.0, 2.0, 3.0));
+    assert_eq!(rows[0].killer_entity_type, "HERO");
+    assert!(!rows[1].is_first_blood);
+    assert_eq!(rows[1].x, -4500.5);
+    assert_eq!(rows[1].killer_entity_type, "TOWER");
+}
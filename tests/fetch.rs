@@ -0,0 +1,937 @@
+//! Runs whole collections against a mock backend standing in for get-matches-since
+
+use std::{
+    path::Path,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use pred_ripper::{
+    fetch::{get_matches_since, RequestOptions},
+    manifest::Manifest,
+    Config, Partition, PredecessorMatch, Ripper, RipperError, Stats,
+};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, Request, Respond, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC. The fixture's matches end half an hour and an hour after this
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn fixture_matches() -> Vec<PredecessorMatch> {
+    serde_json::from_str(FIXTURE).unwrap()
+}
+
+/// A single one hour work window from START_EPOCH, fetched from `server`
+fn test_config(server: &MockServer, output_dir: &Path) -> Config {
+    Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 3600),
+        output_dir: output_dir.to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        ..Config::default()
+    }
+}
+
+async fn run(server: &MockServer, output_dir: &Path) -> Result<Stats, RipperError> {
+    run_with(test_config(server, output_dir)).await
+}
+
+async fn run_with(config: Config) -> Result<Stats, RipperError> {
+    Ripper::new(config)
+        .run_until(Arc::new(AtomicBool::new(false)))
+        .await
+}
+
+/// Every epoch not mocked more specifically comes back empty
+async fn mock_empty_responses(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(server)
+        .await;
+}
+
+fn match_files(output_dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(output_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| pred_ripper::windows::parse_match_file_name(name).is_some())
+        .collect();
+    files.sort();
+    files
+}
+
+#[tokio::test]
+async fn saves_matches_named_after_their_end_times() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run(&server, output_dir.path()).await.unwrap();
+
+    assert_eq!(stats.total_matches, 2);
+    assert_eq!(
+        match_files(output_dir.path()),
+        ["1680309000-1680310800.json"]
+    );
+
+    let saved: Vec<PredecessorMatch> = serde_json::from_slice(
+        &std::fs::read(output_dir.path().join("1680309000-1680310800.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::to_value(&saved).unwrap(),
+        serde_json::to_value(fixture_matches()).unwrap()
+    );
+
+    let manifest = Manifest::load(output_dir.path()).unwrap();
+    assert_eq!(manifest.files.len(), 1);
+    assert_eq!(manifest.files[0].match_count, 2);
+}
+
+#[tokio::test]
+async fn empty_responses_write_no_files() {
+    let server = MockServer::start().await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run(&server, output_dir.path()).await.unwrap();
+
+    assert_eq!(stats.total_matches, 0);
+    assert_eq!(stats.windows_processed, 1);
+    assert!(stats.failed_windows.is_empty());
+    assert!(match_files(output_dir.path()).is_empty());
+}
+
+#[tokio::test]
+async fn client_errors_give_up_on_the_window_without_retrying() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run(&server, output_dir.path()).await.unwrap();
+
+    assert_eq!(stats.total_matches, 0);
+    assert!(match_files(output_dir.path()).is_empty());
+    assert_eq!(stats.failed_windows.len(), 1);
+    assert_eq!(stats.failed_windows[0].start_epoch, START_EPOCH);
+}
+
+#[tokio::test]
+async fn failed_windows_are_an_error_with_fail_on_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+
+    let err = run_with(Config {
+        fail_on_error: true,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap_err();
+
+    assert!(matches!(err, RipperError::WindowsFailed(1)), "{}", err);
+}
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .with_priority(2)
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run(&server, output_dir.path()).await.unwrap();
+
+    assert_eq!(stats.total_matches, 2);
+    assert_eq!(
+        match_files(output_dir.path()),
+        ["1680309000-1680310800.json"]
+    );
+    assert_eq!(stats.retries, 1);
+}
+
+/// A backend that fails the first request twice with a 503 before answering, with retries `max_retries` times
+async fn run_against_two_503s(max_retries: u32) -> (MockServer, Stats) {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .with_priority(2)
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run_with(Config {
+        max_retries,
+        retry_base_delay_ms: 10,
+        retry_max_delay_ms: 10,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+    (server, stats)
+}
+
+#[tokio::test]
+async fn two_503s_then_a_200_is_collected_with_two_retries() {
+    let (_server, stats) = run_against_two_503s(2).await;
+
+    assert_eq!(stats.total_matches, 2);
+    assert_eq!(stats.retries, 2);
+    assert!(stats.failed_windows.is_empty());
+}
+
+#[tokio::test]
+async fn windows_are_given_up_on_after_max_retries() {
+    let (server, stats) = run_against_two_503s(1).await;
+
+    assert_eq!(stats.total_matches, 0);
+    assert_eq!(stats.failed_windows.len(), 1);
+    // The first attempt and the one retry
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn responses_over_the_size_limit_are_abandoned_without_retrying() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let client = reqwest::Client::new();
+    let base_url = format!("{}/get-matches-since", server.uri());
+    let fetch = |max_response_bytes| {
+        let options = RequestOptions {
+            max_response_bytes: Some(max_response_bytes),
+            ..RequestOptions::default()
+        };
+        let (client, base_url) = (&client, &base_url);
+        async move { get_matches_since(client, base_url, START_EPOCH, &options).await }
+    };
+
+    let err = fetch(100).await.unwrap_err();
+    let matches = fetch(FIXTURE.len() as u64).await.unwrap();
+
+    assert!(matches!(
+        err,
+        RipperError::RetriesExhausted { attempts: 1, source, .. }
+            if matches!(*source, RipperError::ResponseTooLarge { max_bytes: 100, .. })
+    ));
+    assert_eq!(matches.len(), 2);
+}
+
+/// The fixture's first match over again, once for each of these end times
+fn matches_ending_at(end_times: &[&str]) -> serde_json::Value {
+    let template: Vec<serde_json::Value> = serde_json::from_str(FIXTURE).unwrap();
+    end_times
+        .iter()
+        .map(|end_time| {
+            let mut m = template[0].clone();
+            m["matchId"] = end_time.to_string().into();
+            m["endTime"] = format!("2023-04-01 {}", end_time).into();
+            m
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn windows_stop_at_their_end_and_leave_the_boundary_to_the_next() {
+    let server = MockServer::start().await;
+    // The first half hour's response runs on into the second
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(matches_ending_at(&["00:15:00", "00:30:00", "00:45:00"])),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH + 1800)))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(matches_ending_at(&["00:30:00", "00:45:00", "01:00:00"])),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+    let config = Config {
+        window_size: 1800,
+        pool_size: 1,
+        ..test_config(&server, output_dir.path())
+    };
+
+    let stats = run_with(config).await.unwrap();
+
+    // 00:30 is only the second window's, and 01:00 is kept since nothing comes after the last window
+    assert_eq!(stats.total_matches, 4);
+    assert_eq!(
+        match_files(output_dir.path()),
+        ["1680308100-1680308100.json", "1680309000-1680310800.json"]
+    );
+    // Neither window asked for anything past its end
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn output_dir_that_cant_be_cleared_is_a_clear_error() {
+    let server = MockServer::start().await;
+    mock_empty_responses(&server).await;
+    // A file where the output folder should be can't be removed as a folder
+    let output_file = tempfile::NamedTempFile::new().unwrap();
+
+    let err = run(&server, output_file.path()).await.unwrap_err();
+
+    assert!(matches!(err, RipperError::ClearOutputDir { .. }), "{}", err);
+    assert!(err.to_string().contains("Another run may be using it"));
+}
+
+#[tokio::test]
+async fn large_responses_are_parsed_in_full() {
+    const COUNT: usize = 2000;
+    let template = fixture_matches().remove(0);
+    let matches: Vec<PredecessorMatch> = (0..COUNT)
+        .map(|i| PredecessorMatch {
+            match_id: format!("m{}", i),
+            ..template.clone()
+        })
+        .collect();
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&matches))
+        .mount(&server)
+        .await;
+    let client = pred_ripper::build_client(Duration::from_secs(30)).unwrap();
+
+    let fetched = get_matches_since(
+        &client,
+        &server.uri(),
+        START_EPOCH,
+        &RequestOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(fetched.len(), COUNT);
+    assert_eq!(fetched[COUNT - 1].match_id, format!("m{}", COUNT - 1));
+}
+
+#[tokio::test]
+async fn corrupt_files_are_removed_and_fetched_again_on_resume() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .expect(2)
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+    run(&server, output_dir.path()).await.unwrap();
+    let file = output_dir.path().join("1680309000-1680310800.json");
+    std::fs::write(&file, &FIXTURE[..FIXTURE.len() / 2]).unwrap();
+
+    let stats = run_with(Config {
+        resume: true,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total_matches, 2);
+    assert_eq!(
+        match_files(output_dir.path()),
+        ["1680309000-1680310800.json"]
+    );
+    let saved: Vec<PredecessorMatch> =
+        serde_json::from_slice(&std::fs::read(&file).unwrap()).unwrap();
+    assert_eq!(saved.len(), 2);
+}
+
+#[tokio::test]
+async fn resuming_a_finished_run_fetches_nothing_again() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+    run(&server, output_dir.path()).await.unwrap();
+    let requests = server.received_requests().await.unwrap().len();
+
+    // The file only covers the matches in it, not the window, which has to be skipped all the same
+    let stats = run_with(Config {
+        resume: true,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total_matches, 0);
+    assert_eq!(server.received_requests().await.unwrap().len(), requests);
+    assert_eq!(
+        match_files(output_dir.path()),
+        ["1680309000-1680310800.json"]
+    );
+}
+
+#[tokio::test]
+async fn batches_waiting_on_a_full_write_buffer_are_all_saved() {
+    let server = MockServer::start().await;
+    // Four quarter hour windows fetched at once, each with a match of its own, all queued for a writer that
+    // only has room for one batch at a time
+    for (i, end_time) in ["00:05:00", "00:20:00", "00:35:00", "00:50:00"]
+        .iter()
+        .enumerate()
+    {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/get-matches-since/{}",
+                START_EPOCH + 900 * i as u64
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(matches_ending_at(&[end_time])))
+            .mount(&server)
+            .await;
+    }
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+    let config = Config {
+        window_size: 900,
+        pool_size: 4,
+        write_buffer: 1,
+        pretty: true,
+        ..test_config(&server, output_dir.path())
+    };
+
+    let stats = run_with(config).await.unwrap();
+
+    assert_eq!(stats.total_matches, 4);
+    assert_eq!(
+        match_files(output_dir.path()),
+        [
+            "1680307500-1680307500.json",
+            "1680308400-1680308400.json",
+            "1680309300-1680309300.json",
+            "1680310200-1680310200.json"
+        ]
+    );
+    assert_eq!(Manifest::load(output_dir.path()).unwrap().files.len(), 4);
+}
+
+#[tokio::test]
+async fn a_batch_that_cant_be_saved_fails_the_run() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+    // A folder where the file's temporary copy goes can't be written to. --resume keeps it from being cleared away
+    std::fs::create_dir(output_dir.path().join("1680309000-1680310800.json.tmp")).unwrap();
+
+    let err = run_with(Config {
+        resume: true,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap_err();
+
+    assert!(matches!(err, RipperError::Io(_)), "{}", err);
+    assert!(match_files(output_dir.path()).is_empty());
+    // Nothing claims the window got past the matches that weren't saved
+    assert!(!output_dir.path().join("checkpoint.json").exists());
+}
+
+#[tokio::test]
+async fn a_match_seen_by_two_windows_is_saved_once() {
+    let server = MockServer::start().await;
+    let mut first = matches_ending_at(&["00:10:00", "00:20:00"]);
+    let mut second = matches_ending_at(&["00:40:00", "00:50:00"]);
+    // The backend hands the same match to both windows, once with a later end time
+    first[1]["matchId"] = "twice".into();
+    second[0]["matchId"] = "twice".into();
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(first))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH + 1800)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(second))
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run_with(Config {
+        window_size: 1800,
+        pool_size: 1,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total_matches, 3);
+    let mut saved: Vec<String> = Vec::new();
+    for file in match_files(output_dir.path()) {
+        let matches: Vec<PredecessorMatch> =
+            serde_json::from_slice(&std::fs::read(output_dir.path().join(file)).unwrap()).unwrap();
+        saved.extend(matches.into_iter().map(|m| m.match_id));
+    }
+    saved.sort();
+    assert_eq!(saved, ["00:10:00", "00:50:00", "twice"]);
+}
+
+#[tokio::test]
+async fn batches_ending_on_the_epoch_asked_for_still_move_the_window_along() {
+    let server = MockServer::start().await;
+    // Each batch's last match ends right on the epoch it was asked for, so going by end times alone
+    // would ask for the same epoch forever
+    for (epoch, end_time) in [(START_EPOCH, "00:00:00"), (START_EPOCH + 1, "00:00:01")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/get-matches-since/{}", epoch)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(matches_ending_at(&[end_time])))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = tokio::time::timeout(Duration::from_secs(10), run(&server, output_dir.path()))
+        .await
+        .expect("the window never finished")
+        .unwrap();
+
+    assert_eq!(stats.total_matches, 2);
+    assert!(stats.failed_windows.is_empty());
+}
+
+#[tokio::test]
+async fn the_manifest_has_an_entry_for_every_file_with_its_count() {
+    let server = MockServer::start().await;
+    for (epoch, end_times) in [
+        (START_EPOCH, &["00:10:00", "00:20:00"][..]),
+        (START_EPOCH + 1800, &["00:40:00"][..]),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/get-matches-since/{}", epoch)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(matches_ending_at(end_times)))
+            .mount(&server)
+            .await;
+    }
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    run_with(Config {
+        window_size: 1800,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    let mut manifest = Manifest::load(output_dir.path()).unwrap();
+    manifest.files.sort_by(|a, b| a.file.cmp(&b.file));
+    let files: Vec<(&str, usize)> = manifest
+        .files
+        .iter()
+        .map(|entry| (entry.file.as_str(), entry.match_count))
+        .collect();
+    assert_eq!(
+        files,
+        [
+            ("1680307800-1680308400.json", 2),
+            ("1680309600-1680309600.json", 1)
+        ]
+    );
+    for entry in &manifest.files {
+        let path = output_dir.path().join(&entry.file);
+        assert_eq!(entry.bytes, std::fs::metadata(path).unwrap().len());
+        assert!(entry.sha256.is_some());
+    }
+}
+
+/// Answers every request with nothing after `delay`, noting when each one came in
+struct SlowEmptyResponses {
+    delay: Duration,
+    arrivals: Arc<Mutex<Vec<Instant>>>,
+}
+
+impl Respond for SlowEmptyResponses {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        self.arrivals.lock().unwrap().push(Instant::now());
+        ResponseTemplate::new(200)
+            .set_body_string("[]")
+            .set_delay(self.delay)
+    }
+}
+
+#[tokio::test]
+async fn no_more_than_pool_size_windows_are_fetched_at_once() {
+    const DELAY: Duration = Duration::from_millis(300);
+    let server = MockServer::start().await;
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+    Mock::given(method("GET"))
+        .respond_with(SlowEmptyResponses {
+            delay: DELAY,
+            arrivals: arrivals.clone(),
+        })
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+
+    // Six windows of one empty response each
+    let stats = run_with(Config {
+        window_size: 600,
+        pool_size: 2,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stats.windows_processed, 6);
+    let arrivals = arrivals.lock().unwrap();
+    assert_eq!(arrivals.len(), 6);
+    // A request is in flight from when it arrives until its delayed response goes out
+    let most_in_flight = arrivals
+        .iter()
+        .map(|&at| {
+            arrivals
+                .iter()
+                .filter(|&&other| other <= at && at < other + DELAY)
+                .count()
+        })
+        .max()
+        .unwrap();
+    assert_eq!(most_in_flight, 2);
+}
+
+#[tokio::test]
+async fn the_zip_is_only_made_without_no_zip() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let archives = TempDir::new().unwrap();
+
+    for no_zip in [true, false] {
+        let output_dir = TempDir::new().unwrap();
+        let zip_path = archives.path().join(format!("no-zip-{no_zip}.zip"));
+        run_with(Config {
+            no_zip,
+            zip_path: Some(zip_path.clone()),
+            ..test_config(&server, output_dir.path())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(zip_path.exists(), !no_zip);
+        // The raw files are kept either way
+        assert_eq!(
+            match_files(output_dir.path()),
+            ["1680309000-1680310800.json"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn the_summary_adds_up_every_window() {
+    let server = MockServer::start().await;
+    let mut second_hour = matches_ending_at(&["01:20:00", "01:40:00"]);
+    second_hour[0]["region"] = "EUROPE".into();
+    second_hour[1]["gameMode"] = "BRAWL".into();
+    for (epoch, body) in [
+        (START_EPOCH, matches_ending_at(&["00:20:00"])),
+        (START_EPOCH + 3600, second_hour),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/get-matches-since/{}", epoch)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+    }
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run_with(Config {
+        end_epoch: Some(START_EPOCH + 2 * 3600),
+        summary: true,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total_matches, 3);
+    assert_eq!(stats.windows_processed, 2);
+    assert_eq!(stats.windows_skipped, 0);
+    assert_eq!(
+        stats.matches_per_region,
+        [("EUROPE".to_string(), 1), ("NAEAST".to_string(), 2)].into()
+    );
+    assert_eq!(
+        stats.matches_per_game_mode,
+        [("BRAWL".to_string(), 1), ("RANKED".to_string(), 2)].into()
+    );
+    let file_bytes: u64 = match_files(output_dir.path())
+        .iter()
+        .map(|file| {
+            std::fs::metadata(output_dir.path().join(file))
+                .unwrap()
+                .len()
+        })
+        .sum();
+    assert_eq!(stats.bytes_written, file_bytes);
+    // The same totals end up in summary.json
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(output_dir.path().join("summary.json")).unwrap())
+            .unwrap();
+    assert_eq!(summary["total_matches"], 3);
+    assert_eq!(summary["matches_per_region"]["EUROPE"], 1);
+}
+
+#[tokio::test]
+async fn a_dry_run_makes_no_requests_and_writes_nothing() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let dir = TempDir::new().unwrap();
+    let output_dir = dir.path().join("matches");
+
+    let windows = Ripper::new(Config {
+        end_epoch: Some(START_EPOCH + 3 * 3600),
+        zip_path: Some(dir.path().join("matches.zip")),
+        no_zip: false,
+        ..test_config(&server, &output_dir)
+    })
+    .dry_run()
+    .unwrap();
+
+    assert_eq!(windows.len(), 3);
+    assert_eq!(windows[0].start_epoch, START_EPOCH);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn the_run_stops_once_the_limit_is_reached() {
+    let server = MockServer::start().await;
+    for hour in 0..3 {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/get-matches-since/{}",
+                START_EPOCH + hour * 3600
+            )))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(matches_ending_at(&[
+                    &format!("{:02}:20:00", hour),
+                    &format!("{:02}:40:00", hour),
+                ])),
+            )
+            .mount(&server)
+            .await;
+    }
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run_with(Config {
+        end_epoch: Some(START_EPOCH + 3 * 3600),
+        pool_size: 1,
+        limit: Some(3),
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    // The window that went over the limit still saves all of its batch, but nothing after it is fetched
+    assert_eq!(stats.total_matches, 4);
+    assert_eq!(match_files(output_dir.path()).len(), 2);
+    let requested_third_hour = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .any(|request| {
+            request
+                .url
+                .path()
+                .ends_with(&(START_EPOCH + 2 * 3600).to_string())
+        });
+    assert!(!requested_third_hour);
+}
+
+#[tokio::test]
+async fn a_batch_from_two_regions_is_saved_to_a_folder_each() {
+    let server = MockServer::start().await;
+    let mut batch = matches_ending_at(&["00:20:00", "00:30:00", "00:40:00"]);
+    batch[1]["region"] = "EUROPE".into();
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(batch))
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    run_with(Config {
+        partition: Some(Partition::Region),
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+        match_files(&output_dir.path().join("NAEAST")),
+        ["1680308400-1680309600.json"]
+    );
+    assert_eq!(
+        match_files(&output_dir.path().join("EUROPE")),
+        ["1680309000-1680309000.json"]
+    );
+    let mut files: Vec<String> = Manifest::load(output_dir.path())
+        .unwrap()
+        .files
+        .into_iter()
+        .map(|entry| entry.file)
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "EUROPE/1680309000-1680309000.json",
+            "NAEAST/1680308400-1680309600.json"
+        ]
+    );
+}
+
+/// The epochs the mock was asked for, in order
+async fn requested_epochs(server: &MockServer) -> Vec<u64> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            request
+                .url
+                .path()
+                .rsplit('/')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn matches_after_an_empty_response_are_still_collected() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH + 900)))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(matches_ending_at(&["00:40:00", "00:50:00"])),
+        )
+        .mount(&server)
+        .await;
+    mock_empty_responses(&server).await;
+    let output_dir = TempDir::new().unwrap();
+
+    let stats = run_with(Config {
+        empty_step: 900,
+        ..test_config(&server, output_dir.path())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total_matches, 2);
+    // Nothing at the start, so it steps forward and finds them, then carries on to the end of the window
+    assert_eq!(
+        requested_epochs(&server).await,
+        [START_EPOCH, START_EPOCH + 900, START_EPOCH + 3000]
+    );
+}
+
+#[tokio::test]
+async fn the_adaptive_window_makes_fewer_requests_over_sparse_history() {
+    let requests_made = |adaptive_window| async move {
+        let server = MockServer::start().await;
+        mock_empty_responses(&server).await;
+        let output_dir = TempDir::new().unwrap();
+        run_with(Config {
+            end_epoch: Some(START_EPOCH + 6 * 3600),
+            window_size: 6 * 3600,
+            empty_step: 600,
+            adaptive_window,
+            max_stride: 7200,
+            ..test_config(&server, output_dir.path())
+        })
+        .await
+        .unwrap();
+        requested_epochs(&server).await
+    };
+
+    let fixed = requests_made(false).await;
+    let adaptive = requests_made(true).await;
+
+    assert_eq!(fixed.len(), 36);
+    // 600, 1200, 2400 and 4800 seconds on from each other, then the 7200 second cap
+    assert_eq!(
+        adaptive,
+        [0, 600, 1800, 4200, 9000, 16200].map(|offset| START_EPOCH + offset)
+    );
+}
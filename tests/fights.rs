@@ -0,0 +1,63 @@
+//! `--report fights`: kills grouped into team fights
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use pred_ripper::{
+    fights::{detect_team_fights, FightThresholds},
+    Config, PredecessorMatch,
+};
+use serde_json::{json, Value};
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn kill(killer: &str, killed: &str, x: f64, y: f64, game_time: i64) -> Value {
+    json!({
+        "killedPlayerId": killed,
+        "killedHeroName": "Grux",
+        "killerPlayerId": killer,
+        "killerHeroName": "Murdock",
+        "killerEntityType": "HERO",
+        "isFirstBlood": false,
+        "location": {"x": x, "y": y, "z": 0.0},
+        "gameTime": game_time
+    })
+}
+
+#[test]
+fn close_kills_are_one_fight_and_a_distant_kill_is_its_own() {
+    let mut raw: Vec<Value> = serde_json::from_str(FIXTURE).unwrap();
+    // Players "a" (team 0) and "b" (team 1) trade kills in mid, then "a" gets a pick across the map
+    raw[0]["heroKills"] = json!([
+        kill("b", "a", 1000.0, 1000.0, 310),
+        kill("a", "b", 1200.0, 900.0, 300),
+        kill("a", "b", 1500.0, 1100.0, 318),
+        kill("a", "b", 15000.0, -12000.0, 325),
+    ]);
+    let m: PredecessorMatch = serde_json::from_value(raw.swap_remove(0)).unwrap();
+
+    let result = detect_team_fights(&m, &FightThresholds::default());
+    assert_eq!(result.match_id, m.match_id);
+    assert_eq!(result.fights.len(), 2);
+
+    let fight = &result.fights[0];
+    assert_eq!(fight.kills, 3);
+    assert_eq!(fight.start_time.as_seconds(), 300);
+    assert_eq!(fight.duration_secs, 18);
+    assert_eq!(
+        fight.participants,
+        BTreeSet::from(["a".to_string(), "b".to_string()])
+    );
+    assert_eq!(fight.net_kills, BTreeMap::from([(0, 1), (1, -1)]));
+
+    let pick = &result.fights[1];
+    assert_eq!(pick.kills, 1);
+    assert_eq!(pick.duration_secs, 0);
+    assert_eq!(pick.net_kills, BTreeMap::from([(0, 1), (1, -1)]));
+}
+
+#[test]
+fn thresholds_can_be_set_in_the_config_file() {
+    let config = Config::parse("[team_fights]\nwindow_secs = 5\n").unwrap();
+    assert_eq!(config.team_fights.window_secs, 5);
+    assert_eq!(config.team_fights.radius, FightThresholds::default().radius);
+}
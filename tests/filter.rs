@@ -0,0 +1,188 @@
+//! Filtering what's saved with --region, --game-mode, --min-duration and --max-duration
+
+use std::{
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use pred_ripper::{
+    models::{GameMode, Region},
+    Config, PredecessorMatch, Ripper, RipperError,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+/// A batch of matches ending 5, 15, 25... minutes after START_EPOCH, with these ids, durations and regions
+fn batch(matches: &[(&str, i64, &str)]) -> Value {
+    let template: Vec<Value> = serde_json::from_str(FIXTURE).unwrap();
+    let batch = matches
+        .iter()
+        .enumerate()
+        .map(|(i, (id, duration, region))| {
+            let mut m = template[0].clone();
+            m["matchId"] = (*id).into();
+            m["gameDuration"] = (*duration).into();
+            m["region"] = (*region).into();
+            m["endTime"] = format!("2023-04-01 00:{:02}:00", i * 10 + 5).into();
+            m
+        })
+        .collect();
+    Value::Array(batch)
+}
+
+fn saved_match_ids(output_dir: &Path) -> Vec<String> {
+    let mut ids = Vec::new();
+    for entry in std::fs::read_dir(output_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        if pred_ripper::windows::parse_match_file_name(name).is_some() {
+            let matches: Vec<PredecessorMatch> =
+                serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+            ids.extend(matches.into_iter().map(|m| m.match_id));
+        }
+    }
+    ids.sort();
+    ids
+}
+
+/// Runs a one hour window from START_EPOCH that gets `batch` and then nothing, with the filters `filters` adds
+/// to the config. Gives the ids saved and the epochs asked for
+async fn run_filtered(
+    batch: Value,
+    filters: impl FnOnce(Config) -> Config,
+) -> (Vec<String>, Vec<String>) {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(batch))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+    let config = filters(Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 3600),
+        output_dir: output_dir.path().to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        ..Config::default()
+    });
+
+    Ripper::new(config)
+        .run_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap();
+
+    let epochs = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.url.path().rsplit('/').next().unwrap().to_owned())
+        .collect();
+    (saved_match_ids(output_dir.path()), epochs)
+}
+
+#[tokio::test]
+async fn only_matches_within_the_duration_range_are_saved() {
+    let batch = batch(&[
+        ("surrender", 300, "NAEAST"),
+        ("short", 900, "NAEAST"),
+        ("full", 1800, "NAEAST"),
+        ("europe", 1800, "EUROPE"),
+        ("long", 2400, "NAEAST"),
+        ("marathon", 3000, "NAEAST"),
+    ]);
+
+    let (saved, epochs) = run_filtered(batch, |config| Config {
+        regions: vec![Region::NaEast],
+        min_duration: Some(900),
+        max_duration: Some(2400),
+        ..config
+    })
+    .await;
+
+    assert_eq!(saved, ["full", "long", "short"]);
+    // The next request carries on from the end of "marathon", not the last match that was kept
+    assert_eq!(epochs[1], (START_EPOCH + 55 * 60).to_string());
+}
+
+#[tokio::test]
+async fn only_matches_from_the_regions_asked_for_are_saved() {
+    let mixed = batch(&[
+        ("east", 1800, "NAEAST"),
+        ("europe", 1800, "EUROPE"),
+        ("west", 1800, "NAWEST"),
+        ("asia", 1800, "ASIA"),
+    ]);
+
+    let (saved, _) = run_filtered(mixed.clone(), |config| Config {
+        regions: vec![Region::NaEast, Region::NaWest],
+        ..config
+    })
+    .await;
+    assert_eq!(saved, ["east", "west"]);
+
+    // A window left with nothing after filtering writes no file at all
+    let (saved, _) = run_filtered(mixed, |config| Config {
+        regions: vec![Region::Oceania],
+        ..config
+    })
+    .await;
+    assert!(saved.is_empty());
+}
+
+#[tokio::test]
+async fn only_matches_in_the_game_modes_asked_for_are_saved() {
+    let mut batch = batch(&[
+        ("ranked", 1800, "NAEAST"),
+        ("brawl", 1800, "NAEAST"),
+        ("pvp", 1800, "NAEAST"),
+        ("custom", 1800, "NAEAST"),
+    ]);
+    for (m, mode) in batch
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .zip(["RANKED", "BRAWL", "PVP", "CUSTOM"])
+    {
+        m["gameMode"] = mode.into();
+    }
+
+    let (saved, epochs) = run_filtered(batch, |config| Config {
+        game_modes: vec![GameMode::Ranked, GameMode::Pvp],
+        ..config
+    })
+    .await;
+
+    assert_eq!(saved, ["pvp", "ranked"]);
+    // From the end of "custom", which was filtered out, so nothing after "pvp" is skipped or asked for twice
+    assert_eq!(epochs[1], (START_EPOCH + 35 * 60).to_string());
+}
+
+#[test]
+fn a_minimum_above_the_maximum_is_an_error() {
+    let config = Config {
+        min_duration: Some(1800),
+        max_duration: Some(900),
+        ..Config::default()
+    };
+    assert!(matches!(
+        config.check(),
+        Err(RipperError::InvalidDurationRange { .. })
+    ));
+}
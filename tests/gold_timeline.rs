@@ -0,0 +1,27 @@
+//! `--report gold-timeline`: team gold summed at every interval
+
+use pred_ripper::{
+    export::{gold_timeline_rows, GoldRow},
+    PredecessorMatch,
+};
+use serde_json::{json, Value};
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn sums_a_teams_gold_cut_to_the_shortest_timeline() {
+    let mut raw: Vec<Value> = serde_json::from_str(FIXTURE).unwrap();
+    let players = &mut raw[0]["playerData"];
+    players[1]["teamId"] = 0.into();
+    players[0]["incomeData"]["goldEarnedAtInterval"] = json!([100, 250, 400]);
+    players[1]["incomeData"]["goldEarnedAtInterval"] = json!([50, 120]);
+    let m: PredecessorMatch = serde_json::from_value(raw.swap_remove(0)).unwrap();
+
+    let row = |interval, gold| GoldRow {
+        match_id: &m.match_id,
+        team_id: 0,
+        interval,
+        gold,
+    };
+    assert_eq!(gold_timeline_rows(&m), vec![row(0, 150), row(1, 370)]);
+}
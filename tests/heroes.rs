@@ -0,0 +1,23 @@
+//! `--report heroes`: games, wins and kill totals per hero
+
+use pred_ripper::{report::aggregate_hero_stats, PredecessorMatch};
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn a_hero_on_both_sides_gets_one_win_and_the_sum_of_its_kda() {
+    let mut matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    matches[1].game_duration = 2400;
+    // Murdock is on team 0 both times, which wins the first match and loses the second
+    assert_eq!((matches[0].winning_team, matches[1].winning_team), (0, 1));
+
+    let stats = aggregate_hero_stats(&matches);
+
+    let murdock = &stats["Murdock"];
+    assert_eq!((murdock.games, murdock.wins), (2, 1));
+    assert_eq!((murdock.kills, murdock.deaths, murdock.assists), (7, 5, 8));
+    assert_eq!(murdock.average_game_duration, 2100.0);
+    let grux = &stats["Grux"];
+    assert_eq!((grux.games, grux.wins), (2, 1));
+    assert_eq!(stats.len(), 2);
+}
@@ -0,0 +1,36 @@
+//! --log-format json and --log-file, run through the binary since logging is set up in main
+
+use std::process::Command;
+
+use serde_json::Value;
+use tempfile::TempDir;
+
+#[test]
+fn json_logs_are_a_record_per_line_on_stderr_and_in_the_log_file() {
+    let dir = TempDir::new().unwrap();
+    let log_file = dir.path().join("ripper.log");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pred-ripper"))
+        .args(["--start-epoch", "1680307200", "--end-epoch", "1680314400"])
+        .args(["--dry-run", "--log-format", "json"])
+        .arg("--output-dir")
+        .arg(dir.path().join("matches"))
+        .arg("--log-file")
+        .arg(&log_file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let records: Vec<Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let record = records
+        .iter()
+        .find(|record| record["fields"]["message"] == "Would collect 2 work windows")
+        .unwrap();
+    assert_eq!(record["level"], "INFO");
+    assert!(record["timestamp"].is_string());
+    assert_eq!(std::fs::read_to_string(log_file).unwrap(), stderr);
+}
@@ -0,0 +1,136 @@
+use std::{collections::BTreeSet, path::Path};
+
+use pred_ripper::{
+    merge::{merge_dirs, MergeSummary},
+    output::{for_each_saved_batch, save_matches},
+    Config, OutputFormat, PredecessorMatch,
+};
+use tempfile::TempDir;
+
+/// m1 and m2 from the fixture, plus m3 which ends an hour after m2
+fn matches() -> (PredecessorMatch, PredecessorMatch, PredecessorMatch) {
+    let mut fixture: Vec<PredecessorMatch> =
+        serde_json::from_str(include_str!("fixtures/matches.json")).unwrap();
+    let m2 = fixture.pop().unwrap();
+    let m1 = fixture.pop().unwrap();
+    let mut m3 = m1.clone();
+    m3.match_id = "m3".to_string();
+    m3.end_time = m2.end_time + chrono::TimeDelta::hours(1);
+    (m1, m2, m3)
+}
+
+fn output_dir_with(matches: &[PredecessorMatch]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    save_matches(
+        dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        None,
+        matches,
+    )
+    .unwrap();
+    dir
+}
+
+fn saved_match_ids(dir: &Path) -> Vec<String> {
+    let mut ids = Vec::new();
+    for_each_saved_batch(dir, |matches| {
+        ids.extend(matches.iter().map(|m| m.match_id.clone()));
+        Ok(())
+    })
+    .unwrap();
+    ids
+}
+
+#[test]
+fn overlapping_matches_are_merged_once() {
+    let (m1, m2, m3) = matches();
+    let a = output_dir_with(&[m1, m2.clone()]);
+    let b = output_dir_with(&[m2, m3]);
+    let merged = TempDir::new().unwrap();
+
+    let summary = merge_dirs(
+        &[a.path().to_path_buf(), b.path().to_path_buf()],
+        merged.path(),
+        &Config::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        summary,
+        MergeSummary {
+            matches: 3,
+            duplicates: 1,
+            conflicts: 0,
+        }
+    );
+    let ids = saved_match_ids(merged.path());
+    assert_eq!(ids.len(), 3);
+    assert_eq!(
+        ids.into_iter().collect::<BTreeSet<_>>(),
+        BTreeSet::from(["m1".to_string(), "m2".to_string(), "m3".to_string()])
+    );
+}
+
+#[test]
+fn conflicting_copies_keep_the_first_input() {
+    let (m1, m2, _) = matches();
+    let mut changed = m2.clone();
+    changed.winning_team = 1 - changed.winning_team;
+    let a = output_dir_with(&[m1.clone(), m2.clone()]);
+    let b = output_dir_with(&[m1, changed]);
+    let merged = TempDir::new().unwrap();
+
+    let summary = merge_dirs(
+        &[a.path().to_path_buf(), b.path().to_path_buf()],
+        merged.path(),
+        &Config::default(),
+    )
+    .unwrap();
+
+    assert_eq!(summary.matches, 2);
+    assert_eq!(summary.duplicates, 1);
+    assert_eq!(summary.conflicts, 1);
+
+    let mut winning_team = None;
+    for_each_saved_batch(merged.path(), |matches| {
+        winning_team = matches
+            .iter()
+            .find(|m| m.match_id == "m2")
+            .map(|m| m.winning_team);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(winning_team, Some(m2.winning_team));
+}
+
+#[test]
+fn files_with_the_same_range_are_merged_into_one() {
+    let (m1, m2, _) = matches();
+    // Different matches that end on the same seconds, so b's file has the same name as a's
+    let mut m4 = m1.clone();
+    m4.match_id = "m4".to_string();
+    let mut m5 = m2.clone();
+    m5.match_id = "m5".to_string();
+    let a = output_dir_with(&[m1, m2]);
+    let b = output_dir_with(&[m4, m5]);
+    let merged = TempDir::new().unwrap();
+
+    let summary = merge_dirs(
+        &[a.path().to_path_buf(), b.path().to_path_buf()],
+        merged.path(),
+        &Config::default(),
+    )
+    .unwrap();
+
+    assert_eq!(summary.matches, 4);
+    let mut ids = saved_match_ids(merged.path());
+    ids.sort();
+    assert_eq!(ids, ["m1", "m2", "m4", "m5"]);
+    let manifest = pred_ripper::manifest::Manifest::load(merged.path()).unwrap();
+    assert_eq!(manifest.files.len(), 1);
+    assert_eq!(manifest.files[0].file, "1680309000-1680310800.json");
+    assert_eq!(manifest.files[0].match_count, 4);
+}
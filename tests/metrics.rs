@@ -0,0 +1,89 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use pred_ripper::{
+    metrics::{LatencyHistogram, Metrics},
+    Stats,
+};
+
+#[test]
+fn render_lists_every_metric() {
+    let metrics = Metrics::default();
+    metrics.requests.fetch_add(3, Ordering::Relaxed);
+    metrics.retries.fetch_add(1, Ordering::Relaxed);
+    metrics.record_status(503);
+    metrics.record_status(404);
+    metrics.record_status(404);
+    metrics.matches_saved.fetch_add(20, Ordering::Relaxed);
+    metrics.active_workers.fetch_add(2, Ordering::Relaxed);
+
+    let rendered = metrics.render();
+
+    for line in [
+        "pred_ripper_requests_total 3",
+        "pred_ripper_retries_total 1",
+        "pred_ripper_client_errors_total 2",
+        "pred_ripper_server_errors_total 1",
+        "pred_ripper_matches_saved_total 20",
+        "pred_ripper_bytes_written_total 0",
+        "pred_ripper_active_workers 2",
+        "# TYPE pred_ripper_active_workers gauge",
+    ] {
+        assert!(
+            rendered.lines().any(|l| l == line),
+            "{:?} missing from\n{}",
+            line,
+            rendered
+        );
+    }
+}
+
+#[test]
+fn latency_percentiles_come_from_the_recorded_requests() {
+    let histogram = LatencyHistogram::default();
+    assert_eq!(histogram.percentile(50.0), None);
+
+    // 90 fast requests, 9 slow ones and one very slow one
+    for _ in 0..90 {
+        histogram.record(Duration::from_millis(5));
+    }
+    for _ in 0..9 {
+        histogram.record(Duration::from_millis(200));
+    }
+    histogram.record(Duration::from_secs(3));
+
+    assert_eq!(histogram.count(), 100);
+    assert_eq!(histogram.percentile(50.0), Some(Duration::from_millis(5)));
+    assert_eq!(histogram.percentile(90.0), Some(Duration::from_millis(5)));
+    assert_eq!(histogram.percentile(95.0), Some(Duration::from_millis(192)));
+    assert_eq!(
+        histogram.percentile(100.0),
+        Some(Duration::from_millis(2816))
+    );
+}
+
+#[test]
+fn bucketed_latencies_are_within_an_eighth() {
+    for ms in [9, 123, 4567, 89012] {
+        let histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_millis(ms));
+        let bucketed = histogram.percentile(50.0).unwrap().as_millis() as u64;
+        assert!(
+            bucketed <= ms && ms - bucketed <= ms / 8,
+            "{ms}ms came out as {bucketed}ms"
+        );
+    }
+}
+
+#[test]
+fn summary_has_the_request_timings() {
+    let metrics = Metrics::default();
+    metrics.record_response(Duration::from_millis(100), 1000);
+    metrics.record_response(Duration::from_millis(300), 3000);
+
+    let mut stats = Stats::default();
+    stats.set_timings(&metrics);
+
+    assert_eq!(stats.latency_p50_ms, Some(96));
+    assert_eq!(stats.latency_p95_ms, Some(288));
+    assert_eq!(stats.response_bytes_per_sec, Some(10000.0));
+}
@@ -0,0 +1,95 @@
+//! The typed fields on a match (game times and the enums over string fields) and the stats derived from them
+
+use std::time::Duration;
+
+use pred_ripper::{GameMode, GameTime, MatchEndReason, PredecessorMatch, Region};
+use serde_json::Value;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn game_times_round_trip_as_bare_seconds() {
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let kill = &matches[0].hero_kills[0];
+    let original: Value = serde_json::from_str(FIXTURE).unwrap();
+
+    assert_eq!(
+        serde_json::to_value(kill).unwrap()["gameTime"],
+        original[0]["heroKills"][0]["gameTime"]
+    );
+    assert_eq!(
+        serde_json::from_str::<GameTime>("754").unwrap(),
+        GameTime(754)
+    );
+    assert_eq!(serde_json::to_string(&GameTime(754)).unwrap(), "754");
+}
+
+#[test]
+fn game_times_convert_to_durations() {
+    assert_eq!(GameTime(90).as_seconds(), 90);
+    assert_eq!(GameTime(90).as_duration(), Duration::from_secs(90));
+    // The backend doesn't promise they're never negative
+    assert_eq!(GameTime(-5).as_duration(), Duration::ZERO);
+}
+
+#[test]
+fn known_values_become_variants_and_unknown_ones_are_kept() {
+    assert_eq!(
+        serde_json::from_str::<Region>("\"NAEAST\"").unwrap(),
+        Region::NaEast
+    );
+    assert_eq!(
+        serde_json::from_str::<MatchEndReason>("\"CORE_DESTROYED\"").unwrap(),
+        MatchEndReason::CoreDestroyed
+    );
+    assert_eq!(
+        serde_json::from_str::<GameMode>("\"ARAM\"").unwrap(),
+        GameMode::Other("ARAM".to_string())
+    );
+    // A match with values the backend added later still loads
+    let mut original: Value = serde_json::from_str(FIXTURE).unwrap();
+    original[0]["region"] = "MARS".into();
+    let matches: Vec<PredecessorMatch> = serde_json::from_value(original).unwrap();
+    assert_eq!(matches[0].region, Region::Other("MARS".to_string()));
+    assert_eq!(serde_json::to_value(&matches[0]).unwrap()["region"], "MARS");
+}
+
+#[test]
+fn known_values_are_saved_in_their_canonical_spelling() {
+    let region: Region = serde_json::from_str("\"naEast\"").unwrap();
+    assert_eq!(region, Region::NaEast);
+    assert_eq!(serde_json::to_string(&region).unwrap(), "\"NAEAST\"");
+    // Unknown values go back out exactly as they came in
+    let mode: GameMode = serde_json::from_str("\"aram\"").unwrap();
+    assert_eq!(serde_json::to_string(&mode).unwrap(), "\"aram\"");
+}
+
+#[test]
+fn filters_only_take_known_values() {
+    assert_eq!(Region::parse_known("europe"), Ok(Region::Europe));
+    assert!(Region::parse_known("NAEST").is_err());
+    assert!(GameMode::parse_known("ARAM").is_err());
+}
+
+#[test]
+fn derived_stats_are_per_minute_of_the_match() {
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let player = &matches[0].player_data[0];
+    // 5 kills, 1 death and 2 assists, 12000 gold and 100 minions over 30 minutes
+    let stats = player.derived_stats(1800);
+
+    assert_eq!(stats.kda, 7.0);
+    assert_eq!(stats.gold_per_min, 400.0);
+    assert!((stats.cs_per_min - 100.0 / 30.0).abs() < 1e-9);
+}
+
+#[test]
+fn no_deaths_is_a_kda_of_kills_plus_assists() {
+    let mut matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let player = &mut matches[0].player_data[0];
+    player.combat_data.deaths = 0;
+
+    assert_eq!(player.derived_stats(1800).kda, 7.0);
+    // Nothing to divide the per-minute stats by
+    assert_eq!(player.derived_stats(0).gold_per_min, 0.0);
+}
@@ -0,0 +1,59 @@
+//! Runs against a backend that refuses every connection
+
+use std::{
+    process::Command,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use pred_ripper::{Config, Ripper, RipperError};
+use tempfile::TempDir;
+
+const START_EPOCH: u64 = 1680307200;
+/// Nothing listens on port 1, so every request fails to connect straight away
+const DOWN_URL: &str = "http://127.0.0.1:1/get-matches-since";
+
+#[tokio::test]
+async fn total_outage_trips_the_breaker_and_fails_the_run() {
+    let output_dir = TempDir::new().unwrap();
+    let config = Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 4 * 3600),
+        pool_size: 4,
+        output_dir: output_dir.path().to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url: DOWN_URL.to_string(),
+        outage_threshold: 4,
+        outage_cooldown_secs: 1,
+        outage_max_pauses: 1,
+        ..Config::default()
+    };
+
+    let result = Ripper::new(config)
+        .run_until(Arc::new(AtomicBool::new(false)))
+        .await;
+
+    assert!(
+        matches!(result, Err(RipperError::BackendUnavailable { pauses: 1 })),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn total_outage_exits_non_zero() {
+    let output_dir = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pred-ripper"))
+        .args(["--base-url", DOWN_URL, "--no-zip", "--output-dir"])
+        .arg(output_dir.path())
+        .args(["--start-epoch", &START_EPOCH.to_string()])
+        .args(["--end-epoch", &(START_EPOCH + 4 * 3600).to_string()])
+        .args(["--pool-size", "4", "--outage-threshold", "4"])
+        .args(["--outage-cooldown-secs", "1", "--outage-max-pauses", "1"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Backend appears unavailable"));
+}
@@ -0,0 +1,340 @@
+use std::io::Write;
+
+use pred_ripper::{
+    archive::zip_matches,
+    output::{
+        for_each_saved_batch, read_matches, save_match_files, save_matches, write_atomically,
+    },
+    windows::parse_match_file_name,
+    Compression, OutputFormat, Partition, PredecessorMatch, RipperError,
+};
+use tempfile::TempDir;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn saving_no_matches_writes_nothing() {
+    let output_dir = TempDir::new().unwrap();
+
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+
+    assert!(saved.is_empty());
+    assert_eq!(std::fs::read_dir(output_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn batches_with_the_same_file_name_are_both_kept() {
+    let output_dir = TempDir::new().unwrap();
+    let first: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    // Same first and last end times, different matches
+    let mut second = first.clone();
+    for m in &mut second {
+        m.match_id.push_str("-other");
+    }
+    let save = |matches: &[PredecessorMatch]| {
+        save_matches(
+            output_dir.path(),
+            OutputFormat::Json,
+            false,
+            None,
+            false,
+            None,
+            matches,
+        )
+        .unwrap()
+        .remove(0)
+        .0
+        .file
+    };
+
+    let first_file = save(&first);
+    let second_file = save(&second);
+
+    assert_eq!(first_file, "1680309000-1680310800.json");
+    assert_eq!(second_file, "1680309000-1680310800.dup1.json");
+    assert_eq!(
+        parse_match_file_name(&second_file),
+        parse_match_file_name(&first_file)
+    );
+    let mut saved_ids = Vec::new();
+    for_each_saved_batch(output_dir.path(), |matches| {
+        saved_ids.extend(matches.iter().map(|m| m.match_id.clone()));
+        Ok(())
+    })
+    .unwrap();
+    saved_ids.sort();
+    assert_eq!(saved_ids, ["m1", "m1-other", "m2", "m2-other"]);
+}
+
+#[test]
+fn split_by_match_writes_a_file_per_match_named_after_its_id() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+
+    let saved = save_match_files(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        &matches,
+    )
+    .unwrap();
+
+    let files: Vec<&str> = saved.iter().map(|(entry, _)| entry.file.as_str()).collect();
+    assert_eq!(files, ["m1.json", "m2.json"]);
+    for (entry, saved_matches) in &saved {
+        let contents = std::fs::read(output_dir.path().join(&entry.file)).unwrap();
+        let on_disk: Vec<PredecessorMatch> = serde_json::from_slice(&contents).unwrap();
+        assert_eq!(on_disk.len(), 1);
+        assert_eq!(on_disk[0].match_id, saved_matches[0].match_id);
+    }
+    assert_eq!(std::fs::read_dir(output_dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn ids_a_path_cant_hold_are_escaped_without_clashing() {
+    let output_dir = TempDir::new().unwrap();
+    let fixture: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let ids = ["../m2/x", "a/b", "a%2Fb", "manifest"];
+    let matches: Vec<PredecessorMatch> = ids
+        .iter()
+        .map(|id| PredecessorMatch {
+            match_id: id.to_string(),
+            ..fixture[0].clone()
+        })
+        .collect();
+
+    let saved = save_match_files(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        &matches,
+    )
+    .unwrap();
+
+    let files: Vec<&str> = saved.iter().map(|(entry, _)| entry.file.as_str()).collect();
+    assert_eq!(
+        files,
+        [
+            "..%2Fm2%2Fx.json",
+            "a%2Fb.json",
+            "a%252Fb.json",
+            "%6Danifest.json"
+        ]
+    );
+    assert_eq!(
+        std::fs::read_dir(output_dir.path()).unwrap().count(),
+        ids.len()
+    );
+}
+
+#[test]
+fn ndjson_files_have_a_match_per_line_and_read_back_the_same() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Ndjson,
+        false,
+        None,
+        false,
+        None,
+        &matches,
+    )
+    .unwrap();
+
+    let path = output_dir.path().join(&saved[0].0.file);
+    assert_eq!(saved[0].0.file, "1680309000-1680310800.ndjson");
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<PredecessorMatch> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        serde_json::to_value(&lines).unwrap(),
+        serde_json::to_value(&matches).unwrap()
+    );
+    let read_back = read_matches(&path, OutputFormat::Ndjson).unwrap().unwrap();
+    assert_eq!(
+        serde_json::to_value(&read_back).unwrap(),
+        serde_json::to_value(&matches).unwrap()
+    );
+}
+
+#[test]
+fn a_write_that_fails_part_way_leaves_no_file_behind() {
+    let output_dir = TempDir::new().unwrap();
+    let path = output_dir.path().join("1680309000-1680310800.json");
+    let existing = output_dir.path().join("manifest.json");
+    std::fs::write(&existing, "old").unwrap();
+    let fail_part_way = |file: &mut std::io::BufWriter<std::fs::File>| {
+        file.write_all(b"[{\"matchId\":").unwrap();
+        Err(RipperError::WriterClosed)
+    };
+
+    assert!(write_atomically(&path, fail_part_way).is_err());
+    assert!(write_atomically(&existing, fail_part_way).is_err());
+
+    assert!(!path.exists());
+    // Whatever was there before is left as it was
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old");
+    let mut names: Vec<String> = std::fs::read_dir(output_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["manifest.json"]);
+}
+
+#[test]
+fn pretty_files_are_indented_and_the_default_is_one_line() {
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let saved_contents = |pretty| {
+        let output_dir = TempDir::new().unwrap();
+        let saved = save_matches(
+            output_dir.path(),
+            OutputFormat::Json,
+            pretty,
+            None,
+            false,
+            None,
+            &matches,
+        )
+        .unwrap();
+        std::fs::read_to_string(output_dir.path().join(&saved[0].0.file)).unwrap()
+    };
+
+    let pretty = saved_contents(true);
+    let minified = saved_contents(false);
+
+    assert!(pretty.starts_with("[\n  {\n    \""), "{}", pretty);
+    assert!(!minified.contains('\n'));
+    assert!(!minified.contains("  "));
+    let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+    let minified: serde_json::Value = serde_json::from_str(&minified).unwrap();
+    assert_eq!(pretty, minified);
+}
+
+#[test]
+fn date_partitions_go_in_a_folder_per_day() {
+    let output_dir = TempDir::new().unwrap();
+    let mut matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    for m in &mut matches {
+        m.end_time += chrono::Duration::days(4);
+    }
+
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        Some(Partition::Date),
+        false,
+        None,
+        &matches,
+    )
+    .unwrap();
+
+    // 2023-04-05 00:30:00 to 01:00:00 UTC
+    let file = "2023/04/05/1680654600-1680656400.json";
+    assert_eq!(saved[0].0.file, file);
+    assert!(output_dir.path().join(file).is_file());
+    // And the zip keeps the same relative path
+    let archives = TempDir::new().unwrap();
+    let zip_path = archives.path().join("matches.zip");
+    zip_matches(output_dir.path(), &zip_path, Compression::Deflate).unwrap();
+    let zip = zip::ZipArchive::new(std::fs::File::open(zip_path).unwrap()).unwrap();
+    assert_eq!(zip.file_names().collect::<Vec<_>>(), [file]);
+}
+
+#[test]
+fn batches_over_max_file_bytes_are_split_into_numbered_parts() {
+    let output_dir = TempDir::new().unwrap();
+    let fixture: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let matches: Vec<PredecessorMatch> = (0..4)
+        .map(|i| {
+            let mut m = fixture[0].clone();
+            m.match_id = format!("m{i}");
+            m.end_time += chrono::Duration::minutes(i);
+            m
+        })
+        .collect();
+    // Room for one match a file, but not two
+    let one_match = serde_json::to_vec(&matches[..1]).unwrap().len() as u64;
+
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        Some(one_match + 16),
+        &matches,
+    )
+    .unwrap();
+
+    let files: Vec<&str> = saved.iter().map(|(entry, _)| entry.file.as_str()).collect();
+    assert_eq!(
+        files,
+        [
+            "1680309000-1680309180.part0.json",
+            "1680309000-1680309180.part1.json",
+            "1680309000-1680309180.part2.json",
+            "1680309000-1680309180.part3.json",
+        ]
+    );
+    for (i, (entry, in_file)) in saved.iter().enumerate() {
+        assert_eq!(entry.match_count, 1);
+        assert_eq!(in_file[0].match_id, format!("m{i}"));
+        assert!(parse_match_file_name(&entry.file).is_some());
+    }
+    assert_eq!(std::fs::read_dir(output_dir.path()).unwrap().count(), 4);
+}
+
+#[test]
+fn gzipped_files_read_back_to_the_same_matches() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        true,
+        None,
+        &matches,
+    )
+    .unwrap();
+
+    let file = &saved[0].0.file;
+    assert_eq!(file, "1680309000-1680310800.json.gz");
+    let path = output_dir.path().join(file);
+    let (_, _, format) = parse_match_file_name(file).unwrap();
+    assert_eq!(format, OutputFormat::Json);
+    let mut decompressed = String::new();
+    std::io::Read::read_to_string(
+        &mut flate2::read::GzDecoder::new(std::fs::File::open(&path).unwrap()),
+        &mut decompressed,
+    )
+    .unwrap();
+    let expected = serde_json::to_value(&matches).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&decompressed).unwrap(),
+        expected
+    );
+    let read_back = read_matches(&path, format).unwrap().unwrap();
+    assert_eq!(serde_json::to_value(&read_back).unwrap(), expected);
+}
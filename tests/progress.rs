@@ -0,0 +1,23 @@
+//! The progress counts, which are kept up to date whether or not the bar is drawn
+
+use pred_ripper::progress::Progress;
+
+#[test]
+fn finished_windows_and_matches_add_up() {
+    let progress = Progress::new(3, false);
+    assert_eq!(
+        (progress.windows_finished(), progress.total_windows()),
+        (0, 3)
+    );
+
+    progress.add_matches(5);
+    progress.window_finished();
+    progress.add_matches(2);
+    progress.window_finished();
+
+    assert_eq!(progress.windows_finished(), 2);
+    assert_eq!(progress.matches(), 7);
+    progress.window_finished();
+    progress.finish();
+    assert_eq!(progress.windows_finished(), 3);
+}
@@ -0,0 +1,96 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use pred_ripper::{
+    checkpoint::Checkpoint,
+    quota::{RequestQuota, QUOTA_WINDOW_SECS},
+    Config, Ripper,
+};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn blocks_once_the_quota_is_used_up() {
+    let quota = RequestQuota::new(3, Vec::new());
+
+    assert!(quota.try_acquire(START_EPOCH));
+    assert!(quota.try_acquire(START_EPOCH + 60));
+    assert!(quota.try_acquire(START_EPOCH + 120));
+    assert!(!quota.try_acquire(START_EPOCH + 180));
+    assert_eq!(quota.used(START_EPOCH + 180), 3);
+    // The first minute's request is the first to roll out, a whole window after the end of its minute
+    assert_eq!(
+        quota.available_at(START_EPOCH + 180),
+        Some(START_EPOCH + 60 + QUOTA_WINDOW_SECS)
+    );
+}
+
+#[test]
+fn frees_up_as_the_window_rolls() {
+    let quota = RequestQuota::new(2, Vec::new());
+    assert!(quota.try_acquire(START_EPOCH));
+    assert!(quota.try_acquire(START_EPOCH + 3600));
+
+    let rolled = START_EPOCH + 60 + QUOTA_WINDOW_SECS;
+    assert!(!quota.try_acquire(rolled - 1));
+    assert!(quota.try_acquire(rolled));
+    assert!(!quota.try_acquire(rolled));
+    assert_eq!(quota.used(rolled), 2);
+}
+
+#[test]
+fn usage_carries_over_to_a_new_tracker() {
+    let quota = RequestQuota::new(2, Vec::new());
+    assert!(quota.try_acquire(START_EPOCH));
+    assert!(quota.try_acquire(START_EPOCH + 1));
+
+    let restarted = RequestQuota::new(2, quota.usage());
+
+    assert!(!restarted.try_acquire(START_EPOCH + 2));
+}
+
+#[tokio::test]
+async fn a_run_stops_at_the_quota_and_checkpoints_its_usage() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+    let config = Config {
+        start_epoch: START_EPOCH,
+        // Long enough that the window still needs another request after the fixture's matches
+        end_epoch: Some(START_EPOCH + 7200),
+        window_size: 7200,
+        output_dir: output_dir.path().to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        daily_quota: Some(1),
+        ..Config::default()
+    };
+
+    let stats = Ripper::new(config)
+        .run_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap();
+
+    assert_eq!(stats.requests, 1);
+    let checkpoint = Checkpoint::load(output_dir.path()).unwrap();
+    assert_eq!(checkpoint.windows.len(), 1);
+    assert_eq!(
+        checkpoint
+            .quota_usage
+            .iter()
+            .map(|b| b.requests)
+            .sum::<u64>(),
+        1
+    );
+}
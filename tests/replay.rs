@@ -0,0 +1,98 @@
+//! `replay`: the responses a --keep-raw run saved, run back through parsing and saving offline
+
+use std::{
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use pred_ripper::{fetch::RAW_DIR, Config, Ripper, RipperError};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn config(output_dir: &Path, base_url: String) -> Config {
+    Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 3600),
+        output_dir: output_dir.to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url,
+        ..Config::default()
+    }
+}
+
+/// Every match file under `output_dir` with its contents, sorted by name
+fn match_files(output_dir: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut files: Vec<_> = pred_ripper::windows::match_files(output_dir)
+        .unwrap()
+        .into_iter()
+        .map(|(path, _)| {
+            let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+            (name, std::fs::read(path).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[tokio::test]
+async fn replaying_saved_responses_gives_the_same_files_as_the_live_run() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let live_dir = TempDir::new().unwrap();
+    let live = Config {
+        keep_raw: true,
+        ..config(
+            live_dir.path(),
+            format!("{}/get-matches-since", server.uri()),
+        )
+    };
+    Ripper::new(live)
+        .run_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap();
+
+    let replay_dir = TempDir::new().unwrap();
+    // Nothing is listening here, so anything fetched would fail
+    let replay = config(replay_dir.path(), "http://127.0.0.1:1".into());
+    let stats = Ripper::new(replay)
+        .replay(&live_dir.path().join(RAW_DIR))
+        .unwrap();
+
+    assert_eq!(stats.total_matches, 2);
+    let replayed = match_files(replay_dir.path());
+    assert_eq!(replayed.len(), 1);
+    assert_eq!(replayed, match_files(live_dir.path()));
+}
+
+#[test]
+fn replaying_into_the_folder_the_responses_are_in_is_an_error() {
+    let output_dir = TempDir::new().unwrap();
+    let raw_dir = output_dir.path().join(RAW_DIR);
+    std::fs::create_dir(&raw_dir).unwrap();
+
+    let ripper = Ripper::new(config(output_dir.path(), "http://127.0.0.1:1".into()));
+
+    assert!(matches!(
+        ripper.replay(&raw_dir),
+        Err(RipperError::Replay(_))
+    ));
+    assert!(raw_dir.exists());
+}
@@ -0,0 +1,375 @@
+//! How single requests to get-matches-since behave: waiting out rate limits, timeouts and what's sent with them
+
+use std::{
+    num::NonZeroU32,
+    time::{Duration, Instant},
+};
+
+use pred_ripper::{
+    backoff::Backoff,
+    build_client, build_client_with,
+    fetch::{
+        get_matches_since, parse_base_url, parse_header, parse_proxy, rate_limiter, RequestOptions,
+    },
+    ClientOptions, RipperError, SchemaCheck, Secret,
+};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{header, method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn base_url(server: &MockServer) -> String {
+    format!("{}/get-matches-since", server.uri())
+}
+
+#[tokio::test]
+async fn a_429_is_waited_out_for_as_long_as_retry_after_says() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "2"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .with_priority(2)
+        .mount(&server)
+        .await;
+    let client = build_client(Duration::from_secs(30)).unwrap();
+
+    let started = Instant::now();
+    let matches = get_matches_since(
+        &client,
+        &base_url(&server),
+        START_EPOCH,
+        &RequestOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(matches.len(), 2);
+    assert!(started.elapsed() >= Duration::from_secs(2));
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn a_server_that_never_answers_times_out_and_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(30)))
+        .mount(&server)
+        .await;
+    let client = build_client(Duration::from_millis(200)).unwrap();
+    let options = RequestOptions {
+        max_retries: 1,
+        backoff: Backoff {
+            base_delay: Duration::from_millis(10),
+            ..Backoff::default()
+        },
+        ..RequestOptions::default()
+    };
+
+    let started = Instant::now();
+    let err = get_matches_since(&client, &base_url(&server), START_EPOCH, &options)
+        .await
+        .unwrap_err();
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(
+        matches!(
+            &err,
+            RipperError::RetriesExhausted { attempts: 2, source, .. }
+                if matches!(source.as_ref(), RipperError::Http(err) if err.is_timeout())
+        ),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn requests_go_to_the_base_url_given() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/mirror/api/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = build_client(Duration::from_secs(30)).unwrap();
+    // The trailing slash is dropped, so it isn't doubled up before the epoch
+    let base_url = parse_base_url(&format!("{}/mirror/api/", server.uri())).unwrap();
+
+    let matches = get_matches_since(&client, &base_url, START_EPOCH, &RequestOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(matches.len(), 2);
+}
+
+#[test]
+fn base_urls_that_arent_http_are_rejected() {
+    for url in [
+        "not a url",
+        "/get-matches-since",
+        "ftp://example.com/get-matches-since",
+    ] {
+        assert!(parse_base_url(url).is_err(), "{}", url);
+    }
+    assert_eq!(
+        parse_base_url("https://example.com/api").unwrap(),
+        "https://example.com/api"
+    );
+}
+
+#[tokio::test]
+async fn requests_are_held_to_max_rps() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .mount(&server)
+        .await;
+    let client = build_client(Duration::from_secs(30)).unwrap();
+    let limiter = rate_limiter(NonZeroU32::new(2).unwrap());
+    let options = RequestOptions {
+        limiter: Some(&limiter),
+        ..RequestOptions::default()
+    };
+
+    let base_url = base_url(&server);
+
+    // All at once, as if from five workers sharing the limiter
+    let started = Instant::now();
+    let results = futures::future::join_all(
+        (0..5).map(|i| get_matches_since(&client, &base_url, START_EPOCH + i, &options)),
+    )
+    .await;
+
+    assert!(results.iter().all(Result::is_ok));
+    // A full second's worth go straight away, then one every half second: the last goes out at 1.5s
+    assert!(started.elapsed() >= Duration::from_millis(1400));
+    assert_eq!(server.received_requests().await.unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn the_user_agent_and_extra_headers_are_sent() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("user-agent", "stats-site/1.0 (admin@example.com)"))
+        .and(header("x-trace-id", "abc123"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = build_client_with(&ClientOptions {
+        user_agent: Some("stats-site/1.0 (admin@example.com)".to_string()),
+        headers: [parse_header("X-Trace-Id = abc123").unwrap()].into(),
+        ..ClientOptions::default()
+    })
+    .unwrap();
+
+    get_matches_since(
+        &client,
+        &base_url(&server),
+        START_EPOCH,
+        &RequestOptions::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[test]
+fn malformed_headers_are_rejected() {
+    assert!(parse_header("no-equals-sign").is_err());
+    assert!(parse_header("bad name=value").is_err());
+    assert!(parse_header("X-Ok=line\nbreak").is_err());
+    let err = build_client_with(&ClientOptions {
+        headers: [("bad name".to_string(), "value".to_string())].into(),
+        ..ClientOptions::default()
+    })
+    .unwrap_err();
+    assert!(matches!(err, RipperError::InvalidHeader(name) if name == "bad name"));
+}
+
+#[tokio::test]
+async fn the_api_token_is_sent_as_a_bearer_token_only_when_set() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .mount(&server)
+        .await;
+    let with_token = build_client_with(&ClientOptions {
+        api_token: Some(Secret::new("s3cret")),
+        ..ClientOptions::default()
+    })
+    .unwrap();
+    let without = build_client(Duration::from_secs(30)).unwrap();
+
+    for client in [&with_token, &without] {
+        get_matches_since(
+            client,
+            &base_url(&server),
+            START_EPOCH,
+            &RequestOptions::default(),
+        )
+        .await
+        .unwrap();
+    }
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(
+        requests[0].headers.get("authorization").unwrap(),
+        "Bearer s3cret"
+    );
+    assert!(requests[1].headers.get("authorization").is_none());
+    assert_eq!(format!("{:?}", Secret::new("s3cret")), "Secret(***)");
+}
+
+#[tokio::test]
+async fn requests_go_through_the_proxy() {
+    // The mock server stands in for the proxy, and sees the request for the real backend come through it
+    let proxy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .expect(1)
+        .mount(&proxy)
+        .await;
+    let client = build_client_with(&ClientOptions {
+        proxy: Some(parse_proxy(&proxy.uri()).unwrap()),
+        ..ClientOptions::default()
+    })
+    .unwrap();
+
+    let matches = get_matches_since(
+        &client,
+        "http://backend.invalid/get-matches-since",
+        START_EPOCH,
+        &RequestOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(matches.len(), 2);
+}
+
+#[test]
+fn proxies_are_checked_up_front() {
+    for proxy in [
+        "socks5://127.0.0.1:1080",
+        "socks5h://127.0.0.1:1080",
+        "https://127.0.0.1:3128",
+    ] {
+        assert_eq!(parse_proxy(proxy).as_deref(), Ok(proxy));
+        build_client_with(&ClientOptions {
+            proxy: Some(proxy.to_string()),
+            ..ClientOptions::default()
+        })
+        .unwrap();
+    }
+    assert!(parse_proxy("not a url")
+        .unwrap_err()
+        .contains("invalid proxy URL"));
+    assert!(parse_proxy("ftp://proxy:21")
+        .unwrap_err()
+        .contains("must be http"));
+    let err = build_client_with(&ClientOptions {
+        proxy: Some("not a url".to_string()),
+        ..ClientOptions::default()
+    })
+    .unwrap_err();
+    assert!(matches!(err, RipperError::InvalidProxy { url, .. } if url == "not a url"));
+}
+
+#[tokio::test]
+async fn strict_schema_reports_fields_the_models_dont_have() {
+    let server = MockServer::start().await;
+    let mut body: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+    body[0]["newField"] = 1.into();
+    body[1]["playerData"][0]["combatData"]["pentaKills"] = 0.into();
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .mount(&server)
+        .await;
+    let client = build_client(Duration::from_secs(30)).unwrap();
+    let fetch = |schema_check| {
+        let (client, base_url) = (&client, base_url(&server));
+        async move {
+            let options = RequestOptions {
+                schema_check,
+                ..RequestOptions::default()
+            };
+            get_matches_since(client, &base_url, START_EPOCH, &options).await
+        }
+    };
+
+    // Lenient by default, and --strict-schema warn only logs them
+    assert_eq!(fetch(None).await.unwrap().len(), 2);
+    assert_eq!(fetch(Some(SchemaCheck::Warn)).await.unwrap().len(), 2);
+    let err = fetch(Some(SchemaCheck::Fail)).await.unwrap_err();
+    // Given up on straight away, asking again would only get the same fields back
+    assert!(
+        matches!(
+            &err,
+            RipperError::RetriesExhausted { attempts: 1, source, .. }
+                if matches!(
+                    source.as_ref(),
+                    RipperError::UnknownFields { epoch: START_EPOCH, fields }
+                        if fields == &["[].newField", "[].playerData[].combatData.pentaKills"]
+                )
+        ),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn raw_responses_are_saved_byte_for_byte_even_when_they_dont_parse() {
+    let server = MockServer::start().await;
+    // Spacing that re-serializing would never give back
+    let body = format!(
+        "  {}\n\n",
+        FIXTURE.replace("\"matchId\": \"m1\"", "\"matchId\" :\"m1\"")
+    );
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body.clone()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH + 1)))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[{\"matchId\": "))
+        .mount(&server)
+        .await;
+    let raw_dir = TempDir::new().unwrap();
+    let client = build_client(Duration::from_secs(30)).unwrap();
+    let options = RequestOptions {
+        raw_dir: Some(raw_dir.path()),
+        max_retries: 0,
+        ..RequestOptions::default()
+    };
+
+    let matches = get_matches_since(&client, &base_url(&server), START_EPOCH, &options)
+        .await
+        .unwrap();
+    let truncated = get_matches_since(&client, &base_url(&server), START_EPOCH + 1, &options).await;
+
+    assert_eq!(matches.len(), 2);
+    assert_eq!(
+        std::fs::read(raw_dir.path().join(format!("{}.json", START_EPOCH))).unwrap(),
+        body.as_bytes()
+    );
+    assert!(truncated.is_err());
+    assert_eq!(
+        std::fs::read(raw_dir.path().join(format!("{}.json", START_EPOCH + 1))).unwrap(),
+        b"[{\"matchId\": "
+    );
+}
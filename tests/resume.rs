@@ -0,0 +1,276 @@
+//! --resume picking up output saved by an earlier run, going by the work windows the manifest recorded
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use pred_ripper::{
+    checkpoint::{Checkpoint, WindowCheckpoint},
+    manifest::Manifest,
+    Config, Ripper, Stats, WorkWindow,
+};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, Request, Respond, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC. The fixture's matches end half an hour and an hour after this
+const START_EPOCH: u64 = 1680307200;
+const HOUR: u64 = 3600;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+/// Two one hour windows. Only the first has matches, and it keeps the one ending inside it. The other ends right on
+/// its end, so it's the second window's, which never sees it
+fn config(server: &MockServer, output_dir: &Path) -> Config {
+    Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 2 * HOUR),
+        output_dir: output_dir.to_path_buf(),
+        no_zip: true,
+        progress: false,
+        pool_size: 1,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        ..Config::default()
+    }
+}
+
+async fn run(config: Config) -> Stats {
+    Ripper::new(config)
+        .run_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap()
+}
+
+async fn resume(server: &MockServer, output_dir: &Path) -> Stats {
+    run(Config {
+        resume: true,
+        ..config(server, output_dir)
+    })
+    .await
+}
+
+async fn mock_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn requested_epochs(server: &MockServer) -> Vec<u64> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            request
+                .url
+                .path()
+                .rsplit('/')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap()
+        })
+        .collect()
+}
+
+fn files(output_dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = Manifest::load(output_dir)
+        .unwrap()
+        .files
+        .into_iter()
+        .map(|entry| entry.file)
+        .collect();
+    files.sort();
+    files
+}
+
+#[tokio::test]
+async fn windows_collected_in_full_are_skipped() {
+    let server = mock_server().await;
+    let output_dir = TempDir::new().unwrap();
+    run(config(&server, output_dir.path())).await;
+    // The file is named after the matches in it, which don't cover the window it came from
+    assert_eq!(files(output_dir.path()), ["1680309000-1680309000.json"]);
+    assert_eq!(Manifest::load(output_dir.path()).unwrap().windows.len(), 2);
+
+    let server = mock_server().await;
+    let stats = resume(&server, output_dir.path()).await;
+
+    // Only the second window is asked for again. It found nothing, so matches may have arrived since
+    let requested = requested_epochs(&server).await;
+    assert!(!requested.is_empty());
+    assert!(requested.iter().all(|&epoch| epoch >= START_EPOCH + HOUR));
+    assert_eq!(stats.total_matches, 0);
+    assert_eq!(files(output_dir.path()), ["1680309000-1680309000.json"]);
+}
+
+#[tokio::test]
+async fn windows_whose_files_no_longer_parse_are_retried() {
+    let server = mock_server().await;
+    let output_dir = TempDir::new().unwrap();
+    run(config(&server, output_dir.path())).await;
+    let file = output_dir.path().join("1680309000-1680309000.json");
+    std::fs::write(&file, &FIXTURE[..FIXTURE.len() / 2]).unwrap();
+
+    let server = mock_server().await;
+    let stats = resume(&server, output_dir.path()).await;
+
+    assert!(requested_epochs(&server).await.contains(&START_EPOCH));
+    assert_eq!(stats.total_matches, 1);
+    assert_eq!(files(output_dir.path()), ["1680309000-1680309000.json"]);
+}
+
+#[tokio::test]
+async fn windows_cut_short_are_picked_back_up() {
+    let server = mock_server().await;
+    let output_dir = TempDir::new().unwrap();
+    // --limit stops the run as soon as the first window has its matches, before it's marked done
+    run(Config {
+        limit: Some(1),
+        ..config(&server, output_dir.path())
+    })
+    .await;
+    let manifest = Manifest::load(output_dir.path()).unwrap();
+    assert_eq!(manifest.files.len(), 1);
+    assert_eq!(
+        manifest.files[0].window,
+        Some(WorkWindow {
+            start_epoch: START_EPOCH,
+            end_epoch: START_EPOCH + HOUR,
+        })
+    );
+    assert!(manifest.windows.is_empty());
+
+    let server = mock_server().await;
+    resume(&server, output_dir.path()).await;
+
+    // From the checkpoint, not the start of the window, so nothing is saved twice
+    let requested = requested_epochs(&server).await;
+    assert!(requested.contains(&(START_EPOCH + HOUR)));
+    assert!(!requested.contains(&START_EPOCH));
+    assert_eq!(files(output_dir.path()), ["1680309000-1680309000.json"]);
+    assert_eq!(Manifest::load(output_dir.path()).unwrap().windows.len(), 2);
+}
+
+/// Serves the fixture, and flips the shutdown flag as it does, as if SIGTERM came in during the request
+struct ShutdownDuringRequest(Arc<AtomicBool>);
+
+impl Respond for ShutdownDuringRequest {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        self.0.store(true, Ordering::Relaxed);
+        ResponseTemplate::new(200).set_body_string(FIXTURE)
+    }
+}
+
+#[tokio::test]
+async fn a_shutdown_mid_window_checkpoints_the_epoch_it_got_to() {
+    let server = MockServer::start().await;
+    let shutdown = Arc::new(AtomicBool::new(false));
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ShutdownDuringRequest(shutdown.clone()))
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+
+    Ripper::new(Config {
+        window_size: 2 * HOUR,
+        ..config(&server, output_dir.path())
+    })
+    .run_until(shutdown)
+    .await
+    .unwrap();
+
+    // The batch is saved and the window stops right after it, at its last match's end
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    assert_eq!(
+        Checkpoint::load(output_dir.path()).unwrap().windows,
+        [WindowCheckpoint {
+            start_epoch: START_EPOCH,
+            end_epoch: START_EPOCH + 2 * HOUR,
+            current_epoch: 1680310800,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn split_match_files_count_toward_their_window() {
+    let server = mock_server().await;
+    let output_dir = TempDir::new().unwrap();
+    let split = |config: Config| Config {
+        split_by_match: true,
+        ..config
+    };
+    run(split(config(&server, output_dir.path()))).await;
+    assert_eq!(files(output_dir.path()), ["m1.json"]);
+
+    let server = mock_server().await;
+    let stats = run(split(Config {
+        resume: true,
+        ..config(&server, output_dir.path())
+    }))
+    .await;
+
+    assert!(!requested_epochs(&server).await.contains(&START_EPOCH));
+    assert_eq!(stats.total_matches, 0);
+}
+
+#[tokio::test]
+async fn a_failed_window_fetched_again_saves_nothing_twice() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    // The window's second request fails for good, so it's never marked done or checkpointed
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(500))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+    let one_window = |config: Config| Config {
+        end_epoch: Some(START_EPOCH + 2 * HOUR),
+        window_size: 2 * HOUR,
+        max_retries: 0,
+        ..config
+    };
+    let stats = run(one_window(config(&server, output_dir.path()))).await;
+    assert_eq!(stats.failed_windows.len(), 1);
+
+    let server = mock_server().await;
+    let stats = run(one_window(Config {
+        resume: true,
+        ..config(&server, output_dir.path())
+    }))
+    .await;
+
+    assert!(requested_epochs(&server).await.contains(&START_EPOCH));
+    assert_eq!(stats.total_matches, 0);
+    assert_eq!(files(output_dir.path()), ["1680309000-1680310800.json"]);
+    let mut ids = Vec::new();
+    pred_ripper::output::for_each_saved_batch(output_dir.path(), |matches| {
+        ids.extend(matches.iter().map(|m| m.match_id.clone()));
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(ids, ["m1", "m2"]);
+}
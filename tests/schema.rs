@@ -0,0 +1,28 @@
+//! `schema`: the JSON Schema of a saved match. Only built with the schema feature
+
+#![cfg(feature = "schema")]
+
+use pred_ripper::schema::match_schema;
+use serde_json::Value;
+
+#[test]
+fn schema_has_the_match_fields_under_their_json_names() {
+    let schema = serde_json::to_value(match_schema()).unwrap();
+
+    let properties = schema["properties"].as_object().unwrap();
+    for field in [
+        "matchId",
+        "playerData",
+        "gameDuration",
+        "startTime",
+        "heroKills",
+    ] {
+        assert!(properties.contains_key(field), "no {field} in the schema");
+    }
+    // Nested types are described too, not just referenced
+    assert!(schema["definitions"]["PlayerData"]["properties"]["heroName"].is_object());
+    assert_eq!(
+        schema["definitions"]["Region"]["type"],
+        Value::from("string")
+    );
+}
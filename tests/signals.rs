@@ -0,0 +1,70 @@
+//! Shutting down gracefully on SIGTERM, as under systemd or Docker
+#![cfg(unix)]
+
+use std::{
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use pred_ripper::checkpoint::Checkpoint;
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC. The fixture's matches end half an hour and an hour after this
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[tokio::test]
+async fn sigterm_lets_the_open_request_finish_and_checkpoints() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    // Slow enough that the signal arrives while the window's second request is open
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("[]")
+                .set_delay(Duration::from_secs(1)),
+        )
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+
+    let child = Command::new(env!("CARGO_BIN_EXE_pred-ripper"))
+        .args(["--start-epoch", &START_EPOCH.to_string()])
+        .args(["--end-epoch", &(START_EPOCH + 7200).to_string()])
+        .args(["--window-size", "7200", "--no-zip", "--no-progress"])
+        .args(["--base-url", &format!("{}/get-matches-since", server.uri())])
+        .arg("--output-dir")
+        .arg(output_dir.path())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    while server.received_requests().await.unwrap().len() < 2 {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let killed = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let output = tokio::task::spawn_blocking(|| child.wait_with_output())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("SIGTERM received"), "{}", stderr);
+    let checkpoint = Checkpoint::load(output_dir.path()).unwrap();
+    assert_eq!(checkpoint.windows.len(), 1);
+    assert!(checkpoint.windows[0].current_epoch > START_EPOCH);
+}
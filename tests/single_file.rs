@@ -0,0 +1,53 @@
+//! --single-file: every match appended as a line of one shared NDJSON file
+
+use std::{collections::HashSet, thread};
+
+use pred_ripper::{output::SingleFile, PredecessorMatch};
+use tempfile::TempDir;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+/// `count` batches of the fixture's matches, with ids that say which writer and batch they came from
+fn batches(writer: &str, count: usize) -> Vec<Vec<PredecessorMatch>> {
+    let template: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    (0..count)
+        .map(|batch| {
+            let mut matches = template.clone();
+            for (i, m) in matches.iter_mut().enumerate() {
+                m.match_id = format!("{writer}-{batch}-{i}");
+            }
+            matches
+        })
+        .collect()
+}
+
+#[test]
+fn concurrent_writers_never_interleave_their_lines() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("all.ndjson");
+    let single_file = SingleFile::create(&path).unwrap();
+
+    thread::scope(|scope| {
+        for writer in ["a", "b"] {
+            let single_file = &single_file;
+            scope.spawn(move || {
+                for batch in batches(writer, 200) {
+                    single_file.append(&batch).unwrap();
+                }
+            });
+        }
+    });
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.ends_with('\n'));
+    let ids: Vec<String> = contents
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<PredecessorMatch>(line)
+                .unwrap()
+                .match_id
+        })
+        .collect();
+    assert_eq!(ids.len(), 2 * 200 * 2);
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+}
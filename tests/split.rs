@@ -0,0 +1,121 @@
+//! --window-splits against a mock backend that pages through matches like the real one
+
+use std::{
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use chrono::DateTime;
+use pred_ripper::{output::for_each_saved_batch, Config, Ripper};
+use serde_json::Value;
+use tempfile::TempDir;
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// 2023-04-01 00:00:00 UTC
+const START_EPOCH: u64 = 1680307200;
+const PAGE_SIZE: usize = 4;
+
+/// Answers get-matches-since/{epoch} with the next PAGE_SIZE matches that ended at or after epoch
+struct Backend(Vec<Value>);
+
+impl Backend {
+    /// Matches every 100 seconds through the hour, plus a pair on each boundary of a 4-way split
+    fn new() -> Self {
+        let fixture: Vec<Value> =
+            serde_json::from_str(include_str!("fixtures/matches.json")).unwrap();
+        let mut end_offsets: Vec<u64> = (1..36).map(|i| i * 100).collect();
+        end_offsets.extend([900, 1800, 1800, 2700]);
+        end_offsets.sort_unstable();
+
+        let matches = end_offsets
+            .iter()
+            .enumerate()
+            .map(|(i, offset)| {
+                let mut m = fixture[0].clone();
+                let end_time = DateTime::from_timestamp((START_EPOCH + offset) as i64, 0).unwrap();
+                m["matchId"] = format!("m{}", i).into();
+                m["endTime"] = end_time.format("%Y-%m-%d %H:%M:%S").to_string().into();
+                m
+            })
+            .collect();
+        Backend(matches)
+    }
+
+    fn end_epoch(m: &Value) -> u64 {
+        pred_ripper::time::parse_epoch(m["endTime"].as_str().unwrap()).unwrap()
+    }
+}
+
+impl Respond for Backend {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let epoch: u64 = request
+            .url
+            .path()
+            .rsplit('/')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let page: Vec<&Value> = self
+            .0
+            .iter()
+            .filter(|m| Self::end_epoch(m) >= epoch)
+            .take(PAGE_SIZE)
+            .collect();
+        ResponseTemplate::new(200).set_body_json(page)
+    }
+}
+
+async fn collect(server: &MockServer, window_splits: u32) -> Vec<String> {
+    let output_dir = TempDir::new().unwrap();
+    let config = Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 3600),
+        output_dir: output_dir.path().to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        empty_step: 60,
+        window_splits,
+        ..Config::default()
+    };
+    Ripper::new(config)
+        .run_until(Arc::new(AtomicBool::new(false)))
+        .await
+        .unwrap();
+    saved_match_ids(output_dir.path())
+}
+
+/// Every saved match id, repeats included, sorted
+fn saved_match_ids(output_dir: &Path) -> Vec<String> {
+    let mut ids = Vec::new();
+    for_each_saved_batch(output_dir, |batch| {
+        ids.extend(batch.iter().map(|m| m.match_id.clone()));
+        Ok(())
+    })
+    .unwrap();
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+async fn split_windows_save_the_same_matches_as_whole_ones() {
+    let server = MockServer::start().await;
+    let backend = Backend::new();
+    let mut all_ids: Vec<String> = backend
+        .0
+        .iter()
+        .map(|m| m["matchId"].as_str().unwrap().to_string())
+        .collect();
+    all_ids.sort();
+    Mock::given(method("GET"))
+        .respond_with(backend)
+        .mount(&server)
+        .await;
+
+    let whole = collect(&server, 1).await;
+    let split = collect(&server, 4).await;
+
+    assert_eq!(whole, all_ids);
+    assert_eq!(split, whole);
+}
@@ -0,0 +1,65 @@
+//! --sqlite: the schema and inserts, against an in-memory database
+#![cfg(feature = "sqlite")]
+
+use pred_ripper::{sqlite::SqliteExport, PredecessorMatch};
+use rusqlite::Connection;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn count(export: &SqliteExport, table: &str) -> i64 {
+    export
+        .connection()
+        .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+#[test]
+fn matches_and_their_players_are_inserted_once_however_often_they_are_saved() {
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let mut export = SqliteExport::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+    export.insert_matches(&matches).unwrap();
+    export.insert_matches(&matches).unwrap();
+
+    assert_eq!(count(&export, "matches"), 2);
+    let players: usize = matches.iter().map(|m| m.player_data.len()).sum();
+    assert_eq!(count(&export, "player_data"), players as i64);
+    let (region, end_time): (String, String) = export
+        .connection()
+        .query_row(
+            "SELECT region, end_time FROM matches WHERE match_id = 'm1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(region, matches[0].region.as_str());
+    assert_eq!(end_time, "2023-04-01 00:30:00");
+}
+
+#[test]
+fn the_schema_is_created_on_an_empty_database() {
+    let export = SqliteExport::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+    let mut statement = export
+        .connection()
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+        .unwrap();
+    let tables: Vec<String> = statement
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        tables,
+        [
+            "hero_kills",
+            "matches",
+            "objective_kills",
+            "player_data",
+            "structure_destructions"
+        ]
+    );
+    assert_eq!(count(&export, "matches"), 0);
+}
@@ -0,0 +1,124 @@
+//! Totals for the matches already in the output folder, as printed by the `stats` subcommand
+
+use std::process::Command;
+
+use pred_ripper::{
+    manifest::Manifest,
+    output::{save_match_files, save_matches},
+    OutputDirStats, OutputFormat, PredecessorMatch,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+#[test]
+fn stats_total_the_matches_in_the_output_folder() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        None,
+        &matches,
+    )
+    .unwrap();
+
+    let stats = OutputDirStats::from_output_dir(output_dir.path()).unwrap();
+    assert_eq!(stats.total_matches, 2);
+    assert_eq!(stats.bytes_written, saved[0].0.bytes);
+    assert_eq!(
+        stats.matches_per_region.values().sum::<usize>(),
+        matches.len()
+    );
+}
+
+#[test]
+fn stats_total_split_match_files_too() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    let saved = save_match_files(
+        output_dir.path(),
+        OutputFormat::Json,
+        true,
+        None,
+        true,
+        &matches,
+    )
+    .unwrap();
+    // Their names don't say they're match files, the manifest does
+    let mut manifest = Manifest::default();
+    for (entry, _) in &saved {
+        manifest.record(entry.clone());
+    }
+    manifest.save(output_dir.path()).unwrap();
+
+    let stats = OutputDirStats::from_output_dir(output_dir.path()).unwrap();
+    assert_eq!(stats.total_matches, 2);
+    assert_eq!(
+        stats.bytes_written,
+        saved.iter().map(|(entry, _)| entry.bytes).sum::<u64>()
+    );
+}
+
+#[test]
+fn the_stats_subcommand_prints_the_totals_and_takes_flags_after_it() {
+    let output_dir = TempDir::new().unwrap();
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        None,
+        false,
+        None,
+        &matches,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pred-ripper"))
+        .arg("stats")
+        .arg("--output-dir")
+        .arg(output_dir.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let printed: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        printed,
+        serde_json::to_value(OutputDirStats::from_output_dir(output_dir.path()).unwrap()).unwrap()
+    );
+    // Only what's on disk, nothing that belongs to a run
+    assert!(printed.get("requests").is_none());
+    // Nothing was fetched or archived
+    assert_eq!(std::fs::read_dir(output_dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn fetch_mode_flags_are_an_error_with_the_stats_subcommand() {
+    let output_dir = TempDir::new().unwrap();
+    let stats = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_pred-ripper"))
+            .args(args)
+            .arg("--output-dir")
+            .arg(output_dir.path())
+            .output()
+            .unwrap()
+    };
+
+    for (args, flag) in [
+        (&["stats", "--report", "heroes"][..], "--report"),
+        (&["--report", "heroes", "stats"], "--report"),
+        (&["--resume", "stats"], "--resume"),
+    ] {
+        let output = stats(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(flag), "{}", stderr);
+        assert!(output.stdout.is_empty());
+    }
+}
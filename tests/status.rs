@@ -0,0 +1,73 @@
+#![cfg(unix)]
+//! --status-socket, read by a consumer connected while the run is going
+
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use pred_ripper::{status::WindowEvent, Config, Ripper};
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::UnixStream,
+};
+use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+/// 2023-04-01 00:00:00 UTC
+const START_EPOCH: u64 = 1680307200;
+
+#[tokio::test]
+async fn finished_windows_are_sent_on_the_socket() {
+    let server = MockServer::start().await;
+    // Slow enough that the consumer is connected before the first window finishes
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("[]")
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+    let socket = output_dir.path().join("status.sock");
+    let config = Config {
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 2 * 3600),
+        pool_size: 1,
+        output_dir: output_dir.path().join("matches"),
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        status_socket: Some(socket.clone()),
+        ..Config::default()
+    };
+    let consumer = async {
+        let stream = loop {
+            match UnixStream::connect(&socket).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let line = BufReader::new(stream).lines().next_line().await.unwrap();
+        serde_json::from_str::<WindowEvent>(&line.unwrap()).unwrap()
+    };
+
+    let ripper = Ripper::new(config);
+    let (result, first) =
+        tokio::join!(ripper.run_until(Arc::new(AtomicBool::new(false))), consumer);
+
+    result.unwrap();
+    assert_eq!(
+        first,
+        WindowEvent {
+            start_epoch: START_EPOCH,
+            end_epoch: START_EPOCH + 3600,
+            match_count: 0,
+            windows_finished: 1,
+            windows_total: 2,
+            total_matches: 0,
+        }
+    );
+    assert!(!socket.exists());
+}
@@ -0,0 +1,102 @@
+//! MatchStream against a mock backend that pages through matches like the real one
+
+use std::time::Duration;
+
+use chrono::DateTime;
+use pred_ripper::{build_client, MatchStream, RipperError};
+use serde_json::Value;
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// 2023-04-01 00:00:00 UTC
+const START_EPOCH: u64 = 1680307200;
+const PAGE_SIZE: usize = 4;
+
+/// Answers get-matches-since/{epoch} with the next PAGE_SIZE matches that ended at or after epoch
+struct Backend(Vec<Value>);
+
+impl Backend {
+    /// `m0`, `m1`, ... ending a minute apart from START_EPOCH, except `m7` ends on the same second as `m6`, so
+    /// a page ends part way through a second
+    fn new(count: u64) -> Self {
+        let fixture: Vec<Value> =
+            serde_json::from_str(include_str!("fixtures/matches.json")).unwrap();
+        let matches = (0..count)
+            .map(|i| {
+                let mut m = fixture[0].clone();
+                let offset = if i == 7 { 6 * 60 } else { i * 60 };
+                let end_time = DateTime::from_timestamp((START_EPOCH + offset) as i64, 0).unwrap();
+                m["matchId"] = format!("m{}", i).into();
+                m["endTime"] = end_time.format("%Y-%m-%d %H:%M:%S").to_string().into();
+                m
+            })
+            .collect();
+        Backend(matches)
+    }
+}
+
+impl Respond for Backend {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let epoch: u64 = request
+            .url
+            .path()
+            .rsplit('/')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let page: Vec<&Value> = self
+            .0
+            .iter()
+            .filter(|m| {
+                pred_ripper::time::parse_epoch(m["endTime"].as_str().unwrap()).unwrap() >= epoch
+            })
+            .take(PAGE_SIZE)
+            .collect();
+        ResponseTemplate::new(200).set_body_json(page)
+    }
+}
+
+/// Tests are synchronous, since MatchStream can't be used from inside a runtime. The server keeps running on
+/// threads of its own once it's started
+fn mock_server(respond: impl Respond + 'static) -> MockServer {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(respond)
+            .mount(&server)
+            .await;
+        server
+    })
+}
+
+fn stream(server: &MockServer) -> MatchStream {
+    MatchStream::new(build_client(Duration::from_secs(5)).unwrap(), START_EPOCH)
+        .unwrap()
+        .base_url(format!("{}/get-matches-since", server.uri()))
+}
+
+#[test]
+fn yields_every_match_once_in_order_up_to_the_end_epoch() {
+    let server = mock_server(Backend::new(20));
+
+    let ids: Vec<String> = stream(&server)
+        .until(START_EPOCH + 15 * 60)
+        .map(|m| m.unwrap().match_id)
+        .collect();
+
+    let expected: Vec<String> = (0..=15).map(|i| format!("m{}", i)).collect();
+    assert_eq!(ids, expected);
+}
+
+#[test]
+fn a_request_that_fails_for_good_ends_the_stream_with_its_error() {
+    let server = mock_server(ResponseTemplate::new(404));
+
+    let results: Vec<_> = stream(&server).collect();
+
+    assert_eq!(results.len(), 1);
+    assert!(matches!(
+        results[0],
+        Err(RipperError::RetriesExhausted { .. })
+    ));
+}
@@ -0,0 +1,70 @@
+use pred_ripper::{
+    time::{parse_epoch, parse_until},
+    Config, PredecessorMatch, RipperError,
+};
+
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+/// 2023-01-01 00:00:00 UTC
+const NEW_YEAR: u64 = 1672531200;
+
+#[test]
+fn a_date_runs_from_midnight_to_the_end_of_the_day() {
+    assert_eq!(parse_epoch("2023-01-01"), Ok(NEW_YEAR));
+    assert_eq!(parse_until("2023-01-01"), Ok(NEW_YEAR + 86400));
+}
+
+#[test]
+fn a_time_is_taken_as_is() {
+    assert_eq!(parse_epoch("2023-01-01 12:00:00"), Ok(NEW_YEAR + 43200));
+    assert_eq!(parse_until("2023-01-01 12:00:00"), Ok(NEW_YEAR + 43200));
+    assert_eq!(parse_until("1672531200"), Ok(NEW_YEAR));
+}
+
+#[test]
+fn nonsense_is_rejected() {
+    assert!(parse_epoch("yesterday").is_err());
+    assert!(parse_until("2023-13-01").is_err());
+}
+
+#[test]
+fn start_after_end_is_an_error() {
+    let config = Config {
+        start_epoch: parse_epoch("2023-02-01").unwrap(),
+        end_epoch: Some(parse_until("2023-01-01").unwrap()),
+        ..Config::default()
+    };
+    assert!(matches!(
+        config.check(),
+        Err(RipperError::InvalidRange { .. })
+    ));
+
+    let config = Config {
+        start_epoch: parse_epoch("2023-01-01").unwrap(),
+        end_epoch: Some(parse_until("2023-01-01").unwrap()),
+        ..Config::default()
+    };
+    assert!(config.check().is_ok());
+}
+
+#[test]
+fn match_times_are_typed_and_keep_the_wire_format() {
+    let matches: Vec<PredecessorMatch> = serde_json::from_str(FIXTURE).unwrap();
+    assert_eq!(matches[0].start_time.timestamp(), 1680307200);
+    assert_eq!(matches[0].end_time.timestamp(), 1680309000);
+
+    let original: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+    let round_tripped = serde_json::to_value(&matches).unwrap();
+    assert_eq!(round_tripped[0]["startTime"], "2023-04-01 00:00:00");
+    for field in ["startTime", "endTime"] {
+        assert_eq!(round_tripped[1][field], original[1][field]);
+    }
+}
+
+#[test]
+fn bad_match_times_fail_to_parse() {
+    let mut original: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+    original[0]["endTime"] = "half past nine".into();
+
+    assert!(serde_json::from_value::<Vec<PredecessorMatch>>(original).is_err());
+}
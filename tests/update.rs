@@ -0,0 +1,143 @@
+//! --update: carrying on from the newest match already saved
+
+use std::{
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use pred_ripper::{
+    manifest::Manifest,
+    output::{for_each_saved_batch, save_matches},
+    Config, OutputFormat, Partition, PredecessorMatch, Ripper, FIRST_EPOCH,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// 2023-04-01 00:00:00 UTC. The fixture's matches end half an hour and an hour after this
+const START_EPOCH: u64 = 1680307200;
+const FIXTURE: &str = include_str!("fixtures/matches.json");
+
+fn update_config(output_dir: &Path) -> Config {
+    Config {
+        update: true,
+        output_dir: output_dir.to_path_buf(),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn new_windows_start_at_the_newest_saved_match() {
+    let output_dir = TempDir::new().unwrap();
+    // No manifest, so it goes by the file name: the newest match ended at 2023-04-01 01:00:00 UTC
+    std::fs::write(
+        output_dir.path().join("1680309000-1680310800.json"),
+        FIXTURE,
+    )
+    .unwrap();
+    let ripper = Ripper::new(update_config(output_dir.path()));
+
+    assert_eq!(ripper.start_epoch().unwrap(), 1680310800);
+    assert_eq!(ripper.dry_run().unwrap()[0].start_epoch, 1680310800);
+    // The file is left alone
+    assert_eq!(
+        std::fs::read_to_string(output_dir.path().join("1680309000-1680310800.json")).unwrap(),
+        FIXTURE
+    );
+}
+
+#[test]
+fn the_manifest_is_used_when_there_is_one() {
+    let output_dir = TempDir::new().unwrap();
+    let matches = serde_json::from_str::<Vec<PredecessorMatch>>(FIXTURE).unwrap();
+    let saved = save_matches(
+        output_dir.path(),
+        OutputFormat::Json,
+        false,
+        Some(Partition::Date),
+        false,
+        None,
+        &matches,
+    )
+    .unwrap();
+    let mut manifest = Manifest::default();
+    for (entry, _) in saved {
+        manifest.record(entry);
+    }
+    manifest.save(output_dir.path()).unwrap();
+
+    let ripper = Ripper::new(update_config(output_dir.path()));
+
+    assert_eq!(ripper.start_epoch().unwrap(), 1680310800);
+}
+
+#[test]
+fn an_empty_output_folder_starts_from_the_first_epoch() {
+    let output_dir = TempDir::new().unwrap();
+
+    let ripper = Ripper::new(update_config(output_dir.path()));
+
+    assert_eq!(ripper.start_epoch().unwrap(), FIRST_EPOCH);
+}
+
+#[tokio::test]
+async fn an_update_does_not_save_the_newest_match_again() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/get-matches-since/{}", START_EPOCH)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FIXTURE))
+        .mount(&server)
+        .await;
+    // "Since" is inclusive, so asking from the newest match's end time hands it back
+    let fixture: Vec<Value> = serde_json::from_str(FIXTURE).unwrap();
+    Mock::given(method("GET"))
+        .and(path("/get-matches-since/1680310800"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&fixture[1..]))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/get-matches-since/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    let output_dir = TempDir::new().unwrap();
+    let config = |update| Config {
+        update,
+        start_epoch: START_EPOCH,
+        end_epoch: Some(START_EPOCH + 4 * 3600),
+        output_dir: output_dir.path().to_path_buf(),
+        no_zip: true,
+        progress: false,
+        base_url: format!("{}/get-matches-since", server.uri()),
+        ..Config::default()
+    };
+    let run = |config| async {
+        Ripper::new(config)
+            .run_until(Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap()
+    };
+    run(config(false)).await;
+
+    let stats = run(config(true)).await;
+
+    assert!(server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .any(|request| request.url.path() == "/get-matches-since/1680310800"));
+    assert_eq!(stats.total_matches, 0);
+    let mut ids = Vec::new();
+    for_each_saved_batch(output_dir.path(), |matches| {
+        ids.extend(matches.iter().map(|m| m.match_id.clone()));
+        Ok(())
+    })
+    .unwrap();
+    ids.sort();
+    assert_eq!(ids, ["m1", "m2"]);
+}
@@ -0,0 +1,74 @@
+use pred_ripper::{
+    validate::{validate_location, validate_match, MapBounds, ValidationIssue},
+    Config, Location, PredecessorMatch,
+};
+
+fn location(x: f64, y: f64, z: f64) -> Location {
+    Location { x, y, z }
+}
+
+#[test]
+fn locations_inside_the_bounds_are_valid() {
+    let bounds = MapBounds::default();
+    assert!(validate_location(
+        &location(1200.5, -3400.0, 150.0),
+        &bounds
+    ));
+    assert!(validate_location(
+        &location(bounds.max_x, bounds.min_y, 0.0),
+        &bounds
+    ));
+}
+
+#[test]
+fn sentinel_and_far_off_locations_are_invalid() {
+    let bounds = MapBounds::default();
+    assert!(!validate_location(&location(0.0, 0.0, 0.0), &bounds));
+    assert!(!validate_location(&location(1e7, 0.0, 0.0), &bounds));
+    assert!(!validate_location(&location(100.0, 100.0, -1e6), &bounds));
+}
+
+#[test]
+fn bounds_can_be_overridden_in_the_config_file() {
+    let config = Config::parse("[map_bounds]\nmax_x = 100.0\n").unwrap();
+    assert_eq!(config.map_bounds.max_x, 100.0);
+    assert_eq!(config.map_bounds.min_x, MapBounds::default().min_x);
+    assert!(!validate_location(
+        &location(500.0, 0.0, 0.0),
+        &config.map_bounds
+    ));
+}
+
+#[test]
+fn off_map_kills_are_flagged() {
+    let mut matches: Vec<PredecessorMatch> =
+        serde_json::from_str(include_str!("fixtures/matches.json")).unwrap();
+    let mut m = matches.remove(0);
+    assert_eq!(validate_match(&m, &MapBounds::default()), Ok(()));
+
+    m.hero_kills[0].location = location(0.0, 0.0, 0.0);
+    let issues = validate_match(&m, &MapBounds::default()).unwrap_err();
+    assert!(matches!(
+        &issues[..],
+        [ValidationIssue::BadLocation { player_id, .. }] if player_id == "b"
+    ));
+}
+
+#[test]
+fn a_winning_team_with_no_players_is_flagged() {
+    let mut matches: Vec<PredecessorMatch> =
+        serde_json::from_str(include_str!("fixtures/matches.json")).unwrap();
+    let mut m = matches.remove(0);
+    assert_eq!(validate_match(&m, &MapBounds::default()), Ok(()));
+
+    m.winning_team = 7;
+    m.hero_kills[0].killer_player_id = "ghost".to_string();
+    let issues = validate_match(&m, &MapBounds::default()).unwrap_err();
+    assert_eq!(
+        issues,
+        [
+            ValidationIssue::WinningTeamNotPlaying(7),
+            ValidationIssue::UnknownKillerPlayer("ghost".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,71 @@
+use pred_ripper::{generate_work_windows, windows::find_coverage_gaps, Config, Ripper, WorkWindow};
+
+fn window(start_epoch: u64, end_epoch: u64) -> WorkWindow {
+    WorkWindow {
+        start_epoch,
+        end_epoch,
+    }
+}
+
+#[test]
+fn windows_stop_at_the_end_bound() {
+    assert_eq!(
+        generate_work_windows(1000, Some(4500), 1000),
+        [window(1000, 2000), window(2000, 3000), window(3000, 4000)]
+    );
+    assert!(generate_work_windows(1000, Some(1500), 1000).is_empty());
+}
+
+#[test]
+fn newest_first_reverses_the_windows() {
+    let config = Config {
+        start_epoch: 1000,
+        end_epoch: Some(4000),
+        window_size: 1000,
+        ..Config::default()
+    };
+    let oldest_first = Ripper::new(config.clone()).dry_run().unwrap();
+    let newest_first = Ripper::new(Config {
+        newest_first: true,
+        ..config
+    })
+    .dry_run()
+    .unwrap();
+
+    assert_eq!(
+        newest_first,
+        [window(3000, 4000), window(2000, 3000), window(1000, 2000)]
+    );
+    assert_eq!(
+        newest_first.into_iter().rev().collect::<Vec<_>>(),
+        oldest_first
+    );
+}
+
+/// Four windows from 1000 to 5000
+fn gaps(downloaded: &[(u64, u64)]) -> Vec<WorkWindow> {
+    find_coverage_gaps(&generate_work_windows(1000, Some(5000), 1000), downloaded)
+}
+
+#[test]
+fn contiguous_files_leave_no_gaps() {
+    assert!(gaps(&[(1000, 1990), (2010, 2990), (3000, 3500), (4100, 4999)]).is_empty());
+}
+
+#[test]
+fn a_window_with_no_files_is_a_gap() {
+    assert_eq!(
+        gaps(&[(1000, 1990), (3000, 3500), (4100, 4999)]),
+        [window(2000, 3000)]
+    );
+}
+
+#[test]
+fn every_missing_window_is_reported() {
+    // Only part of the first and third windows made it, which still counts as covered
+    assert_eq!(
+        gaps(&[(1500, 1600), (3500, 3600)]),
+        [window(2000, 3000), window(4000, 5000)]
+    );
+    assert_eq!(gaps(&[]).len(), 4);
+}